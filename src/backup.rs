@@ -0,0 +1,460 @@
+//! Snapshots of the files of a world taken before they are written, so an
+//! edit that went wrong can be undone. Every write of level.dat or the
+//! database takes one, the newest [`MAX_BACKUPS`] of a world are kept. Every
+//! snapshot is a folder named after the time it was taken, below a folder of
+//! the world named after its folder and a hash of its full path, as worlds of
+//! different games share names.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::leveldb::DbLock;
+
+/// Number of snapshots kept of each world, the oldest are removed first.
+pub const MAX_BACKUPS: usize = 10;
+
+/// File in a snapshot with the path of the world folder it was taken of.
+const SOURCE_FILE: &str = "world.txt";
+
+/// File of a Bedrock world with the name shown in the game.
+const LEVEL_NAME_FILE: &str = "levelname.txt";
+
+/// A part of a world folder that is backed up as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorldPart {
+    LevelDat,
+    /// The LevelDB folder
+    Db,
+}
+
+impl WorldPart {
+    pub const ALL: [WorldPart; 2] = [WorldPart::LevelDat, WorldPart::Db];
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            WorldPart::LevelDat => "level.dat",
+            WorldPart::Db => "db",
+        }
+    }
+}
+
+impl std::fmt::Display for WorldPart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_name())
+    }
+}
+
+/// A snapshot of some parts of a world.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub dir: PathBuf,
+    /// World folder the snapshot was taken of
+    pub world: PathBuf,
+    /// Name of the world in the game, or its folder name
+    pub name: String,
+    /// When the snapshot was taken, as `YYYY-MM-DD_HH-MM-SS` in UTC
+    pub time: String,
+    pub parts: Vec<WorldPart>,
+}
+
+impl std::fmt::Display for Backup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<&str> = self.parts.iter().map(|v| v.file_name()).collect();
+        let (date, clock) = self.time.split_once('_').unwrap_or((&self.time, ""));
+
+        write!(
+            f,
+            "{}, {date} {} UTC ({})",
+            self.name,
+            clock.replace('-', ":"),
+            parts.join(", ")
+        )
+    }
+}
+
+/// Folder the snapshots are kept in by default, in the platform data directory.
+pub fn default_root() -> Result<PathBuf, String> {
+    match dirs::data_dir() {
+        Some(v) => Ok(v.join("BEditor").join("backups")),
+        None => Err(String::from("No data directory found for backups")),
+    }
+}
+
+/// World folder `path` is the level.dat of, if it is one.
+pub fn world_of(path: &Path) -> Option<PathBuf> {
+    if path.file_name()? != WorldPart::LevelDat.file_name() {
+        return None;
+    }
+
+    let world = path.parent()?;
+
+    match world.join(WorldPart::Db.file_name()).is_dir() {
+        true => Some(world.to_path_buf()),
+        false => None,
+    }
+}
+
+/// Folder in the backup root with the snapshots of `world`.
+fn world_dir(root: &Path, world: &Path) -> Option<PathBuf> {
+    let name = world.file_name()?;
+    let path = fs::canonicalize(world).unwrap_or_else(|_| world.to_path_buf());
+
+    // FNV-1a, which unlike the std hasher stays the same across Rust versions
+    let hash = path
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, v| {
+            (hash ^ u64::from(v)).wrapping_mul(0x100000001b3)
+        });
+
+    Some(root.join(format!("{}-{hash:016x}", name.to_string_lossy())))
+}
+
+/// Date and time as `YYYY-MM-DD_HH-MM-SS` in UTC.
+fn timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |v| v.as_secs());
+    let (days, rest) = (seconds / 86400, seconds % 86400);
+
+    // Howard Hinnant's days to civil date, for days since 1970 in eras of 400 years
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}",
+        rest / 3600,
+        rest / 60 % 60,
+        rest % 60
+    )
+}
+
+/// Orders snapshot folder names, the ones taken in the same second as another
+/// get a number after the time.
+fn order(time: &str) -> (&str, u32) {
+    match time.split_at_checked(19) {
+        Some((time, suffix)) => (time, suffix.trim_start_matches('_').parse().unwrap_or(0)),
+        None => (time, 0),
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+
+        // Held by whoever has the database open, and not part of its content
+        if entry.file_name() == "LOCK" {
+            continue;
+        }
+
+        match entry.file_type()?.is_dir() {
+            true => copy_dir(&entry.path(), &to.join(entry.file_name()))?,
+            false => {
+                fs::copy(entry.path(), to.join(entry.file_name()))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies `part` of the world folder `from` into the folder `to`, replacing
+/// what is there. Parts that don't exist are skipped.
+fn copy_part(from: &Path, to: &Path, part: WorldPart) -> std::io::Result<()> {
+    let source = from.join(part.file_name());
+    let target = to.join(part.file_name());
+
+    if !source.exists() {
+        return Ok(());
+    }
+
+    match part {
+        WorldPart::LevelDat => fs::copy(source, target).map(|_| ()),
+        WorldPart::Db => {
+            // Files the snapshot doesn't have would be read as part of it
+            if target.is_dir() {
+                for entry in fs::read_dir(&target)? {
+                    let entry = entry?;
+
+                    if entry.file_name() == "LOCK" {
+                        continue;
+                    }
+
+                    match entry.file_type()?.is_dir() {
+                        true => fs::remove_dir_all(entry.path())?,
+                        false => fs::remove_file(entry.path())?,
+                    }
+                }
+            }
+
+            copy_dir(&source, &target)
+        }
+    }
+}
+
+fn world_name(world: &Path) -> String {
+    match fs::read_to_string(world.join(LEVEL_NAME_FILE)) {
+        Ok(v) if !v.trim().is_empty() => v.trim().to_string(),
+        _ => world
+            .file_name()
+            .map_or(String::new(), |v| v.to_string_lossy().to_string()),
+    }
+}
+
+/// Reads the snapshot in the folder `dir`.
+fn read_backup(dir: &Path) -> Option<Backup> {
+    let world = PathBuf::from(fs::read_to_string(dir.join(SOURCE_FILE)).ok()?.trim_end());
+
+    Some(Backup {
+        dir: dir.to_path_buf(),
+        name: world_name(&world),
+        world,
+        time: dir.file_name()?.to_string_lossy().to_string(),
+        parts: WorldPart::ALL
+            .into_iter()
+            .filter(|v| dir.join(v.file_name()).exists())
+            .collect(),
+    })
+}
+
+/// Snapshots in the folder `dir` of a world, oldest first.
+fn world_backups(dir: &Path) -> Vec<Backup> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<Backup> = entries
+        .filter_map(|v| v.ok())
+        .filter_map(|v| read_backup(&v.path()))
+        .collect();
+
+    backups.sort_by(|a, b| order(&a.time).cmp(&order(&b.time)));
+
+    backups
+}
+
+/// Every snapshot in `root`, newest first.
+pub fn list(root: &Path) -> Vec<Backup> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<Backup> = entries
+        .filter_map(|v| v.ok())
+        .flat_map(|v| world_backups(&v.path()))
+        .collect();
+
+    backups.sort_by(|a, b| order(&b.time).cmp(&order(&a.time)));
+
+    backups
+}
+
+/// Copies `parts` of `world` into a new snapshot in `root`.
+fn take(root: &Path, world: &Path, parts: &[WorldPart]) -> Result<Backup, String> {
+    let Some(world_dir) = world_dir(root, world) else {
+        return Err(format!(
+            "Error backing up {}: not a folder",
+            world.display()
+        ));
+    };
+
+    let time = timestamp(SystemTime::now());
+
+    // Numbered after the last snapshot of the same second, even if older
+    // ones of that second were removed
+    let last = world_backups(&world_dir)
+        .iter()
+        .map(|v| order(&v.time))
+        .filter(|v| v.0 == time)
+        .map(|v| v.1.max(1))
+        .max();
+
+    let dir = match last {
+        Some(v) => world_dir.join(format!("{time}_{}", v + 1)),
+        None => world_dir.join(&time),
+    };
+
+    let result = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(dir.join(SOURCE_FILE), world.to_string_lossy().as_bytes()))
+        .and_then(|_| parts.iter().try_for_each(|v| copy_part(world, &dir, *v)));
+
+    if let Err(e) = result {
+        // A partial snapshot would restore a broken world
+        let _ = fs::remove_dir_all(&dir);
+        return Err(format!("Error backing up {}: {e:?}", world.display()));
+    }
+
+    read_backup(&dir).ok_or_else(|| format!("Error reading backup {}", dir.display()))
+}
+
+/// Removes the oldest snapshots of `world` beyond [`MAX_BACKUPS`].
+fn prune(root: &Path, world: &Path) {
+    let Some(world_dir) = world_dir(root, world) else {
+        return;
+    };
+
+    let backups = world_backups(&world_dir);
+
+    for v in backups
+        .iter()
+        .take(backups.len().saturating_sub(MAX_BACKUPS))
+    {
+        let _ = fs::remove_dir_all(&v.dir);
+    }
+}
+
+/// Copies `parts` of `world` into a new snapshot in `root`, removing the
+/// oldest snapshots of the world beyond [`MAX_BACKUPS`].
+pub fn snapshot(root: &Path, world: &Path, parts: &[WorldPart]) -> Result<Backup, String> {
+    let backup = take(root, world, parts)?;
+    prune(root, world);
+
+    Ok(backup)
+}
+
+/// Snapshots the world in [`default_root`] whose level.dat is about to be written to `path`.
+pub fn snapshot_level_dat(path: &Path) -> Result<Option<Backup>, String> {
+    let Some(world) = world_of(path) else {
+        return Ok(None);
+    };
+
+    snapshot(&default_root()?, &world, &[WorldPart::LevelDat]).map(Some)
+}
+
+/// Snapshots the world in [`default_root`] whose database in `db` is about to be written.
+pub fn snapshot_db(db: &Path) -> Result<Option<Backup>, String> {
+    let Some(world) = db.parent() else {
+        return Ok(None);
+    };
+
+    snapshot(&default_root()?, world, &[WorldPart::Db]).map(Some)
+}
+
+/// Copies the snapshot back into its world, after taking a snapshot of the
+/// current state so the restore can be undone. Fails while the game or a
+/// server has the world open.
+pub fn restore(root: &Path, backup: &Backup) -> Result<Backup, String> {
+    let db = backup.world.join(WorldPart::Db.file_name());
+
+    let _lock = match db.is_dir() {
+        true => Some(DbLock::acquire(&db)?),
+        false => None,
+    };
+
+    let current = take(root, &backup.world, &backup.parts)?;
+
+    for part in backup.parts.iter() {
+        if let Err(e) = copy_part(&backup.dir, &backup.world, *part) {
+            return Err(format!("Error restoring {part}: {e:?}"));
+        }
+    }
+
+    // Only now, the restored snapshot may be the oldest
+    prune(root, &backup.world);
+
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn timestamps_in_utc() {
+        assert_eq!(timestamp(at(0)), "1970-01-01_00-00-00");
+        assert_eq!(timestamp(at(951_782_400)), "2000-02-29_00-00-00");
+        assert_eq!(timestamp(at(4_107_542_399)), "2100-02-28_23-59-59");
+        assert_eq!(timestamp(at(1_792_195_200)), "2026-10-17_00-00-00");
+    }
+
+    #[test]
+    fn snapshots_of_the_same_second_are_numbered() {
+        let mut times = [
+            "2026-10-17_00-00-00_10",
+            "2026-10-17_00-00-01",
+            "2026-10-17_00-00-00_2",
+            "2026-10-17_00-00-00",
+        ];
+        times.sort_by(|a, b| order(a).cmp(&order(b)));
+
+        assert_eq!(
+            times,
+            [
+                "2026-10-17_00-00-00",
+                "2026-10-17_00-00-00_2",
+                "2026-10-17_00-00-00_10",
+                "2026-10-17_00-00-01"
+            ]
+        );
+    }
+
+    #[test]
+    fn worlds_of_the_same_name_are_kept_apart() {
+        let temp = TempDir::new("backup-names");
+        let root = temp.0.join("backups");
+
+        let worlds = [
+            temp.0.join("a").join("world"),
+            temp.0.join("b").join("world"),
+        ];
+
+        for (i, world) in worlds.iter().enumerate() {
+            fs::create_dir_all(world).unwrap();
+            fs::write(world.join("level.dat"), [i as u8]).unwrap();
+        }
+
+        for _ in 0..=MAX_BACKUPS {
+            snapshot(&root, &worlds[0], &[WorldPart::LevelDat]).unwrap();
+        }
+
+        snapshot(&root, &worlds[1], &[WorldPart::LevelDat]).unwrap();
+
+        let backups = list(&root);
+        let of = |world: &Path| backups.iter().filter(|v| v.world == world).count();
+
+        assert_eq!(of(&worlds[0]), MAX_BACKUPS);
+        assert_eq!(of(&worlds[1]), 1);
+    }
+
+    #[test]
+    fn restore_replaces_db_subfolders() {
+        let temp = TempDir::new("backup-restore");
+        let root = temp.0.join("backups");
+        let world = temp.0.join("world");
+        let db = world.join("db");
+
+        fs::create_dir_all(&db).unwrap();
+        fs::write(db.join("CURRENT"), "old").unwrap();
+
+        let backup = snapshot(&root, &world, &[WorldPart::Db]).unwrap();
+
+        fs::write(db.join("CURRENT"), "new").unwrap();
+        fs::create_dir_all(db.join("lost")).unwrap();
+        fs::write(db.join("lost").join("file"), "new").unwrap();
+
+        restore(&root, &backup).unwrap();
+
+        assert_eq!(fs::read_to_string(db.join("CURRENT")).unwrap(), "old");
+        assert!(!db.join("lost").exists());
+    }
+}
@@ -0,0 +1,462 @@
+//! The command line, a console program separate from the editor.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use beditor::backup;
+use beditor::document::{DocumentError, NbtDocument, NbtEndian, NbtHeader};
+use beditor::json;
+use beditor::nbt_edit;
+use beditor::nbt_path::NbtPath;
+use beditor::nbt_query::NbtPathQuery;
+use beditor::snbt;
+
+const USAGE: &str = "Usage:
+  beditor-cli dump <file> [--format snbt|json] [--compact]
+  beditor-cli get <file> <path> [--format snbt|json]
+  beditor-cli set <file> <path> <value> [--output <file>]
+  beditor-cli convert <input> <output> [--to-endian <endian>] [--to-header <header>]
+
+Options:
+  --endian <endian>    little, little_network or big, detected if left out
+  --header <header>    none, normal or level_dat, detected if left out
+
+Files ending in .json are read and written as JSON exports. Paths look like
+a.b[3].c, with * for any child, [*] for any list element and ** for any number
+of levels in between, like **.Count. Values are SNBT or the plain value of the
+tag being replaced. Converting to a header writes the format version of the
+file, or the StorageVersion of a level.dat.";
+
+/// Exit code for wrong arguments, failures while running return 1.
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, Default)]
+struct Options {
+    args: Vec<String>,
+    endian: Option<NbtEndian>,
+    header: Option<NbtHeader>,
+    to_endian: Option<NbtEndian>,
+    to_header: Option<NbtHeader>,
+    output: Option<String>,
+    json: bool,
+    compact: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| match args.next() {
+                Some(v) => Ok(v.clone()),
+                None => Err(format!("Missing value for {name}")),
+            };
+
+            match arg.as_str() {
+                "--endian" => options.endian = Some(parse_endian(&value(arg)?)?),
+                "--header" => options.header = Some(parse_header(&value(arg)?)?),
+                "--to-endian" => options.to_endian = Some(parse_endian(&value(arg)?)?),
+                "--to-header" => options.to_header = Some(parse_header(&value(arg)?)?),
+                "--output" | "-o" => options.output = Some(value(arg)?),
+                "--format" => match value(arg)?.as_str() {
+                    "snbt" => options.json = false,
+                    "json" => options.json = true,
+                    v => return Err(format!("Unknown format \"{v}\"")),
+                },
+                "--compact" => options.compact = true,
+                v if v.starts_with("--") => return Err(format!("Unknown option \"{v}\"")),
+                v => options.args.push(v.to_string()),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// The positional arguments, if there are exactly `N` of them.
+    fn positional<const N: usize>(&self) -> Result<[&str; N], String> {
+        if self.args.len() != N {
+            return Err(format!(
+                "Expected {N} arguments but got {}",
+                self.args.len()
+            ));
+        }
+
+        Ok(std::array::from_fn(|i| self.args[i].as_str()))
+    }
+}
+
+fn parse_endian(id: &str) -> Result<NbtEndian, String> {
+    match NbtEndian::from_id(id) {
+        Some(v) => Ok(v),
+        None => Err(format!("Unknown endian \"{id}\"")),
+    }
+}
+
+fn parse_header(id: &str) -> Result<NbtHeader, String> {
+    match NbtHeader::from_id(id) {
+        Some(v) => Ok(v),
+        None => Err(format!("Unknown header \"{id}\"")),
+    }
+}
+
+fn is_json(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|v| v == "json")
+}
+
+/// Reads a binary Nbt file, or a JSON export. The endian and header that
+/// weren't passed are detected like in the editor.
+fn load(path: &str, options: &Options) -> Result<NbtDocument, String> {
+    if is_json(path) {
+        let text = match fs::read_to_string(path) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("Error reading File: {e:?}")),
+        };
+
+        return json::from_json(&text).map_err(|e| e.to_string());
+    }
+
+    let data = match fs::read(path) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Error reading File: {e:?}")),
+    };
+
+    let file_name = Path::new(path).file_name().and_then(|v| v.to_str());
+
+    match NbtDocument::parse_detect(data, options.endian, options.header, file_name) {
+        Ok(v) => Ok(v),
+        Err(DocumentError::UnknownFormat) => Err(format!(
+            "Could not detect the format of {path}, pass --endian and --header"
+        )),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn write(path: &str, document: &NbtDocument) -> Result<(), String> {
+    backup::snapshot_level_dat(Path::new(path))?;
+
+    let data = match is_json(path) {
+        true => json::to_json(document).into_bytes(),
+        false => document.to_bytes().map_err(|e| e.to_string())?,
+    };
+
+    match fs::write(path, data) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Error writing File: {e:?}")),
+    }
+}
+
+/// What `dump` prints of `document`.
+fn dump_text(document: &NbtDocument, options: &Options) -> Result<String, String> {
+    match options.json {
+        true => Ok(json::to_json(document)),
+        false => snbt::to_snbt(&document.tag, !options.compact),
+    }
+}
+
+fn dump(options: &Options) -> Result<(), String> {
+    let [file] = options.positional()?;
+    let document = load(file, options)?;
+
+    println!("{}", dump_text(&document, options)?);
+
+    Ok(())
+}
+
+/// Paths of the tags `query` matches in `document`, failing if there are none.
+fn find(document: &NbtDocument, query: &NbtPathQuery, text: &str) -> Result<Vec<NbtPath>, String> {
+    let paths = query.matches(&document.tag);
+
+    match paths.is_empty() {
+        true => Err(format!("No tag at {text}")),
+        false => Ok(paths),
+    }
+}
+
+/// What `get` prints of the tags `text` matches. A single path prints the
+/// bare tag, wildcards print every match with its path, as a JSON object
+/// with `--format json`.
+fn get_text(document: &NbtDocument, text: &str, options: &Options) -> Result<String, String> {
+    let query = NbtPathQuery::parse(text)?;
+    let paths = find(document, &query, text)?;
+
+    let tags = paths
+        .iter()
+        .filter_map(|v| v.get(&document.tag).map(|tag| (v, tag)));
+
+    let lines = match (options.json, query.as_path().is_some()) {
+        (true, true) => tags
+            .map(|(_, tag)| json::tag_to_json(tag).to_string())
+            .collect(),
+        (false, true) => tags
+            .map(|(_, tag)| snbt::to_snbt(tag, !options.compact))
+            .collect::<Result<Vec<_>, _>>()?,
+        (true, false) => {
+            let map = tags
+                .map(|(path, tag)| (path.to_string(), json::tag_to_json(tag)))
+                .collect::<serde_json::Map<_, _>>();
+
+            vec![serde_json::Value::Object(map).to_string()]
+        }
+        (false, false) => tags
+            .map(|(path, tag)| {
+                Ok(format!(
+                    "{path} = {}",
+                    snbt::to_snbt(tag, !options.compact)?
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+    };
+
+    Ok(lines.join("\n"))
+}
+
+fn get(options: &Options) -> Result<(), String> {
+    let [file, text] = options.positional()?;
+    let document = load(file, options)?;
+
+    println!("{}", get_text(&document, text, options)?);
+
+    Ok(())
+}
+
+/// Replaces every tag `text` matches with `value`, failing without a change
+/// to the file if one of them can't be. Matches inside other matches are
+/// refused, replacing the outer tag would change or remove them.
+fn set_tags(document: &mut NbtDocument, text: &str, value: &str) -> Result<(), String> {
+    let query = NbtPathQuery::parse(text)?;
+    let paths = find(document, &query, text)?;
+
+    let matched: HashSet<&NbtPath> = paths.iter().collect();
+
+    for path in paths.iter() {
+        let mut parent = path.clone();
+
+        while parent.pop().is_some() {
+            if matched.contains(&parent) {
+                return Err(format!(
+                    "{text} matches {path} inside {parent}, narrow it down to one of them"
+                ));
+            }
+        }
+    }
+
+    for path in paths {
+        let Some(old) = path.get(&document.tag) else {
+            return Err(format!("No tag at {path}"));
+        };
+
+        // Plain values keep the type of the tag they replace, "1" stays a Byte
+        let new = match nbt_edit::parse_scalar(old, value) {
+            Ok(v) => v,
+            Err(scalar_error) => match snbt::parse_snbt(value) {
+                Ok(v) => v,
+                Err(_) if nbt_edit::scalar_to_string(old).is_some() => {
+                    return Err(format!("{path}: {scalar_error}"))
+                }
+                Err(e) => return Err(format!("Invalid SNBT at {e}")),
+            },
+        };
+
+        nbt_edit::check_replace(&document.tag, &path, &new).map_err(|e| format!("{path}: {e}"))?;
+
+        if let Some(v) = path.get_mut(&mut document.tag) {
+            *v = new;
+        }
+    }
+
+    Ok(())
+}
+
+fn set(options: &Options) -> Result<(), String> {
+    let [file, text, value] = options.positional()?;
+    let mut document = load(file, options)?;
+
+    set_tags(&mut document, text, value)?;
+
+    write(options.output.as_deref().unwrap_or(file), &document)
+}
+
+/// Switches `document` to the endian and header given with `--to-endian` and `--to-header`.
+fn convert_document(document: &mut NbtDocument, options: &Options) {
+    if let Some(v) = options.to_endian {
+        document.endian = v;
+    }
+
+    if let Some(v) = options.to_header {
+        document.header = v;
+    }
+}
+
+fn convert(options: &Options) -> Result<(), String> {
+    let [input, output] = options.positional()?;
+    let mut document = load(input, options)?;
+
+    convert_document(&mut document, options);
+
+    write(output, &document)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(run(&args));
+}
+
+/// Runs the command in `args`, the arguments after the program name.
+/// Returns the exit code.
+fn run(args: &[String]) -> i32 {
+    let Some((command, args)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return EXIT_USAGE;
+    };
+
+    let options = match Options::parse(args) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return EXIT_USAGE;
+        }
+    };
+
+    let result = match command.as_str() {
+        "dump" => dump(&options),
+        "get" => get(&options),
+        "set" => set(&options),
+        "convert" => convert(&options),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            return 0;
+        }
+        v => {
+            eprintln!("Unknown command \"{v}\"\n\n{USAGE}");
+            return EXIT_USAGE;
+        }
+    };
+
+    match result {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use beditor::nbt_decode::NbtLayout;
+    use bedrock_rs::nbt::NbtTag;
+
+    use super::*;
+
+    fn options(args: &[&str]) -> Options {
+        let args: Vec<String> = args.iter().map(|v| v.to_string()).collect();
+        Options::parse(&args).unwrap()
+    }
+
+    fn item(count: i8) -> NbtTag {
+        NbtTag::Compound(HashMap::from([(
+            String::from("Count"),
+            NbtTag::Byte(count as u8),
+        )]))
+    }
+
+    /// `{Name: "abc", Items: [{Count: 1b}, {Count: 2b}]}`
+    fn document() -> NbtDocument {
+        NbtDocument {
+            name: String::new(),
+            tag: NbtTag::Compound(HashMap::from([
+                (String::from("Name"), NbtTag::String(String::from("abc"))),
+                (String::from("Items"), NbtTag::List(vec![item(1), item(2)])),
+            ])),
+            endian: NbtEndian::Little,
+            header: NbtHeader::None,
+            header_values: None,
+            layout: NbtLayout::default(),
+        }
+    }
+
+    #[test]
+    fn dump_writes_snbt_or_json() {
+        let document = document();
+
+        assert_eq!(
+            dump_text(&document, &options(&["--compact"])).unwrap(),
+            snbt::to_snbt(&document.tag, false).unwrap()
+        );
+
+        let text = dump_text(&document, &options(&["--format", "json"])).unwrap();
+        assert_eq!(json::from_json(&text).unwrap().tag, document.tag);
+    }
+
+    #[test]
+    fn get_prints_bare_tags_or_matches_with_paths() {
+        let document = document();
+        let compact = options(&["--compact"]);
+
+        assert_eq!(
+            get_text(&document, "Items[1].Count", &compact).unwrap(),
+            "2b"
+        );
+        assert_eq!(
+            get_text(&document, "**.Count", &compact).unwrap(),
+            "Items[0].Count = 1b\nItems[1].Count = 2b"
+        );
+        assert_eq!(
+            get_text(&document, "Name", &options(&["--format", "json"])).unwrap(),
+            r#"{"type":"string","value":"abc"}"#
+        );
+        assert!(get_text(&document, "Missing", &compact).is_err());
+    }
+
+    #[test]
+    fn set_keeps_the_type_of_plain_values() {
+        let mut document = document();
+
+        set_tags(&mut document, "**.Count", "5").unwrap();
+        assert_eq!(
+            get_text(&document, "**.Count", &options(&["--compact"])).unwrap(),
+            "Items[0].Count = 5b\nItems[1].Count = 5b"
+        );
+
+        set_tags(&mut document, "Items[0]", "{Count: 7b}").unwrap();
+        assert_eq!(
+            NbtPath::root().key("Items").index(0).get(&document.tag),
+            Some(&item(7))
+        );
+
+        let e = set_tags(&mut document, "Items[1].Count", "1 2").unwrap_err();
+        assert!(e.starts_with("Items[1].Count: "), "{e}");
+        assert!(set_tags(&mut document, "Items[0]", "{Count: 7b").is_err());
+    }
+
+    #[test]
+    fn set_refuses_matches_inside_matches() {
+        let mut document = document();
+        let tag = document.tag.clone();
+
+        let e = set_tags(&mut document, "Items.**", "{}").unwrap_err();
+        assert!(e.contains("inside"), "{e}");
+        assert_eq!(document.tag, tag);
+    }
+
+    #[test]
+    fn convert_switches_endian_and_header() {
+        let mut document = document();
+        let tag = document.tag.clone();
+
+        convert_document(
+            &mut document,
+            &options(&["--to-endian", "big", "--to-header", "level_dat"]),
+        );
+
+        let data = document.to_bytes().unwrap();
+        let converted = NbtDocument::parse(data, NbtEndian::Big, NbtHeader::LevelDat).unwrap();
+
+        assert_eq!(converted.tag, tag);
+        assert_eq!(converted.header_values.map(|v| v.0), Some(10));
+    }
+}
@@ -0,0 +1,120 @@
+use std::fs;
+use std::path::PathBuf;
+
+/// Number of recently opened entries that are remembered.
+pub const MAX_RECENT: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecentKind {
+    Nbt,
+    World,
+    /// A resource or behavior pack folder
+    Pack,
+}
+
+impl RecentKind {
+    fn id(&self) -> &'static str {
+        match self {
+            RecentKind::Nbt => "nbt",
+            RecentKind::World => "world",
+            RecentKind::Pack => "pack",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "nbt" => Some(RecentKind::Nbt),
+            "world" => Some(RecentKind::World),
+            "pack" => Some(RecentKind::Pack),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for RecentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RecentKind::Nbt => "Nbt",
+                RecentKind::World => "World",
+                RecentKind::Pack => "Pack",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recent {
+    pub kind: RecentKind,
+    pub path: PathBuf,
+}
+
+/// Settings kept between runs, stored as `kind<TAB>path` lines in the
+/// platform config directory.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Most recent first
+    pub recent: Vec<Recent>,
+}
+
+impl Config {
+    fn file() -> Option<PathBuf> {
+        dirs::config_dir().map(|v| v.join("BEditor").join("recent.txt"))
+    }
+
+    /// Loads the config, falling back to an empty one if there is none or it is unreadable.
+    pub fn load() -> Self {
+        let Some(Ok(text)) = Self::file().map(fs::read_to_string) else {
+            return Self::default();
+        };
+
+        let recent = text
+            .lines()
+            .filter_map(|line| {
+                let (kind, path) = line.split_once('\t')?;
+
+                Some(Recent {
+                    kind: RecentKind::from_id(kind)?,
+                    path: PathBuf::from(path),
+                })
+            })
+            .take(MAX_RECENT)
+            .collect();
+
+        Self { recent }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let Some(file) = Self::file() else {
+            return Err(String::from("No config directory found"));
+        };
+
+        if let Some(dir) = file.parent() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                return Err(format!("Error creating config directory: {e:?}"));
+            }
+        }
+
+        let text: String = self
+            .recent
+            .iter()
+            .map(|v| format!("{}\t{}\n", v.kind.id(), v.path.display()))
+            .collect();
+
+        match fs::write(file, text) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Error writing config: {e:?}")),
+        }
+    }
+
+    /// Moves `path` to the top of the recently opened entries.
+    pub fn add_recent(&mut self, kind: RecentKind, path: PathBuf) {
+        let recent = Recent { kind, path };
+
+        self.recent.retain(|v| v != &recent);
+        self.recent.insert(0, recent);
+        self.recent.truncate(MAX_RECENT);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::document::{NbtEndian, NbtHeader};
+use crate::nbt_decode::{NbtDecoder, TAG_COMPOUND, TAG_LIST};
+
+/// Most likely encoding of a file as found by [`detect`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    pub endian: NbtEndian,
+    pub header: NbtHeader,
+    /// Between 0 and 1, lower when the data is damaged or several formats fit
+    pub confidence: f32,
+}
+
+/// Score of one endian and header combination, the more checks pass the higher it is.
+/// Returned as a fraction of the highest score the header can reach.
+fn score(data: &[u8], endian: NbtEndian, header: NbtHeader, level_dat_name: bool) -> f32 {
+    let max = match header {
+        NbtHeader::None => 70.0,
+        NbtHeader::Normal | NbtHeader::LevelDat => 105.0,
+    };
+
+    score_points(data, endian, header, level_dat_name) as f32 / max
+}
+
+fn score_points(data: &[u8], endian: NbtEndian, header: NbtHeader, level_dat_name: bool) -> u32 {
+    let mut score = 0;
+
+    let body = match header {
+        NbtHeader::None => data,
+        NbtHeader::Normal | NbtHeader::LevelDat => {
+            let Some(body) = data.get(8..) else {
+                return 0;
+            };
+
+            let mut length = [0; 4];
+            length.copy_from_slice(&data[4..8]);
+
+            // The second field holds the length of the following Nbt
+            if i32::from_le_bytes(length) as i64 == body.len() as i64 {
+                score += 30;
+            } else {
+                return 0;
+            }
+
+            match (header, level_dat_name) {
+                (NbtHeader::LevelDat, true) | (NbtHeader::Normal, false) => score += 5,
+                _ => {}
+            }
+
+            body
+        }
+    };
+
+    let mut decoder = NbtDecoder::new(body, endian);
+
+    // Files start with a compound, rarely a list
+    match decoder.read_u8() {
+        Ok(TAG_COMPOUND) => score += 10,
+        Ok(TAG_LIST) => score += 5,
+        _ => return score,
+    }
+
+    // The root name has to fit into the data and be valid UTF-8, for
+    // LittleNetwork its length also has to be a valid varint
+    match decoder.read_string() {
+        Ok(_) => score += 10,
+        Err(_) => return score,
+    }
+
+    let mut decoder = NbtDecoder::new(body, endian);
+
+    match decoder.read_root() {
+        Ok(_) if decoder.position() == body.len() => score + 50,
+        Ok(_) => score + 30,
+        Err(_) => score,
+    }
+}
+
+/// Probes `data` with every endian and header and returns the combination
+/// that explains it best. `file_name` helps to tell level.dat headers apart
+/// from normal ones, they only differ in meaning.
+pub fn detect(data: &[u8], file_name: Option<&str>) -> Option<Detection> {
+    let level_dat_name = file_name.is_some_and(|v| v.starts_with("level.dat"));
+
+    let mut scores = Vec::new();
+
+    for header in NbtHeader::ALL {
+        for endian in NbtEndian::ALL {
+            scores.push((score(data, endian, header, level_dat_name), endian, header));
+        }
+    }
+
+    // Stable, so the first of equal scores wins
+    scores.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let (best, endian, header) = scores[0];
+    let second = scores[1].0;
+
+    if best == 0.0 {
+        return None;
+    }
+
+    let mut confidence = best;
+
+    if best == second {
+        confidence /= 2.0;
+    }
+
+    Some(Detection {
+        endian,
+        header,
+        confidence: confidence.min(1.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bedrock_rs::nbt::NbtTag;
+
+    use super::*;
+    use crate::nbt_decode::NbtLayout;
+    use crate::nbt_encode;
+
+    fn nbt(endian: NbtEndian) -> Vec<u8> {
+        let tag = NbtTag::Compound(HashMap::from([
+            (
+                String::from("LevelName"),
+                NbtTag::String(String::from("abc")),
+            ),
+            (String::from("Time"), NbtTag::Int32(-65)),
+        ]));
+
+        nbt_encode::encode_root("", &tag, endian, &NbtLayout::default()).unwrap()
+    }
+
+    #[test]
+    fn level_dat_header() {
+        let body = nbt(NbtEndian::Little);
+
+        let mut data = 10i32.to_le_bytes().to_vec();
+        data.extend_from_slice(&(body.len() as i32).to_le_bytes());
+        data.extend_from_slice(&body);
+
+        let detection = detect(&data, Some("level.dat")).unwrap();
+
+        assert_eq!(detection.endian, NbtEndian::Little);
+        assert_eq!(detection.header, NbtHeader::LevelDat);
+        assert_eq!(detection.confidence, 1.0);
+
+        // The same file under another name has a normal header
+        let detection = detect(&data, Some("entity.nbt")).unwrap();
+        assert_eq!(detection.header, NbtHeader::Normal);
+    }
+
+    #[test]
+    fn little_network_varints() {
+        let detection = detect(&nbt(NbtEndian::LittleNetwork), None).unwrap();
+
+        assert_eq!(detection.endian, NbtEndian::LittleNetwork);
+        assert_eq!(detection.header, NbtHeader::None);
+        assert_eq!(detection.confidence, 1.0);
+    }
+
+    #[test]
+    fn big_endian() {
+        let detection = detect(&nbt(NbtEndian::Big), None).unwrap();
+
+        assert_eq!(detection.endian, NbtEndian::Big);
+        assert_eq!(detection.header, NbtHeader::None);
+        assert_eq!(detection.confidence, 1.0);
+    }
+
+    #[test]
+    fn tie_halves_the_confidence() {
+        // An empty compound with an empty name reads the same in both fixed width encodings
+        let data = [TAG_COMPOUND, 0, 0, 0];
+
+        let detection = detect(&data, None).unwrap();
+
+        assert_eq!(detection.endian, NbtEndian::Little);
+        assert_eq!(detection.header, NbtHeader::None);
+        assert_eq!(detection.confidence, 0.5);
+    }
+
+    #[test]
+    fn nothing_fits() {
+        assert_eq!(detect(&[0xff, 1, 2], None), None);
+        assert_eq!(detect(&[], None), None);
+    }
+}
@@ -0,0 +1,227 @@
+use beditor::document::NbtDocument;
+use beditor::history::NbtCommand;
+use beditor::nbt_diff;
+use beditor::nbt_diff::{DiffKind, NbtDiff};
+use beditor::nbt_path::NbtPath;
+use beditor::snbt;
+use bedrock_rs::nbt::NbtTag;
+use iced::widget::{Button, Column, Row, Scrollable, Text};
+use iced::{Alignment, Color, Command, Element, Length};
+
+use crate::messages::BEditorMessage;
+use crate::nbt_input::NbtInput;
+use crate::view::BEditorView;
+
+const ADDED_COLOR: Color = Color::from_rgb(0.2, 0.7, 0.3);
+const REMOVED_COLOR: Color = Color::from_rgb(0.8, 0.2, 0.2);
+const CHANGED_COLOR: Color = Color::from_rgb(0.8, 0.6, 0.0);
+const TYPE_CHANGED_COLOR: Color = Color::from_rgb(0.9, 0.4, 0.1);
+/// Longest value shown in the list of differences, in characters
+const SUMMARY_LENGTH: usize = 48;
+const KIND_WIDTH: f32 = 120.0;
+
+/// Which of the two compared documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffSide {
+    Left,
+    Right,
+}
+
+/// Compares two Nbt files and copies values between them.
+pub struct DiffView {
+    left: NbtInput,
+    right: NbtInput,
+    diffs: Vec<NbtDiff>,
+}
+
+impl DiffView {
+    fn side(&mut self, side: DiffSide) -> &mut NbtInput {
+        match side {
+            DiffSide::Left => &mut self.left,
+            DiffSide::Right => &mut self.right,
+        }
+    }
+
+    /// Compares the documents again after one of them changed.
+    fn rediff(&mut self) {
+        self.diffs = match (self.left.tag(), self.right.tag()) {
+            (Some(l), Some(r)) => nbt_diff::diff(l, r),
+            _ => Vec::new(),
+        };
+    }
+
+    /// Makes the tag at `path` on the `to` side the same as on the other side,
+    /// adding or removing it if only one side has it.
+    fn copy(&mut self, path: NbtPath, to: DiffSide) {
+        let (from, target) = match to {
+            DiffSide::Left => (&self.right, &mut self.left),
+            DiffSide::Right => (&self.left, &mut self.right),
+        };
+
+        let Ok(NbtDocument {
+            tag: root, layout, ..
+        }) = &mut target.nbt
+        else {
+            return;
+        };
+
+        let source = from.tag().and_then(|v| path.get(v)).cloned();
+
+        let command = match (source, path.get(root)) {
+            (Some(new), Some(old)) => NbtCommand::SetValue {
+                path,
+                old: old.clone(),
+                new,
+            },
+            (Some(tag), None) => NbtCommand::Insert { path, tag },
+            (None, Some(tag)) => NbtCommand::Remove {
+                path,
+                tag: tag.clone(),
+            },
+            (None, None) => return,
+        };
+
+        target.status = target.history.apply(root, layout, command).err().map(Err);
+
+        self.rediff();
+    }
+
+    fn diff2element(&self, diff: &NbtDiff) -> Element<'_, BEditorMessage> {
+        let color = match diff.kind {
+            DiffKind::Added => ADDED_COLOR,
+            DiffKind::Removed => REMOVED_COLOR,
+            DiffKind::Changed => CHANGED_COLOR,
+            DiffKind::TypeChanged => TYPE_CHANGED_COLOR,
+        };
+
+        let left = self.left.tag().and_then(|v| diff.path.get(v));
+        let right = self.right.tag().and_then(|v| diff.path.get(v));
+
+        Row::new()
+            .push(
+                Text::new(diff.kind.to_string())
+                    .style(color)
+                    .width(Length::Fixed(KIND_WIDTH)),
+            )
+            .push(Text::new(diff.path.to_string()).width(Length::Fill))
+            .push(Text::new(summary(left)).width(Length::Fill))
+            .push(
+                Button::new(Text::new("->")).on_press(BEditorMessage::DiffViewCopy(
+                    diff.path.clone(),
+                    DiffSide::Right,
+                )),
+            )
+            .push(
+                Button::new(Text::new("<-")).on_press(BEditorMessage::DiffViewCopy(
+                    diff.path.clone(),
+                    DiffSide::Left,
+                )),
+            )
+            .push(Text::new(summary(right)).width(Length::Fill))
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into()
+    }
+}
+
+/// Short text for a tag in the list of differences.
+pub fn summary(tag: Option<&NbtTag>) -> String {
+    let text = match tag {
+        None => return String::from("-"),
+        Some(NbtTag::Compound(v)) => return format!("{{ {} entries }}", v.len()),
+        Some(NbtTag::List(v)) => return format!("[ {} entries ]", v.len()),
+        Some(v) => snbt::to_snbt(v, false).unwrap_or_else(|_| format!("{v:?}")),
+    };
+
+    match text.chars().count() > SUMMARY_LENGTH {
+        true => format!(
+            "{}...",
+            text.chars().take(SUMMARY_LENGTH).collect::<String>()
+        ),
+        false => text,
+    }
+}
+
+impl BEditorView for DiffView {
+    fn new() -> Self {
+        Self {
+            left: NbtInput::new(),
+            right: NbtInput::new(),
+            diffs: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, message: BEditorMessage) -> Command<BEditorMessage> {
+        match message {
+            BEditorMessage::DiffViewInput(side, message) => {
+                let (loaded, command) = self
+                    .side(side)
+                    .update(message, move |v| BEditorMessage::DiffViewInput(side, v));
+
+                if loaded {
+                    self.rediff();
+                }
+
+                return command;
+            }
+            BEditorMessage::DiffViewCopy(path, to) => self.copy(path, to),
+            // Handled by the app or other views
+            _ => {}
+        }
+
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, BEditorMessage> {
+        let mut diffs = Column::new();
+
+        for diff in self.diffs.iter() {
+            diffs = diffs.push(self.diff2element(diff));
+        }
+
+        let count = |kind: DiffKind| self.diffs.iter().filter(|v| v.kind == kind).count();
+
+        let summary = match (self.left.tag(), self.right.tag()) {
+            (Some(_), Some(_)) if self.diffs.is_empty() => String::from("The files are the same"),
+            (Some(_), Some(_)) => format!(
+                "{} added, {} removed, {} changed, {} type changed",
+                count(DiffKind::Added),
+                count(DiffKind::Removed),
+                count(DiffKind::Changed),
+                count(DiffKind::TypeChanged)
+            ),
+            _ => String::from("Open two files to compare them"),
+        };
+
+        Column::new()
+            .push(
+                Row::new()
+                    .push(self.left.view("Left", true, |v| {
+                        BEditorMessage::DiffViewInput(DiffSide::Left, v)
+                    }))
+                    .push(self.right.view("Right", true, |v| {
+                        BEditorMessage::DiffViewInput(DiffSide::Right, v)
+                    }))
+                    .spacing(16),
+            )
+            .push(Text::new(summary))
+            .push(
+                Scrollable::new(diffs)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .width(Length::Fill)
+            .into()
+    }
+
+    fn title(&self) -> String {
+        match (self.left.tag(), self.right.tag()) {
+            (Some(_), Some(_)) => format!("{} / {}", self.left.file_name(), self.right.file_name()),
+            _ => String::from("Compare"),
+        }
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.left.is_dirty() || self.right.is_dirty()
+    }
+}
@@ -0,0 +1,423 @@
+use std::fs;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Write};
+use std::path::Path;
+
+use bedrock_rs::nbt::NbtTag;
+
+use crate::detect;
+use crate::nbt_decode::{DecodeError, NbtDecoder, NbtLayout};
+use crate::nbt_encode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NbtEndian {
+    #[default]
+    Little,
+    LittleNetwork,
+    Big,
+}
+
+impl NbtEndian {
+    pub const ALL: [NbtEndian; 3] = [NbtEndian::Little, NbtEndian::LittleNetwork, NbtEndian::Big];
+
+    /// Name used in exported files and on the command line.
+    pub fn id(&self) -> &'static str {
+        match self {
+            NbtEndian::Little => "little",
+            NbtEndian::LittleNetwork => "little_network",
+            NbtEndian::Big => "big",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|v| v.id() == id)
+    }
+}
+
+impl std::fmt::Display for NbtEndian {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                NbtEndian::Little => "Little Endian",
+                NbtEndian::LittleNetwork => "Little Endian Network",
+                NbtEndian::Big => "Big Endian",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NbtHeader {
+    #[default]
+    None,
+    Normal,
+    LevelDat,
+}
+
+impl NbtHeader {
+    pub const ALL: [NbtHeader; 3] = [NbtHeader::None, NbtHeader::Normal, NbtHeader::LevelDat];
+
+    /// Number of bytes the header takes up before the Nbt.
+    pub fn size(&self) -> usize {
+        match self {
+            NbtHeader::None => 0,
+            NbtHeader::Normal | NbtHeader::LevelDat => 8,
+        }
+    }
+
+    /// Name used in exported files and on the command line.
+    pub fn id(&self) -> &'static str {
+        match self {
+            NbtHeader::None => "none",
+            NbtHeader::Normal => "normal",
+            NbtHeader::LevelDat => "level_dat",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|v| v.id() == id)
+    }
+}
+
+impl std::fmt::Display for NbtHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                NbtHeader::None => "No Header",
+                NbtHeader::Normal => "Normal Header",
+                NbtHeader::LevelDat => "Level.dat Header",
+            }
+        )
+    }
+}
+
+/// Format version written into headers of files that didn't have one,
+/// the `StorageVersion` of level.dat files of current games.
+pub const DEFAULT_FORMAT_VERSION: i32 = 10;
+
+/// Why a document couldn't be read or written.
+#[derive(Debug)]
+pub enum DocumentError {
+    Read(std::io::Error),
+    Write(std::io::Error),
+    /// The file is shorter than its header
+    Header(String),
+    /// Where decoding failed, with the tree read up to there
+    Decode(Box<DecodeError>),
+    Serialize(String),
+    /// The Nbt doesn't fit into the length field of the header
+    TooLarge(usize),
+    Json(String),
+    /// None of the endian and header combinations fit the data
+    UnknownFormat,
+}
+
+impl std::fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentError::Read(e) => write!(f, "Error reading File: {e:?}"),
+            DocumentError::Write(e) => write!(f, "Error writing File: {e:?}"),
+            DocumentError::Header(e) => write!(f, "Error reading Nbt header: {e}"),
+            DocumentError::Decode(e) => write!(f, "Error parsing Nbt: {e}"),
+            DocumentError::Serialize(e) => write!(f, "Error serializing Nbt: {e}"),
+            DocumentError::TooLarge(v) => {
+                write!(f, "Error writing Nbt header: Nbt too large ({v} bytes)")
+            }
+            DocumentError::Json(e) => write!(f, "Error importing JSON: {e}"),
+            DocumentError::UnknownFormat => write!(f, "Could not detect the Nbt format"),
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+/// A parsed Nbt file together with everything needed to write it back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NbtDocument {
+    /// Name of the root tag
+    pub name: String,
+    pub tag: NbtTag,
+    pub endian: NbtEndian,
+    pub header: NbtHeader,
+    /// The two header fields as they were read, `None` without a header
+    pub header_values: Option<(i32, i32)>,
+    /// Key order and empty list types of the file, kept when it is written
+    pub layout: NbtLayout,
+}
+
+impl NbtDocument {
+    /// Parses `data` in the given encoding.
+    pub fn parse(
+        data: Vec<u8>,
+        endian: NbtEndian,
+        header: NbtHeader,
+    ) -> Result<Self, DocumentError> {
+        let start = header.size();
+
+        let header_values = match header {
+            NbtHeader::None => None,
+            NbtHeader::Normal | NbtHeader::LevelDat => {
+                if data.len() < start {
+                    return Err(DocumentError::Header(format!(
+                        "the file has only {} bytes",
+                        data.len()
+                    )));
+                }
+
+                let mut first = [0; 4];
+                let mut second = [0; 4];
+                first.copy_from_slice(&data[0..4]);
+                second.copy_from_slice(&data[4..8]);
+
+                Some((i32::from_le_bytes(first), i32::from_le_bytes(second)))
+            }
+        };
+
+        let mut decoder = NbtDecoder::new(&data[start..], endian).with_layout();
+
+        match decoder.read_root() {
+            Ok((name, tag)) => Ok(Self {
+                name,
+                tag,
+                endian,
+                header,
+                header_values,
+                layout: decoder.into_layout(),
+            }),
+            Err(mut e) => {
+                e.shift(start);
+                Err(DocumentError::Decode(e))
+            }
+        }
+    }
+
+    /// Parses `data`, detecting the endian and header that aren't given.
+    /// `file_name` helps telling level.dat headers apart.
+    pub fn parse_detect(
+        data: Vec<u8>,
+        endian: Option<NbtEndian>,
+        header: Option<NbtHeader>,
+        file_name: Option<&str>,
+    ) -> Result<Self, DocumentError> {
+        let (endian, header) = match (endian, header) {
+            (Some(endian), Some(header)) => (endian, header),
+            (endian, header) => match detect::detect(&data, file_name) {
+                Some(v) => (endian.unwrap_or(v.endian), header.unwrap_or(v.header)),
+                None => return Err(DocumentError::UnknownFormat),
+            },
+        };
+
+        Self::parse(data, endian, header)
+    }
+
+    pub fn open(path: &Path, endian: NbtEndian, header: NbtHeader) -> Result<Self, DocumentError> {
+        match fs::read(path) {
+            Ok(v) => Self::parse(v, endian, header),
+            Err(e) => Err(DocumentError::Read(e)),
+        }
+    }
+
+    /// First header field for a file that was read without a header, the
+    /// `StorageVersion` of a level.dat or the one current games write.
+    fn default_format_version(&self) -> i32 {
+        match &self.tag {
+            NbtTag::Compound(v) => match v.get("StorageVersion") {
+                Some(NbtTag::Int32(v)) => *v,
+                _ => DEFAULT_FORMAT_VERSION,
+            },
+            _ => DEFAULT_FORMAT_VERSION,
+        }
+    }
+
+    /// Writes the document in its encoding, in the key order it was read in.
+    /// The first header field (the format version for level.dat) is kept, or
+    /// made up if no header was read, the length field is recomputed.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, DocumentError> {
+        let nbt = nbt_encode::encode_root(&self.name, &self.tag, self.endian, &self.layout)
+            .map_err(DocumentError::Serialize)?;
+
+        let mut data = Vec::with_capacity(nbt.len() + 8);
+
+        match self.header {
+            NbtHeader::None => {}
+            NbtHeader::Normal | NbtHeader::LevelDat => {
+                let first = match self.header_values {
+                    Some((v, _)) => v,
+                    None => self.default_format_version(),
+                };
+
+                let length = match i32::try_from(nbt.len()) {
+                    Ok(v) => v,
+                    Err(_) => {
+                        return Err(DocumentError::TooLarge(nbt.len()));
+                    }
+                };
+
+                data.extend_from_slice(&first.to_le_bytes());
+                data.extend_from_slice(&length.to_le_bytes());
+            }
+        }
+
+        data.extend_from_slice(&nbt);
+
+        Ok(data)
+    }
+
+    /// Writes the document to `path`, updating the header fields to the written ones.
+    /// The file is replaced at once, a failed write leaves the old one.
+    pub fn save(&mut self, path: &Path) -> Result<(), DocumentError> {
+        let data = self.to_bytes()?;
+
+        let Some(name) = path.file_name() else {
+            return Err(DocumentError::Write(Error::new(
+                ErrorKind::InvalidInput,
+                "no file name",
+            )));
+        };
+
+        let mut temp = name.to_os_string();
+        temp.push(".tmp");
+        let temp = path.with_file_name(temp);
+
+        // Renaming replaces the file at once, like the CURRENT file of a LevelDB
+        let result = File::create(&temp)
+            .and_then(|mut v| {
+                v.write_all(&data)?;
+                v.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp, path));
+
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temp);
+            return Err(DocumentError::Write(e));
+        }
+
+        if let NbtHeader::Normal | NbtHeader::LevelDat = self.header {
+            let mut first = [0; 4];
+            first.copy_from_slice(&data[0..4]);
+
+            self.header_values = Some((i32::from_le_bytes(first), (data.len() - 8) as i32));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::nbt_path::NbtPath;
+    use crate::temp_dir::TempDir;
+
+    fn document(tag: NbtTag, header: NbtHeader) -> NbtDocument {
+        NbtDocument {
+            name: String::new(),
+            tag,
+            endian: NbtEndian::Little,
+            header,
+            header_values: None,
+            layout: NbtLayout::default(),
+        }
+    }
+
+    fn header(data: &[u8]) -> (i32, i32) {
+        let mut first = [0; 4];
+        let mut length = [0; 4];
+        first.copy_from_slice(&data[0..4]);
+        length.copy_from_slice(&data[4..8]);
+
+        (i32::from_le_bytes(first), i32::from_le_bytes(length))
+    }
+
+    #[test]
+    fn header_is_made_up_without_one_read() {
+        let tag = NbtTag::Compound(HashMap::from([(
+            String::from("StorageVersion"),
+            NbtTag::Int32(8),
+        )]));
+
+        let data = document(tag, NbtHeader::LevelDat).to_bytes().unwrap();
+        assert_eq!(header(&data), (8, data.len() as i32 - 8));
+
+        let data = document(NbtTag::Int32(0), NbtHeader::Normal)
+            .to_bytes()
+            .unwrap();
+        assert_eq!(
+            header(&data),
+            (DEFAULT_FORMAT_VERSION, data.len() as i32 - 8)
+        );
+    }
+
+    #[test]
+    fn read_header_is_kept() {
+        let mut document = document(NbtTag::Int32(0), NbtHeader::LevelDat);
+        document.header_values = Some((3, 999));
+
+        let data = document.to_bytes().unwrap();
+        assert_eq!(header(&data), (3, data.len() as i32 - 8));
+    }
+
+    #[test]
+    fn parse_keeps_header_and_key_order() {
+        let tag = NbtTag::Compound(HashMap::from([
+            (String::from("b"), NbtTag::Byte(1)),
+            (String::from("a"), NbtTag::Byte(2)),
+        ]));
+
+        let mut layout = NbtLayout::default();
+        layout
+            .keys
+            .insert(NbtPath::root(), vec![String::from("b"), String::from("a")]);
+
+        let nbt = nbt_encode::encode_root("", &tag, NbtEndian::Little, &layout).unwrap();
+        let mut data = 9i32.to_le_bytes().to_vec();
+        data.extend_from_slice(&(nbt.len() as i32).to_le_bytes());
+        data.extend_from_slice(&nbt);
+
+        let document = NbtDocument::parse(data.clone(), NbtEndian::Little, NbtHeader::LevelDat);
+        let document = document.unwrap();
+
+        assert_eq!(document.tag, tag);
+        assert_eq!(document.header_values, Some((9, nbt.len() as i32)));
+        assert_eq!(document.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn parse_reports_short_header_and_damage() {
+        let e = NbtDocument::parse(vec![0; 3], NbtEndian::Little, NbtHeader::Normal);
+        assert!(matches!(e, Err(DocumentError::Header(_))));
+
+        // A compound without its end, the offset counts the header
+        let data = [0, 0, 0, 0, 4, 0, 0, 0, 10, 0, 0, 1];
+        match NbtDocument::parse(data.to_vec(), NbtEndian::Little, NbtHeader::Normal) {
+            Err(DocumentError::Decode(e)) => assert!(e.offset >= 8, "{e}"),
+            v => panic!("{v:?}"),
+        }
+    }
+
+    #[test]
+    fn save_replaces_the_file() {
+        let dir = TempDir::new("document-save");
+        let path = dir.0.join("level.dat");
+        fs::write(&path, b"old").unwrap();
+
+        let mut document = document(NbtTag::Int32(0), NbtHeader::LevelDat);
+        document.save(&path).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), document.to_bytes().unwrap());
+        assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 1);
+        assert_eq!(
+            document.header_values,
+            Some((
+                DEFAULT_FORMAT_VERSION,
+                fs::read(&path).unwrap().len() as i32 - 8
+            ))
+        );
+    }
+}
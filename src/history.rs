@@ -0,0 +1,393 @@
+use std::time::{Duration, Instant};
+
+use bedrock_rs::nbt::NbtTag;
+
+use crate::nbt_decode::NbtLayout;
+use crate::nbt_edit;
+use crate::nbt_path::{NbtPath, NbtPathSegment};
+
+/// A reversible change to an Nbt tree.
+#[derive(Debug, Clone)]
+pub enum NbtCommand {
+    SetValue {
+        path: NbtPath,
+        old: NbtTag,
+        new: NbtTag,
+    },
+    Insert {
+        path: NbtPath,
+        tag: NbtTag,
+    },
+    Remove {
+        path: NbtPath,
+        tag: NbtTag,
+    },
+    /// Rename the compound entry at `path` to `key`
+    Rename {
+        path: NbtPath,
+        key: String,
+    },
+    /// Move the list element at `path` to `index`
+    Move {
+        path: NbtPath,
+        index: usize,
+    },
+    /// Move the compound entry at `path` from position `from` to `to` in the key order
+    MoveKey {
+        path: NbtPath,
+        from: usize,
+        to: usize,
+    },
+}
+
+impl NbtCommand {
+    /// Applies the command to `root`, whose key order is kept in `layout`.
+    pub fn apply(&self, root: &mut NbtTag, layout: &mut NbtLayout) -> Result<(), String> {
+        match self {
+            NbtCommand::SetValue { path, new, .. } => match path.get_mut(root) {
+                Some(v) => {
+                    *v = new.clone();
+                    Ok(())
+                }
+                None => Err(format!("No tag at {path}")),
+            },
+            NbtCommand::Insert { path, tag } => nbt_edit::insert(root, path, tag.clone()),
+            NbtCommand::Remove { path, .. } => nbt_edit::remove(root, path).map(|_| ()),
+            NbtCommand::Rename { path, key } => {
+                nbt_edit::rename(root, layout, path, key).map(|_| ())
+            }
+            NbtCommand::Move { path, index } => nbt_edit::move_to(root, path, *index).map(|_| ()),
+            NbtCommand::MoveKey { path, to, .. } => nbt_edit::move_key(root, layout, path, *to),
+        }
+    }
+
+    /// The command undoing this one.
+    pub fn inverse(&self) -> NbtCommand {
+        match self {
+            NbtCommand::SetValue { path, old, new } => NbtCommand::SetValue {
+                path: path.clone(),
+                old: new.clone(),
+                new: old.clone(),
+            },
+            NbtCommand::Insert { path, tag } => NbtCommand::Remove {
+                path: path.clone(),
+                tag: tag.clone(),
+            },
+            NbtCommand::Remove { path, tag } => NbtCommand::Insert {
+                path: path.clone(),
+                tag: tag.clone(),
+            },
+            NbtCommand::Rename { path, key } => match path.split_last() {
+                Some((parent, NbtPathSegment::Key(old))) => NbtCommand::Rename {
+                    path: parent.key(key.clone()),
+                    key: old.clone(),
+                },
+                _ => self.clone(),
+            },
+            NbtCommand::Move { path, index } => match path.split_last() {
+                Some((parent, NbtPathSegment::Index(old))) => NbtCommand::Move {
+                    path: parent.index(*index),
+                    index: *old,
+                },
+                _ => self.clone(),
+            },
+            NbtCommand::MoveKey { path, from, to } => NbtCommand::MoveKey {
+                path: path.clone(),
+                from: *to,
+                to: *from,
+            },
+        }
+    }
+}
+
+/// Pause in typing after which the next keystroke starts a new undo step.
+pub const TYPING_PAUSE: Duration = Duration::from_secs(1);
+
+/// Undo and redo stacks of the commands applied to a document.
+#[derive(Debug)]
+pub struct History {
+    undo: Vec<NbtCommand>,
+    redo: Vec<NbtCommand>,
+    /// Length of the undo stack when the document was last saved, `None`
+    /// once that state can't be reached anymore
+    saved: Option<usize>,
+    /// When the last command was typed, while further keystrokes in the
+    /// same input still belong to it
+    typing: Option<Instant>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            saved: Some(0),
+            typing: None,
+        }
+    }
+
+    /// Applies `command` to `root` and records it as its own step.
+    pub fn apply(
+        &mut self,
+        root: &mut NbtTag,
+        layout: &mut NbtLayout,
+        command: NbtCommand,
+    ) -> Result<(), String> {
+        self.typing = None;
+        self.push(root, layout, command)
+    }
+
+    /// Applies a value typed into an input. Typing yields one edit per
+    /// keystroke, they are kept as one step until [`Self::end_typing`] or a
+    /// pause of [`TYPING_PAUSE`].
+    pub fn apply_typed(
+        &mut self,
+        root: &mut NbtTag,
+        layout: &mut NbtLayout,
+        command: NbtCommand,
+    ) -> Result<(), String> {
+        let typing = self.typing.is_some_and(|v| v.elapsed() < TYPING_PAUSE);
+
+        // The saved state stays reachable by undo
+        if typing && self.saved != Some(self.undo.len()) {
+            if let (
+                Some(NbtCommand::SetValue {
+                    path: last, new, ..
+                }),
+                NbtCommand::SetValue {
+                    path, new: next, ..
+                },
+            ) = (self.undo.last_mut(), &command)
+            {
+                if last == path {
+                    command.apply(root, layout)?;
+                    *new = next.clone();
+                    self.redo.clear();
+                    self.typing = Some(Instant::now());
+                    return Ok(());
+                }
+            }
+        }
+
+        self.push(root, layout, command)?;
+        self.typing = Some(Instant::now());
+
+        Ok(())
+    }
+
+    /// Ends the step the typed values are collected in, like when the input is submitted.
+    pub fn end_typing(&mut self) {
+        self.typing = None;
+    }
+
+    fn push(
+        &mut self,
+        root: &mut NbtTag,
+        layout: &mut NbtLayout,
+        command: NbtCommand,
+    ) -> Result<(), String> {
+        command.apply(root, layout)?;
+
+        if self.saved.is_some_and(|v| v > self.undo.len()) {
+            self.saved = None;
+        }
+
+        self.redo.clear();
+        self.undo.push(command);
+
+        Ok(())
+    }
+
+    pub fn undo(&mut self, root: &mut NbtTag, layout: &mut NbtLayout) -> Result<(), String> {
+        self.typing = None;
+
+        let Some(command) = self.undo.pop() else {
+            return Err(String::from("Nothing to undo"));
+        };
+
+        if let Err(e) = command.inverse().apply(root, layout) {
+            self.undo.push(command);
+            return Err(e);
+        }
+
+        self.redo.push(command);
+
+        Ok(())
+    }
+
+    pub fn redo(&mut self, root: &mut NbtTag, layout: &mut NbtLayout) -> Result<(), String> {
+        self.typing = None;
+
+        let Some(command) = self.redo.pop() else {
+            return Err(String::from("Nothing to redo"));
+        };
+
+        if let Err(e) = command.apply(root, layout) {
+            self.redo.push(command);
+            return Err(e);
+        }
+
+        self.undo.push(command);
+
+        Ok(())
+    }
+
+    /// Applies every command that can be undone to `root`, in order.
+    pub fn replay(&self, root: &mut NbtTag, layout: &mut NbtLayout) -> Result<(), String> {
+        for command in self.undo.iter() {
+            command.apply(root, layout)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.typing = None;
+        self.saved = Some(self.undo.len());
+    }
+
+    /// Marks a document that was never written to disk, like an imported one.
+    pub fn mark_unsaved(&mut self) {
+        self.saved = None;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.saved != Some(self.undo.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn root() -> NbtTag {
+        NbtTag::Compound(HashMap::from([(String::from("a"), NbtTag::Int32(0))]))
+    }
+
+    fn set(root: &NbtTag, value: i32) -> NbtCommand {
+        let path = NbtPath::root().key("a");
+
+        NbtCommand::SetValue {
+            old: path.get(root).unwrap().clone(),
+            path,
+            new: NbtTag::Int32(value),
+        }
+    }
+
+    fn type_value(history: &mut History, root: &mut NbtTag, value: i32) {
+        let command = set(root, value);
+        history
+            .apply_typed(root, &mut NbtLayout::default(), command)
+            .unwrap();
+    }
+
+    fn apply_value(history: &mut History, root: &mut NbtTag, value: i32) {
+        let command = set(root, value);
+        history
+            .apply(root, &mut NbtLayout::default(), command)
+            .unwrap();
+    }
+
+    fn value(root: &NbtTag) -> &NbtTag {
+        NbtPath::root().key("a").get(root).unwrap()
+    }
+
+    #[test]
+    fn typing_is_one_step() {
+        let mut root = root();
+        let mut history = History::new();
+
+        for v in [1, 12, 123] {
+            type_value(&mut history, &mut root, v);
+        }
+
+        history.undo(&mut root, &mut NbtLayout::default()).unwrap();
+        assert_eq!(value(&root), &NbtTag::Int32(0));
+        assert!(history.undo(&mut root, &mut NbtLayout::default()).is_err());
+    }
+
+    #[test]
+    fn submit_ends_the_step() {
+        let mut root = root();
+        let mut history = History::new();
+
+        type_value(&mut history, &mut root, 1);
+        history.end_typing();
+        type_value(&mut history, &mut root, 2);
+
+        history.undo(&mut root, &mut NbtLayout::default()).unwrap();
+        assert_eq!(value(&root), &NbtTag::Int32(1));
+    }
+
+    #[test]
+    fn pause_ends_the_step() {
+        let mut root = root();
+        let mut history = History::new();
+
+        type_value(&mut history, &mut root, 1);
+        history.typing = Instant::now().checked_sub(TYPING_PAUSE);
+        type_value(&mut history, &mut root, 2);
+
+        history.undo(&mut root, &mut NbtLayout::default()).unwrap();
+        assert_eq!(value(&root), &NbtTag::Int32(1));
+    }
+
+    #[test]
+    fn commands_are_not_merged() {
+        let mut root = root();
+        let mut history = History::new();
+
+        apply_value(&mut history, &mut root, 1);
+        apply_value(&mut history, &mut root, 2);
+
+        history.undo(&mut root, &mut NbtLayout::default()).unwrap();
+        assert_eq!(value(&root), &NbtTag::Int32(1));
+    }
+
+    #[test]
+    fn saved_state_stays_reachable() {
+        let mut root = root();
+        let mut history = History::new();
+
+        type_value(&mut history, &mut root, 1);
+        history.mark_saved();
+        type_value(&mut history, &mut root, 2);
+        assert!(history.is_dirty());
+
+        history.undo(&mut root, &mut NbtLayout::default()).unwrap();
+        assert!(!history.is_dirty());
+        assert_eq!(value(&root), &NbtTag::Int32(1));
+    }
+
+    #[test]
+    fn key_moves_are_undone() {
+        let mut root = NbtTag::Compound(HashMap::from([
+            (String::from("a"), NbtTag::Int32(0)),
+            (String::from("b"), NbtTag::Int32(0)),
+            (String::from("c"), NbtTag::Int32(0)),
+        ]));
+        let mut layout = NbtLayout::default();
+        let mut history = History::new();
+
+        let command = NbtCommand::MoveKey {
+            path: NbtPath::root().key("c"),
+            from: 2,
+            to: 0,
+        };
+        history.apply(&mut root, &mut layout, command).unwrap();
+
+        let keys = |v: &NbtLayout| v.keys[&NbtPath::root()].join("");
+        assert_eq!(keys(&layout), "cab");
+
+        history.undo(&mut root, &mut layout).unwrap();
+        assert_eq!(keys(&layout), "abc");
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+
+use bedrock_rs::nbt::NbtTag;
+use serde_json::{json, Map, Number, Value};
+
+use crate::document::{DocumentError, NbtDocument, NbtEndian, NbtHeader};
+use crate::nbt_decode::NbtLayout;
+use crate::nbt_edit;
+use crate::nbt_path::{NbtPath, NbtPathSegment};
+
+/// JSON has no NaN or infinity, those are written as the bits of the float
+/// in hex so the sign and NaN payload survive, like `"0x7fc00000"`.
+fn float_to_json(v: f64, bits: u64, width: usize) -> Value {
+    match Number::from_f64(v) {
+        Some(v) => Value::Number(v),
+        None => json!(format!("0x{bits:0width$x}")),
+    }
+}
+
+/// Bits of a float written by [`float_to_json`].
+fn float_bits(v: &Value) -> Option<u64> {
+    let hex = v.as_str()?.strip_prefix("0x")?;
+    u64::from_str_radix(hex, 16).ok()
+}
+
+fn float_from_json(v: &Value, path: &str) -> Result<f64, String> {
+    match v {
+        Value::Number(n) => match n.as_f64() {
+            Some(v) => Ok(v),
+            None => Err(format!("{path}: {n} is not a float")),
+        },
+        _ => Err(format!("{path}: expected a float")),
+    }
+}
+
+/// Maps a tag to `{"type": ..., "value": ...}` so the exact variant survives.
+/// Compound keys are sorted.
+pub fn tag_to_json(tag: &NbtTag) -> Value {
+    write_tag(tag, &mut NbtPath::root(), &NbtLayout::default())
+}
+
+/// Like [`tag_to_json`], with compound keys in the order of `layout`. Empty
+/// lists get the `"element"` type id they were read with.
+fn write_tag(tag: &NbtTag, path: &mut NbtPath, layout: &NbtLayout) -> Value {
+    let (kind, value) = match tag {
+        NbtTag::Byte(v) => ("byte", json!(v)),
+        NbtTag::Int16(v) => ("int16", json!(v)),
+        NbtTag::Int32(v) => ("int32", json!(v)),
+        NbtTag::Int64(v) => ("int64", json!(v)),
+        NbtTag::Float32(v) => ("float32", float_to_json(*v as f64, v.to_bits() as u64, 8)),
+        NbtTag::Float64(v) => ("float64", float_to_json(*v, v.to_bits(), 16)),
+        NbtTag::String(v) => ("string", json!(v)),
+        NbtTag::List(v) if v.is_empty() => {
+            return match layout.empty_lists.get(path) {
+                Some(element) => json!({ "type": "list", "value": [], "element": element }),
+                None => json!({ "type": "list", "value": [] }),
+            };
+        }
+        NbtTag::List(v) => {
+            let mut values = Vec::with_capacity(v.len());
+
+            for (i, tag) in v.iter().enumerate() {
+                path.push(NbtPathSegment::Index(i));
+                values.push(write_tag(tag, path, layout));
+                path.pop();
+            }
+
+            ("list", Value::Array(values))
+        }
+        NbtTag::Compound(v) => {
+            let mut map = Map::new();
+
+            for key in layout.ordered_keys(path, v) {
+                path.push(NbtPathSegment::Key(key.clone()));
+                map.insert(key.clone(), write_tag(&v[key], path, layout));
+                path.pop();
+            }
+
+            ("compound", Value::Object(map))
+        }
+        NbtTag::Empty => return json!({ "type": "empty" }),
+    };
+
+    json!({ "type": kind, "value": value })
+}
+
+fn integer_from_json(v: &Value, min: i64, max: i64, path: &str) -> Result<i64, String> {
+    match v.as_i64() {
+        Some(v) if v >= min && v <= max => Ok(v),
+        _ => Err(format!(
+            "{path}: expected an integer between {min} and {max}"
+        )),
+    }
+}
+
+/// Reads a tag written by [`tag_to_json`], `path` names it in errors.
+pub fn tag_from_json(v: &Value, path: &str) -> Result<NbtTag, String> {
+    read_tag(v, path, &mut NbtPath::root(), &mut NbtLayout::default())
+}
+
+/// Like [`tag_from_json`], adding the key order and empty list types to `layout`.
+fn read_tag(
+    v: &Value,
+    path: &str,
+    tag_path: &mut NbtPath,
+    layout: &mut NbtLayout,
+) -> Result<NbtTag, String> {
+    let Some(kind) = v.get("type").and_then(Value::as_str) else {
+        return Err(format!("{path}: missing \"type\""));
+    };
+
+    if kind == "empty" {
+        return Ok(NbtTag::Empty);
+    }
+
+    let Some(value) = v.get("value") else {
+        return Err(format!("{path}: missing \"value\""));
+    };
+
+    match kind {
+        "byte" => integer_from_json(value, u8::MIN as i64, u8::MAX as i64, path)
+            .map(|v| NbtTag::Byte(v as u8)),
+        "int16" => integer_from_json(value, i16::MIN as i64, i16::MAX as i64, path)
+            .map(|v| NbtTag::Int16(v as i16)),
+        "int32" => integer_from_json(value, i32::MIN as i64, i32::MAX as i64, path)
+            .map(|v| NbtTag::Int32(v as i32)),
+        "int64" => integer_from_json(value, i64::MIN, i64::MAX, path).map(NbtTag::Int64),
+        "float32" => match float_bits(value) {
+            Some(bits) => match u32::try_from(bits) {
+                Ok(v) => Ok(NbtTag::Float32(f32::from_bits(v))),
+                Err(_) => Err(format!("{path}: {bits:#x} has more than 32 bits")),
+            },
+            None => float_from_json(value, path).map(|v| NbtTag::Float32(v as f32)),
+        },
+        "float64" => match float_bits(value) {
+            Some(bits) => Ok(NbtTag::Float64(f64::from_bits(bits))),
+            None => float_from_json(value, path).map(NbtTag::Float64),
+        },
+        "string" => match value.as_str() {
+            Some(v) => Ok(NbtTag::String(v.to_string())),
+            None => Err(format!("{path}: expected a string")),
+        },
+        "list" => {
+            let Some(values) = value.as_array() else {
+                return Err(format!("{path}: expected an array"));
+            };
+
+            if values.is_empty() {
+                if let Some(element) = v.get("element") {
+                    let element = integer_from_json(element, 0, u8::MAX as i64, path)?;
+                    layout.empty_lists.insert(tag_path.clone(), element as u8);
+                }
+            }
+
+            let mut list = Vec::with_capacity(values.len());
+
+            for (i, v) in values.iter().enumerate() {
+                let element_path = format!("{path}[{i}]");
+
+                tag_path.push(NbtPathSegment::Index(i));
+                let tag = read_tag(v, &element_path, tag_path, layout);
+                tag_path.pop();
+                let tag = tag?;
+
+                if let Err(e) = nbt_edit::check_list_element(&list, &tag) {
+                    return Err(format!("{element_path}: {e}"));
+                }
+
+                list.push(tag);
+            }
+
+            Ok(NbtTag::List(list))
+        }
+        "compound" => {
+            let Some(values) = value.as_object() else {
+                return Err(format!("{path}: expected an object"));
+            };
+
+            let mut compound = HashMap::with_capacity(values.len());
+
+            // Objects keep the order they were written in
+            if !values.is_empty() {
+                layout
+                    .keys
+                    .insert(tag_path.clone(), values.keys().cloned().collect());
+            }
+
+            for (k, v) in values.iter() {
+                tag_path.push(NbtPathSegment::Key(k.clone()));
+                let tag = read_tag(v, &format!("{path}.{k}"), tag_path, layout);
+                tag_path.pop();
+
+                compound.insert(k.clone(), tag?);
+            }
+
+            Ok(NbtTag::Compound(compound))
+        }
+        _ => Err(format!("{path}: unknown type \"{kind}\"")),
+    }
+}
+
+/// Writes a whole document, including the root name and header, as pretty JSON.
+pub fn to_json(document: &NbtDocument) -> String {
+    let header = match (document.header, document.header_values) {
+        (NbtHeader::None, _) | (_, None) => Value::Null,
+        (header, Some((first, length))) => json!({
+            "kind": header.id(),
+            "first": first,
+            "length": length,
+        }),
+    };
+
+    let value = json!({
+        "name": document.name,
+        "endian": document.endian.id(),
+        "header": header,
+        "root": write_tag(&document.tag, &mut NbtPath::root(), &document.layout),
+    });
+
+    // Serializing a Value can't fail
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
+
+/// Reads a document written by [`to_json`].
+pub fn from_json(text: &str) -> Result<NbtDocument, DocumentError> {
+    read_document(text).map_err(DocumentError::Json)
+}
+
+fn read_document(text: &str) -> Result<NbtDocument, String> {
+    let value: Value = match serde_json::from_str(text) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Invalid JSON: {e}")),
+    };
+
+    let name = match value.get("name").and_then(Value::as_str) {
+        Some(v) => v.to_string(),
+        None => return Err(String::from("Missing \"name\"")),
+    };
+
+    let endian = match value.get("endian").and_then(Value::as_str) {
+        Some(v) => match NbtEndian::from_id(v) {
+            Some(v) => v,
+            None => return Err(format!("Unknown endian \"{v}\"")),
+        },
+        None => return Err(String::from("Missing \"endian\"")),
+    };
+
+    let (header, header_values) = match value.get("header") {
+        None | Some(Value::Null) => (NbtHeader::None, None),
+        Some(v) => {
+            let header = match v.get("kind").and_then(Value::as_str) {
+                Some(kind) => match NbtHeader::from_id(kind) {
+                    Some(v) => v,
+                    None => return Err(format!("Unknown header \"{kind}\"")),
+                },
+                None => return Err(String::from("Missing header \"kind\"")),
+            };
+
+            let first = v.get("first").map_or(Ok(0), |v| {
+                integer_from_json(v, i32::MIN as i64, i32::MAX as i64, "header.first")
+            })?;
+            let length = v.get("length").map_or(Ok(0), |v| {
+                integer_from_json(v, i32::MIN as i64, i32::MAX as i64, "header.length")
+            })?;
+
+            (header, Some((first as i32, length as i32)))
+        }
+    };
+
+    let mut layout = NbtLayout::default();
+
+    let tag = match value.get("root") {
+        Some(v) => read_tag(v, "root", &mut NbtPath::root(), &mut layout)?,
+        None => return Err(String::from("Missing \"root\"")),
+    };
+
+    Ok(NbtDocument {
+        name,
+        tag,
+        endian,
+        header,
+        header_values,
+        layout,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt_decode::{NbtDecoder, TAG_INT16};
+    use crate::nbt_encode;
+
+    /// A Little Endian file with keys out of order, a NaN with payload and an
+    /// empty list of Int16, as a game would write it.
+    fn file() -> Vec<u8> {
+        let tag = NbtTag::Compound(HashMap::from([
+            (
+                String::from("z"),
+                NbtTag::Float32(f32::from_bits(0xffc0_0001)),
+            ),
+            (String::from("inf"), NbtTag::Float64(f64::NEG_INFINITY)),
+            (String::from("a"), NbtTag::List(Vec::new())),
+            (String::from("m"), NbtTag::Byte(200)),
+        ]));
+
+        let mut layout = NbtLayout::default();
+        layout.keys.insert(
+            NbtPath::root(),
+            ["z", "inf", "a", "m"].map(String::from).to_vec(),
+        );
+        layout
+            .empty_lists
+            .insert(NbtPath::root().key("a"), TAG_INT16);
+
+        let nbt = nbt_encode::encode_root("", &tag, NbtEndian::Little, &layout).unwrap();
+
+        let mut data = 10i32.to_le_bytes().to_vec();
+        data.extend_from_slice(&(nbt.len() as i32).to_le_bytes());
+        data.extend_from_slice(&nbt);
+        data
+    }
+
+    fn document(data: &[u8]) -> NbtDocument {
+        let mut decoder = NbtDecoder::new(&data[8..], NbtEndian::Little).with_layout();
+        let (name, tag) = decoder.read_root().unwrap();
+
+        NbtDocument {
+            name,
+            tag,
+            endian: NbtEndian::Little,
+            header: NbtHeader::LevelDat,
+            header_values: Some((10, data.len() as i32 - 8)),
+            layout: decoder.into_layout(),
+        }
+    }
+
+    #[test]
+    fn import_rebuilds_the_file() {
+        let data = file();
+        let text = to_json(&document(&data));
+        let imported = from_json(&text).unwrap();
+
+        assert_eq!(imported.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn exports_keys_in_file_order() {
+        let text = to_json(&document(&file()));
+
+        let positions: Vec<usize> = ["\"z\"", "\"inf\"", "\"a\"", "\"m\""]
+            .iter()
+            .map(|v| text.find(v).unwrap())
+            .collect();
+
+        assert!(positions.windows(2).all(|v| v[0] < v[1]), "{text}");
+        assert!(text.contains("\"0xffc00001\""), "{text}");
+        assert!(text.contains("\"0xfff0000000000000\""), "{text}");
+    }
+
+    #[test]
+    fn reports_where_the_json_is_wrong() {
+        let value = json!({
+            "type": "compound",
+            "value": { "a": { "type": "list", "value": [
+                { "type": "byte", "value": 1 },
+                { "type": "int16", "value": 1 }
+            ] } }
+        });
+
+        let e = tag_from_json(&value, "root").unwrap_err();
+        assert!(e.starts_with("root.a[1]: "), "{e}");
+
+        let value = json!({ "type": "byte", "value": 256 });
+        assert!(tag_from_json(&value, "root").is_err());
+    }
+}
@@ -0,0 +1,1129 @@
+//! Reading and writing the LevelDB in the `db` folder of a Bedrock world. Only
+//! what Bedrock needs is supported: the default bytewise comparator and
+//! Mojang's zlib and raw deflate block compression next to the snappy of the
+//! original. Writes go to a new log file the game replays on its next start,
+//! tables are never rewritten.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::{DeflateDecoder, ZlibDecoder};
+
+/// Size of the blocks log files are split into.
+const LOG_BLOCK_SIZE: usize = 32768;
+/// Checksum, length and type in front of every log record.
+const LOG_HEADER_SIZE: usize = 7;
+const FOOTER_SIZE: usize = 48;
+const TABLE_MAGIC: u64 = 0xdb4775248b80fb57;
+/// Compression type and checksum after every table block.
+const BLOCK_TRAILER_SIZE: usize = 5;
+const CRC_MASK_DELTA: u32 = 0xa282ead8;
+
+// Fragment types of log records
+const RECORD_FULL: u8 = 1;
+const RECORD_FIRST: u8 = 2;
+const RECORD_MIDDLE: u8 = 3;
+const RECORD_LAST: u8 = 4;
+
+// Fields of the version edits in the manifest
+const EDIT_COMPARATOR: u32 = 1;
+const EDIT_LOG_NUMBER: u32 = 2;
+const EDIT_NEXT_FILE: u32 = 3;
+const EDIT_LAST_SEQUENCE: u32 = 4;
+const EDIT_COMPACT_POINTER: u32 = 5;
+const EDIT_DELETED_FILE: u32 = 6;
+const EDIT_NEW_FILE: u32 = 7;
+const EDIT_PREV_LOG_NUMBER: u32 = 9;
+
+const VALUE_DELETION: u8 = 0;
+const VALUE_SET: u8 = 1;
+
+// Block compression, 2 and 4 are only used by Mojang's fork
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_SNAPPY: u8 = 1;
+const COMPRESSION_ZLIB: u8 = 2;
+const COMPRESSION_ZLIB_RAW: u8 = 4;
+
+/// Reads the varints and length prefixed slices LevelDB files are made of.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn varint64(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let Some((byte, rest)) = self.data.split_first() else {
+                return Err(String::from("Error reading LevelDB: truncated varint"));
+            };
+
+            self.data = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(String::from("Error reading LevelDB: varint too long"))
+    }
+
+    fn varint32(&mut self) -> Result<u32, String> {
+        u32::try_from(self.varint64()?)
+            .map_err(|_| String::from("Error reading LevelDB: varint too large"))
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.data.len() < len {
+            return Err(String::from("Error reading LevelDB: truncated data"));
+        }
+
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+
+        Ok(bytes)
+    }
+
+    /// A slice prefixed with its length.
+    fn slice(&mut self) -> Result<&'a [u8], String> {
+        let len = self.varint32()? as usize;
+        self.bytes(len)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    out.push(value as u8);
+}
+
+/// Writes a slice prefixed with its length.
+fn put_slice(out: &mut Vec<u8>, data: &[u8]) {
+    put_varint(out, data.len() as u64);
+    out.extend_from_slice(data);
+}
+
+/// The checksum LevelDB stores, masked so checksums of data containing
+/// checksums stay useful.
+fn masked_crc(data: &[u8]) -> u32 {
+    let crc = crc32c::crc32c(data);
+    crc.rotate_right(15).wrapping_add(CRC_MASK_DELTA)
+}
+
+/// Splits a log or manifest file into its records. A record cut off at the
+/// end is dropped like LevelDB does after a crash.
+fn read_log(data: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut records = Vec::new();
+    let mut record: Option<Vec<u8>> = None;
+
+    for (i, block) in data.chunks(LOG_BLOCK_SIZE).enumerate() {
+        let mut pos = 0;
+
+        while pos + LOG_HEADER_SIZE <= block.len() {
+            let header = &block[pos..pos + LOG_HEADER_SIZE];
+            let crc = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+            let len = u16::from_le_bytes([header[4], header[5]]) as usize;
+            let kind = header[6];
+
+            // Preallocated space that was never written
+            if kind == 0 && len == 0 {
+                break;
+            }
+
+            let Some(payload) = block.get(pos + LOG_HEADER_SIZE..pos + LOG_HEADER_SIZE + len)
+            else {
+                return Ok(records);
+            };
+
+            if masked_crc(&block[pos + 6..pos + LOG_HEADER_SIZE + len]) != crc {
+                return Err(format!(
+                    "Error reading LevelDB log: checksum mismatch at offset {}",
+                    i * LOG_BLOCK_SIZE + pos
+                ));
+            }
+
+            match (kind, &mut record) {
+                (RECORD_FULL, _) => records.push(payload.to_vec()),
+                (RECORD_FIRST, _) => record = Some(payload.to_vec()),
+                (RECORD_MIDDLE, Some(v)) => v.extend_from_slice(payload),
+                (RECORD_LAST, Some(v)) => {
+                    v.extend_from_slice(payload);
+                    records.extend(record.take());
+                }
+                (RECORD_MIDDLE | RECORD_LAST, None) => {
+                    return Err(String::from(
+                        "Error reading LevelDB log: record continues without a start",
+                    ));
+                }
+                (v, _) => {
+                    return Err(format!(
+                        "Error reading LevelDB log: unknown record type {v}"
+                    ))
+                }
+            }
+
+            pos += LOG_HEADER_SIZE + len;
+        }
+    }
+
+    Ok(records)
+}
+
+/// Encodes records in the log format for the start of a new file, splitting
+/// them at block boundaries like [`read_log`] expects.
+fn encode_log(records: &[&[u8]]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    for record in records {
+        let mut rest = *record;
+        let mut first = true;
+
+        loop {
+            let left = LOG_BLOCK_SIZE - out.len() % LOG_BLOCK_SIZE;
+
+            // Too little space for a header, the rest of the block is padding
+            if left < LOG_HEADER_SIZE {
+                out.resize(out.len() + left, 0);
+                continue;
+            }
+
+            let len = rest.len().min(left - LOG_HEADER_SIZE);
+            let last = len == rest.len();
+
+            let kind = match (first, last) {
+                (true, true) => RECORD_FULL,
+                (true, false) => RECORD_FIRST,
+                (false, false) => RECORD_MIDDLE,
+                (false, true) => RECORD_LAST,
+            };
+
+            let mut fragment = vec![kind];
+            fragment.extend_from_slice(&rest[..len]);
+
+            out.extend_from_slice(&masked_crc(&fragment).to_le_bytes());
+            out.extend_from_slice(&(len as u16).to_le_bytes());
+            out.extend_from_slice(&fragment);
+
+            rest = &rest[len..];
+            first = false;
+
+            if last {
+                break;
+            }
+        }
+    }
+
+    out
+}
+
+/// Where a block is in a table file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BlockHandle {
+    offset: u64,
+    size: u64,
+}
+
+impl BlockHandle {
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        Ok(Self {
+            offset: reader.varint64()?,
+            size: reader.varint64()?,
+        })
+    }
+}
+
+fn decompress(compression: u8, data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+
+    let result = match compression {
+        COMPRESSION_NONE => return Ok(data.to_vec()),
+        COMPRESSION_SNAPPY => {
+            return snap::raw::Decoder::new()
+                .decompress_vec(data)
+                .map_err(|e| format!("Error decompressing LevelDB block: {e:?}"));
+        }
+        COMPRESSION_ZLIB => ZlibDecoder::new(data).read_to_end(&mut out),
+        COMPRESSION_ZLIB_RAW => DeflateDecoder::new(data).read_to_end(&mut out),
+        v => {
+            return Err(format!(
+                "Error reading LevelDB block: unknown compression {v}"
+            ))
+        }
+    };
+
+    match result {
+        Ok(_) => Ok(out),
+        Err(e) => Err(format!("Error decompressing LevelDB block: {e:?}")),
+    }
+}
+
+/// Checks and decompresses a block read together with its trailer.
+fn unpack_block(data: &[u8]) -> Result<Vec<u8>, String> {
+    let Some(split) = data.len().checked_sub(BLOCK_TRAILER_SIZE) else {
+        return Err(String::from("Error reading LevelDB block: truncated block"));
+    };
+
+    let (block, trailer) = data.split_at(split);
+    let crc = u32::from_le_bytes([trailer[1], trailer[2], trailer[3], trailer[4]]);
+
+    if masked_crc(&data[..split + 1]) != crc {
+        return Err(String::from(
+            "Error reading LevelDB block: checksum mismatch",
+        ));
+    }
+
+    decompress(trailer[0], block)
+}
+
+fn table_block(table: &[u8], handle: BlockHandle) -> Result<Vec<u8>, String> {
+    let start = handle.offset as usize;
+    let end = start + handle.size as usize + BLOCK_TRAILER_SIZE;
+
+    match table.get(start..end) {
+        Some(v) => unpack_block(v),
+        None => Err(String::from(
+            "Error reading LevelDB table: block out of bounds",
+        )),
+    }
+}
+
+/// A key with its value in a table block.
+type BlockEntry<'a> = (Vec<u8>, &'a [u8]);
+
+/// Keys and values of a table block. Keys are stored as the difference to the
+/// one before, the restart points at the end are only needed for seeking.
+fn block_entries(block: &[u8]) -> Result<Vec<BlockEntry<'_>>, String> {
+    let Some(count_start) = block.len().checked_sub(4) else {
+        return Err(String::from("Error reading LevelDB block: truncated block"));
+    };
+
+    let restarts = Reader::new(&block[count_start..]).u32()? as usize;
+
+    let Some(end) = count_start.checked_sub(restarts * 4) else {
+        return Err(String::from(
+            "Error reading LevelDB block: too many restarts",
+        ));
+    };
+
+    let mut reader = Reader::new(&block[..end]);
+    let mut entries = Vec::new();
+    let mut key: Vec<u8> = Vec::new();
+
+    while !reader.is_empty() {
+        let shared = reader.varint32()? as usize;
+        let unshared = reader.varint32()? as usize;
+        let value_len = reader.varint32()? as usize;
+
+        if shared > key.len() {
+            return Err(String::from(
+                "Error reading LevelDB block: invalid shared key",
+            ));
+        }
+
+        key.truncate(shared);
+        key.extend_from_slice(reader.bytes(unshared)?);
+
+        entries.push((key.clone(), reader.bytes(value_len)?));
+    }
+
+    Ok(entries)
+}
+
+/// Splits an internal key into the user key, sequence number and value type.
+fn split_internal_key(key: &[u8]) -> Result<(&[u8], u64, u8), String> {
+    let Some(split) = key.len().checked_sub(8) else {
+        return Err(String::from(
+            "Error reading LevelDB table: internal key too short",
+        ));
+    };
+
+    let trailer = Reader::new(&key[split..]).u64()?;
+
+    Ok((&key[..split], trailer >> 8, trailer as u8))
+}
+
+/// A table file of the current version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableFile {
+    pub level: u32,
+    pub number: u64,
+    pub size: u64,
+    /// Smallest and largest internal key in the file
+    pub smallest: Vec<u8>,
+    pub largest: Vec<u8>,
+}
+
+/// The state of the database the manifest describes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Version {
+    comparator: Option<String>,
+    log_number: u64,
+    prev_log_number: u64,
+    next_file: u64,
+    last_sequence: u64,
+    tables: Vec<TableFile>,
+}
+
+impl Version {
+    fn apply(&mut self, edit: &[u8]) -> Result<(), String> {
+        let mut reader = Reader::new(edit);
+
+        while !reader.is_empty() {
+            match reader.varint32()? {
+                EDIT_COMPARATOR => {
+                    self.comparator = Some(String::from_utf8_lossy(reader.slice()?).to_string());
+                }
+                EDIT_LOG_NUMBER => self.log_number = reader.varint64()?,
+                EDIT_PREV_LOG_NUMBER => self.prev_log_number = reader.varint64()?,
+                EDIT_NEXT_FILE => self.next_file = reader.varint64()?,
+                EDIT_LAST_SEQUENCE => self.last_sequence = reader.varint64()?,
+                EDIT_COMPACT_POINTER => {
+                    reader.varint32()?;
+                    reader.slice()?;
+                }
+                EDIT_DELETED_FILE => {
+                    let level = reader.varint32()?;
+                    let number = reader.varint64()?;
+
+                    self.tables
+                        .retain(|v| v.level != level || v.number != number);
+                }
+                EDIT_NEW_FILE => {
+                    let level = reader.varint32()?;
+                    let number = reader.varint64()?;
+                    let size = reader.varint64()?;
+                    let smallest = reader.slice()?.to_vec();
+                    let largest = reader.slice()?.to_vec();
+
+                    self.tables.push(TableFile {
+                        level,
+                        number,
+                        size,
+                        smallest,
+                        largest,
+                    });
+                }
+                v => return Err(format!("Error reading LevelDB manifest: unknown field {v}")),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Version {
+    /// The whole version as one edit, the first record of a new manifest.
+    fn snapshot(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        if let Some(v) = &self.comparator {
+            put_varint(&mut out, EDIT_COMPARATOR.into());
+            put_slice(&mut out, v.as_bytes());
+        }
+
+        for (field, value) in [
+            (EDIT_LOG_NUMBER, self.log_number),
+            (EDIT_PREV_LOG_NUMBER, self.prev_log_number),
+            (EDIT_NEXT_FILE, self.next_file),
+            (EDIT_LAST_SEQUENCE, self.last_sequence),
+        ] {
+            put_varint(&mut out, field.into());
+            put_varint(&mut out, value);
+        }
+
+        for table in self.tables.iter() {
+            put_varint(&mut out, EDIT_NEW_FILE.into());
+            put_varint(&mut out, table.level.into());
+            put_varint(&mut out, table.number);
+            put_varint(&mut out, table.size);
+            put_slice(&mut out, &table.smallest);
+            put_slice(&mut out, &table.largest);
+        }
+
+        out
+    }
+}
+
+/// Changes written to a database at once, either all of them end up on disk
+/// or none. A later change to the same key replaces the earlier one.
+#[derive(Debug, Clone, Default)]
+pub struct WriteBatch {
+    /// New value of each key, `None` to delete it
+    changes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.changes.insert(key, Some(value));
+    }
+
+    pub fn delete(&mut self, key: Vec<u8>) {
+        self.changes.insert(key, None);
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The batch as it is stored in a log record, numbered from `sequence`.
+    fn encode(&self, sequence: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        out.extend_from_slice(&sequence.to_le_bytes());
+        out.extend_from_slice(&(self.changes.len() as u32).to_le_bytes());
+
+        for (key, value) in self.changes.iter() {
+            match value {
+                Some(v) => {
+                    out.push(VALUE_SET);
+                    put_slice(&mut out, key);
+                    put_slice(&mut out, v);
+                }
+                None => {
+                    out.push(VALUE_DELETION);
+                    put_slice(&mut out, key);
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// The LOCK file of a database, held the way LevelDB holds it so the game
+/// can't open the world while it is written. Released when dropped.
+pub struct DbLock {
+    _file: File,
+}
+
+impl DbLock {
+    pub fn acquire(dir: &Path) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join("LOCK"));
+
+        let file = match file {
+            Ok(v) => v,
+            Err(e) => return Err(format!("Error opening LevelDB LOCK: {e:?}")),
+        };
+
+        match try_lock(&file) {
+            Ok(true) => Ok(Self { _file: file }),
+            Ok(false) => Err(String::from(
+                "The world is open in the game or a server, close it first",
+            )),
+            Err(e) => Err(format!("Error locking LevelDB LOCK: {e:?}")),
+        }
+    }
+}
+
+/// LevelDB locks with `fcntl`, which on Linux doesn't see `flock` locks.
+#[cfg(unix)]
+fn try_lock(file: &File) -> std::io::Result<bool> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: flock is plain data and all zeroes locks the whole file
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as _;
+    lock.l_whence = libc::SEEK_SET as _;
+
+    // SAFETY: the descriptor stays open while `file` is borrowed
+    match unsafe { libc::fcntl(file.as_raw_fd(), libc::F_SETLK, &lock) } {
+        -1 => {
+            let e = std::io::Error::last_os_error();
+
+            match e.raw_os_error() {
+                Some(libc::EACCES | libc::EAGAIN) => Ok(false),
+                _ => Err(e),
+            }
+        }
+        _ => Ok(true),
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock(file: &File) -> std::io::Result<bool> {
+    match file.try_lock() {
+        Ok(()) => Ok(true),
+        Err(std::fs::TryLockError::WouldBlock) => Ok(false),
+        Err(std::fs::TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Writes `data` to a new file and flushes it to disk.
+fn write_synced(path: &Path, data: &[u8]) -> Result<(), String> {
+    let result = File::create(path).and_then(|mut v| {
+        v.write_all(data)?;
+        v.sync_all()
+    });
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Error writing {}: {e:?}", path.display())),
+    }
+}
+
+/// Reads the manifest `CURRENT` points to.
+fn read_version(dir: &Path) -> Result<Version, String> {
+    let current = match fs::read_to_string(dir.join("CURRENT")) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Error reading LevelDB CURRENT: {e:?}")),
+    };
+
+    let manifest = match fs::read(dir.join(current.trim())) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Error reading LevelDB manifest: {e:?}")),
+    };
+
+    let mut version = Version::default();
+
+    for edit in read_log(&manifest)? {
+        version.apply(&edit)?;
+    }
+
+    if let Some(v) = &version.comparator {
+        if v != "leveldb.BytewiseComparator" {
+            return Err(format!("Error reading LevelDB: unsupported comparator {v}"));
+        }
+    }
+
+    Ok(version)
+}
+
+/// Where the newest value of a key is stored.
+#[derive(Debug, Clone)]
+enum Location {
+    /// In a data block of a table file, read again when asked for
+    Table { file: u64, block: BlockHandle },
+    /// Written to a log file that wasn't compacted yet, kept in memory
+    Log(Vec<u8>),
+}
+
+/// The newest versions of the keys, deleted ones are `None`.
+type Newest = BTreeMap<Vec<u8>, (u64, Option<Location>)>;
+
+fn keep_newest(newest: &mut Newest, key: &[u8], sequence: u64, location: Option<Location>) {
+    match newest.get(key) {
+        Some((v, _)) if *v >= sequence => {}
+        _ => {
+            newest.insert(key.to_vec(), (sequence, location));
+        }
+    }
+}
+
+/// A LevelDB read once on open. Only the keys and where their values are
+/// stored are kept, values are read again from the tables when needed.
+pub struct LevelDb {
+    dir: PathBuf,
+    version: Version,
+    /// Highest sequence number used, the logs can be ahead of the manifest
+    sequence: u64,
+    records: BTreeMap<Vec<u8>, Location>,
+    /// Size of each log file when it was read
+    logs: BTreeMap<u64, u64>,
+}
+
+impl LevelDb {
+    /// Reads the database in `dir`, usually the `db` folder of a world.
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        let mut db = Self {
+            dir: dir.to_path_buf(),
+            version: read_version(dir)?,
+            sequence: 0,
+            records: BTreeMap::new(),
+            logs: BTreeMap::new(),
+        };
+
+        let mut newest = Newest::new();
+
+        for table in db.version.tables.iter() {
+            db.read_table(table.number, &mut newest)?;
+        }
+
+        for number in db.log_numbers()? {
+            db.read_log_file(number, &mut newest)?;
+        }
+
+        db.sequence = newest
+            .values()
+            .map(|(v, _)| *v)
+            .fold(db.version.last_sequence, u64::max);
+
+        db.records = newest
+            .into_iter()
+            .filter_map(|(k, (_, v))| v.map(|v| (k, v)))
+            .collect();
+
+        Ok(db)
+    }
+
+    /// Picks up what was written since the database was read. Only the
+    /// manifest and the logs that grew are read, the tables only if they
+    /// were compacted or a log was replaced in the meantime.
+    fn refresh(&mut self) -> Result<(), String> {
+        let version = read_version(&self.dir)?;
+        let old = std::mem::replace(&mut self.version, version);
+
+        let mut sizes = BTreeMap::new();
+
+        for number in self.log_numbers()? {
+            match fs::metadata(self.log_path(number)) {
+                Ok(v) => sizes.insert(number, v.len()),
+                Err(e) => return Err(format!("Error reading LevelDB log {number}: {e:?}")),
+            };
+        }
+
+        let replaced = old.tables != self.version.tables
+            || self
+                .logs
+                .iter()
+                .any(|(number, size)| sizes.get(number).is_none_or(|v| v < size));
+
+        if replaced {
+            *self = Self::open(&self.dir)?;
+            return Ok(());
+        }
+
+        let mut newest = Newest::new();
+
+        for (number, size) in sizes {
+            if self.logs.get(&number) != Some(&size) {
+                self.read_log_file(number, &mut newest)?;
+            }
+        }
+
+        self.apply_newer(newest);
+
+        Ok(())
+    }
+
+    /// Takes over the keys of `newest` written after the ones read so far,
+    /// a log that grew is read again from its start.
+    fn apply_newer(&mut self, newest: Newest) {
+        let read = self.sequence;
+
+        for (key, (sequence, location)) in newest {
+            if sequence <= read {
+                continue;
+            }
+
+            self.sequence = self.sequence.max(sequence);
+
+            match location {
+                Some(v) => self.records.insert(key, v),
+                None => self.records.remove(&key),
+            };
+        }
+
+        self.sequence = self.sequence.max(self.version.last_sequence);
+    }
+
+    /// Path of a table file, older versions named them `.sst`.
+    fn table_path(&self, number: u64) -> PathBuf {
+        let path = self.dir.join(format!("{number:06}.ldb"));
+
+        match path.exists() {
+            true => path,
+            false => self.dir.join(format!("{number:06}.sst")),
+        }
+    }
+
+    fn log_path(&self, number: u64) -> PathBuf {
+        self.dir.join(format!("{number:06}.log"))
+    }
+
+    /// Numbers of the log files that weren't compacted into tables yet, oldest first.
+    fn log_numbers(&self) -> Result<Vec<u64>, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("Error reading LevelDB folder: {e:?}")),
+        };
+
+        let mut numbers: Vec<u64> = entries
+            .filter_map(|v| v.ok())
+            .filter_map(|v| {
+                let name = v.file_name().to_string_lossy().to_string();
+                name.strip_suffix(".log")?.parse().ok()
+            })
+            .filter(|v| *v >= self.version.log_number || *v == self.version.prev_log_number)
+            .collect();
+
+        numbers.sort();
+
+        Ok(numbers)
+    }
+
+    fn read_table(&self, number: u64, newest: &mut Newest) -> Result<(), String> {
+        let path = self.table_path(number);
+
+        let data = match fs::read(&path) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("Error reading LevelDB table {number}: {e:?}")),
+        };
+
+        let Some(footer_start) = data.len().checked_sub(FOOTER_SIZE) else {
+            return Err(format!(
+                "Error reading LevelDB table {number}: file too short"
+            ));
+        };
+
+        let footer = &data[footer_start..];
+
+        if Reader::new(&footer[FOOTER_SIZE - 8..]).u64()? != TABLE_MAGIC {
+            return Err(format!("Error reading LevelDB table {number}: not a table"));
+        }
+
+        let mut reader = Reader::new(footer);
+        let _meta_index = BlockHandle::read(&mut reader)?;
+        let index = BlockHandle::read(&mut reader)?;
+
+        let index = table_block(&data, index)?;
+
+        for (_, handle) in block_entries(&index)? {
+            let handle = BlockHandle::read(&mut Reader::new(handle))?;
+
+            for (key, _) in block_entries(&table_block(&data, handle)?)? {
+                let (key, sequence, kind) = split_internal_key(&key)?;
+
+                let location = match kind {
+                    VALUE_SET => Some(Location::Table {
+                        file: number,
+                        block: handle,
+                    }),
+                    _ => None,
+                };
+
+                keep_newest(newest, key, sequence, location);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replays the write batches of a log file.
+    fn read_log_file(&mut self, number: u64, newest: &mut Newest) -> Result<(), String> {
+        let data = match fs::read(self.log_path(number)) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("Error reading LevelDB log {number}: {e:?}")),
+        };
+
+        self.logs.insert(number, data.len() as u64);
+
+        for batch in read_log(&data)? {
+            let mut reader = Reader::new(&batch);
+            let sequence = reader.u64()?;
+            let count = reader.u32()?;
+
+            for i in 0..count as u64 {
+                match reader.bytes(1)?[0] {
+                    VALUE_SET => {
+                        let key = reader.slice()?;
+                        let value = reader.slice()?;
+
+                        keep_newest(
+                            newest,
+                            key,
+                            sequence + i,
+                            Some(Location::Log(value.to_vec())),
+                        );
+                    }
+                    VALUE_DELETION => keep_newest(newest, reader.slice()?, sequence + i, None),
+                    v => {
+                        return Err(format!(
+                            "Error reading LevelDB log {number}: unknown value type {v}"
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// All keys in bytewise order.
+    pub fn keys(&self) -> impl Iterator<Item = &[u8]> {
+        self.records.keys().map(|v| v.as_slice())
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.records.contains_key(key)
+    }
+
+    /// Reads the newest value of `key`, `None` if there is no such key.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let (file, block) = match self.records.get(key) {
+            None => return Ok(None),
+            Some(Location::Log(v)) => return Ok(Some(v.clone())),
+            Some(Location::Table { file, block }) => (*file, *block),
+        };
+
+        let mut data = vec![0; block.size as usize + BLOCK_TRAILER_SIZE];
+
+        let read = File::open(self.table_path(file)).and_then(|mut v| {
+            v.seek(SeekFrom::Start(block.offset))?;
+            v.read_exact(&mut data)
+        });
+
+        if let Err(e) = read {
+            return Err(format!("Error reading LevelDB table {file}: {e:?}"));
+        }
+
+        // Versions of a key are sorted newest first, so the first one is the one kept
+        for (internal, value) in block_entries(&unpack_block(&data)?)? {
+            if split_internal_key(&internal)?.0 == key {
+                return Ok(Some(value.to_vec()));
+            }
+        }
+
+        Err(format!("Error reading LevelDB table {file}: key vanished"))
+    }
+
+    /// Holds the database so nothing else writes it, until the lock is dropped.
+    pub fn lock(&self) -> Result<DbLock, String> {
+        DbLock::acquire(&self.dir)
+    }
+
+    /// Writes `batch` to a new log file and points a new manifest at it, then
+    /// reads the new log and manifest back to check every written key. Fails without
+    /// writing anything while the game or a server has the world open.
+    pub fn write(&mut self, batch: &WriteBatch) -> Result<(), String> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let lock = self.lock()?;
+        self.write_locked(batch, &lock)
+    }
+
+    /// Like [`Self::write`], with the lock taken by [`Self::lock`] beforehand.
+    pub fn write_locked(&mut self, batch: &WriteBatch, _lock: &DbLock) -> Result<(), String> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        // The game or a restored backup may have changed the files since they were read
+        self.refresh()?;
+
+        // The game numbers its files after the newest log it finds
+        let log = self
+            .log_numbers()?
+            .last()
+            .map_or(0, |v| v + 1)
+            .max(self.version.next_file);
+        let manifest = log + 1;
+
+        let mut version = self.version.clone();
+        version.next_file = manifest + 1;
+        version.last_sequence = self.sequence + batch.len() as u64;
+
+        // A batch is one log record, a torn write drops all of it
+        let record = batch.encode(self.sequence + 1);
+        write_synced(&self.log_path(log), &encode_log(&[&record]))?;
+
+        let snapshot = version.snapshot();
+        write_synced(
+            &self.dir.join(format!("MANIFEST-{manifest:06}")),
+            &encode_log(&[&snapshot]),
+        )?;
+
+        // Renaming replaces CURRENT at once, like LevelDB does
+        let temp = self.dir.join(format!("{manifest:06}.dbtmp"));
+        write_synced(&temp, format!("MANIFEST-{manifest:06}\n").as_bytes())?;
+
+        if let Err(e) = fs::rename(&temp, self.dir.join("CURRENT")) {
+            return Err(format!("Error replacing LevelDB CURRENT: {e:?}"));
+        }
+
+        if read_version(&self.dir)? != version {
+            return Err(String::from(
+                "Error verifying LevelDB write: the manifest doesn't read back as written",
+            ));
+        }
+
+        self.version = version;
+
+        let mut newest = Newest::new();
+        self.read_log_file(log, &mut newest)?;
+        self.apply_newer(newest);
+
+        for (key, value) in batch.changes.iter() {
+            if self.get(key)? != *value {
+                return Err(format!(
+                    "Error verifying LevelDB write: {} doesn't read back as written",
+                    String::from_utf8_lossy(key)
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    /// An empty database like LevelDB creates it.
+    fn create_db(dir: &Path) {
+        let version = Version {
+            comparator: Some(String::from("leveldb.BytewiseComparator")),
+            next_file: 2,
+            ..Version::default()
+        };
+
+        fs::write(dir.join("CURRENT"), "MANIFEST-000001\n").unwrap();
+        fs::write(
+            dir.join("MANIFEST-000001"),
+            encode_log(&[&version.snapshot()]),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn crc_is_crc32c_masked() {
+        // Test vector of the LevelDB sources
+        assert_eq!(crc32c::crc32c(&[0; 32]), 0x8a9136aa);
+        assert_eq!(
+            masked_crc(&[0; 32]),
+            0x8a9136aa_u32.rotate_right(15).wrapping_add(0xa282ead8)
+        );
+    }
+
+    #[test]
+    fn log_records_span_blocks() {
+        let small = vec![1; 100];
+        let large = vec![2; LOG_BLOCK_SIZE * 2 + 10];
+        // Leaves less than a header at the end of the first block
+        let filler = vec![3; LOG_BLOCK_SIZE - 2 * LOG_HEADER_SIZE - small.len() - 3];
+
+        let records: [&[u8]; 4] = [&small, &filler, &large, &small];
+        let data = encode_log(&records);
+
+        assert_eq!(read_log(&data).unwrap(), records);
+    }
+
+    #[test]
+    fn torn_log_record_is_dropped() {
+        let data = encode_log(&[b"kept", b"torn"]);
+
+        assert_eq!(
+            read_log(&data[..data.len() - 1]).unwrap(),
+            [b"kept".to_vec()]
+        );
+    }
+
+    #[test]
+    fn corrupt_log_record_fails() {
+        let mut data = encode_log(&[b"record"]);
+        data[LOG_HEADER_SIZE] ^= 1;
+
+        assert!(read_log(&data).is_err());
+    }
+
+    #[test]
+    fn writes_read_back_after_reopening() {
+        let dir = TempDir::new("leveldb-write");
+        create_db(&dir.0);
+
+        let mut db = LevelDb::open(&dir.0).unwrap();
+        assert!(db.is_empty());
+
+        let mut batch = WriteBatch::new();
+        batch.put(b"a".to_vec(), b"1".to_vec());
+        batch.put(b"b".to_vec(), vec![0; LOG_BLOCK_SIZE]);
+        db.write(&batch).unwrap();
+
+        let mut batch = WriteBatch::new();
+        batch.put(b"a".to_vec(), b"2".to_vec());
+        batch.delete(b"b".to_vec());
+        batch.put(b"c".to_vec(), b"3".to_vec());
+        db.write(&batch).unwrap();
+
+        let db = LevelDb::open(&dir.0).unwrap();
+
+        assert_eq!(db.keys().collect::<Vec<_>>(), [b"a", b"c"]);
+        assert_eq!(db.get(b"a").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(db.get(b"b").unwrap(), None);
+        assert_eq!(db.sequence, 5);
+    }
+
+    #[test]
+    fn writes_pick_up_other_writers() {
+        let dir = TempDir::new("leveldb-refresh");
+        create_db(&dir.0);
+
+        let mut db = LevelDb::open(&dir.0).unwrap();
+        let mut other = LevelDb::open(&dir.0).unwrap();
+
+        let mut batch = WriteBatch::new();
+        batch.put(b"a".to_vec(), b"1".to_vec());
+        db.write(&batch).unwrap();
+
+        let mut batch = WriteBatch::new();
+        batch.put(b"b".to_vec(), b"2".to_vec());
+        batch.delete(b"a".to_vec());
+        other.write(&batch).unwrap();
+
+        let mut batch = WriteBatch::new();
+        batch.put(b"c".to_vec(), b"3".to_vec());
+        db.write(&batch).unwrap();
+
+        assert_eq!(db.keys().collect::<Vec<_>>(), [b"b", b"c"]);
+        assert_eq!(db.sequence, 4);
+
+        let reopened = LevelDb::open(&dir.0).unwrap();
+
+        assert_eq!(reopened.keys().collect::<Vec<_>>(), [b"b", b"c"]);
+        assert_eq!(reopened.sequence, 4);
+    }
+}
@@ -0,0 +1,24 @@
+//! Reading, editing and writing Bedrock Nbt files, without the editor.
+//! Everything the GUI and the command line share lives here.
+
+pub mod backup;
+pub mod detect;
+pub mod document;
+pub mod history;
+pub mod json;
+pub mod leveldb;
+pub mod nbt_decode;
+pub mod nbt_diff;
+pub mod nbt_edit;
+pub mod nbt_encode;
+pub mod nbt_merge;
+pub mod nbt_path;
+pub mod nbt_query;
+pub mod nbt_search;
+pub mod pack;
+pub mod snbt;
+pub mod subchunk;
+pub mod world_key;
+
+#[cfg(test)]
+mod temp_dir;
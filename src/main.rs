@@ -1,53 +1,348 @@
 #![windows_subsystem = "windows"]
 
-use iced::widget::text;
-use iced::{Element, Sandbox, Settings};
+use std::path::PathBuf;
 
+use beditor::pack;
+use iced::keyboard::{Key, Modifiers};
+use iced::widget::{Button, Column, Row, Text};
+use iced::{
+    event, executor, keyboard, window, Application, Command, Element, Event, Settings,
+    Subscription, Theme,
+};
+
+use crate::config::{Config, Recent, RecentKind};
+use crate::diff_view::DiffView;
+use crate::merge_view::MergeView;
 use crate::messages::BEditorMessage;
 use crate::nbt_view::NbtView;
+use crate::pack_view::PackView;
+use crate::start_view::StartView;
 use crate::state::BEditorState;
 use crate::view::BEditorView;
+use crate::world_view::WorldView;
 
+mod config;
+mod diff_view;
+mod merge_view;
 mod messages;
+mod nbt_input;
+mod nbt_rows;
 mod nbt_view;
+mod pack_view;
+mod start_view;
 pub mod state;
 mod view;
+mod world_rows;
+mod world_view;
 
+/// The command line is the `beditor-cli` program.
 pub fn main() -> iced::Result {
     App::run(Settings::default())
 }
 
+/// Change that drops unsaved edits and waits for confirmation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Discard {
+    /// Replace the active tab with the start screen
+    GoHome,
+    CloseTab(usize),
+}
+
 struct App {
-    state: BEditorState,
+    tabs: Vec<BEditorState>,
+    active: usize,
+    /// Asking whether to drop the unsaved changes of a tab
+    discard: Option<Discard>,
 }
 
-impl Sandbox for App {
-    type Message = BEditorMessage;
+impl App {
+    fn active(&mut self) -> &mut BEditorState {
+        &mut self.tabs[self.active]
+    }
+
+    fn apply(&mut self, discard: Discard) {
+        self.discard = None;
+
+        match discard {
+            Discard::GoHome => *self.active() = BEditorState::Idle(StartView::new()),
+            Discard::CloseTab(i) => {
+                self.tabs.remove(i);
+
+                if self.tabs.is_empty() {
+                    self.tabs.push(BEditorState::Idle(StartView::new()));
+                }
+
+                if self.active > i || self.active >= self.tabs.len() {
+                    self.active = self.active.saturating_sub(1);
+                }
+            }
+        }
+    }
+
+    /// Runs `discard` right away if nothing would be lost, otherwise asks first.
+    fn request(&mut self, discard: Discard) {
+        let tab = match discard {
+            Discard::GoHome => self.active,
+            Discard::CloseTab(i) => i,
+        };
+
+        match self.tabs.get(tab) {
+            Some(v) if v.is_dirty() => {
+                self.active = tab;
+                self.discard = Some(discard);
+            }
+            Some(_) => self.apply(discard),
+            None => {}
+        }
+    }
+
+    /// Opens `recent` in a new tab, or in the active one if it shows the start screen.
+    fn open(&mut self, recent: Recent) {
+        let state = match recent.kind {
+            RecentKind::Nbt => {
+                let mut view = NbtView::new();
+                view.open_path(&recent.path);
 
-    fn new() -> Self {
-        Self {
-            state: BEditorState::NbtView(NbtView::new()),
+                if view.loaded_path().is_some() {
+                    remember(recent);
+                }
+
+                BEditorState::NbtView(view)
+            }
+            RecentKind::World => {
+                let mut view = WorldView::new();
+                view.open_path(&recent.path);
+
+                if view.loaded_path().is_some() {
+                    remember(recent);
+                }
+
+                BEditorState::WorldView(Box::new(view))
+            }
+            RecentKind::Pack => {
+                let mut view = PackView::new();
+                view.open_path(&recent.path);
+
+                if view.loaded_path().is_some() {
+                    remember(recent);
+                }
+
+                BEditorState::PackView(view)
+            }
+        };
+
+        self.show(state);
+    }
+
+    /// Shows `state` in a new tab, or in the active one if it shows the start screen.
+    fn show(&mut self, state: BEditorState) {
+        match self.active() {
+            BEditorState::Idle(_) => *self.active() = state,
+            _ => {
+                self.tabs.push(state);
+                self.active = self.tabs.len() - 1;
+            }
         }
+
+        self.discard = None;
+    }
+}
+
+/// Adds an entry to the recently opened list of the start screen.
+fn remember(recent: Recent) {
+    let mut config = Config::load();
+    config.add_recent(recent.kind, recent.path);
+
+    // Not being able to store the list shouldn't keep the file from opening
+    let _ = config.save();
+}
+
+impl Application for App {
+    type Executor = executor::Default;
+    type Message = BEditorMessage;
+    type Theme = Theme;
+    type Flags = ();
+
+    fn new(_flags: ()) -> (Self, Command<Self::Message>) {
+        (
+            Self {
+                tabs: vec![BEditorState::Idle(StartView::new())],
+                active: 0,
+                discard: None,
+            },
+            Command::none(),
+        )
     }
 
     fn title(&self) -> String {
-        match self.state {
-            BEditorState::Idle => String::from("BEditor - Idle"),
-            BEditorState::NbtView(_) => String::from("BEditor - Nbt"),
+        let tab = &self.tabs[self.active];
+
+        format!(
+            "BEditor - {}{}",
+            tab.title(),
+            if tab.is_dirty() { "*" } else { "" }
+        )
+    }
+
+    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+        match message {
+            BEditorMessage::GoHome => self.request(Discard::GoHome),
+            // Without a question of the app they answer one of the tab
+            BEditorMessage::DiscardChanges if self.discard.is_some() => {
+                if let Some(v) = self.discard {
+                    self.apply(v);
+                }
+            }
+            BEditorMessage::KeepChanges if self.discard.is_some() => self.discard = None,
+            BEditorMessage::TabSelect(i) => {
+                if i < self.tabs.len() {
+                    self.active = i;
+                    self.discard = None;
+                }
+            }
+            BEditorMessage::TabNew => {
+                self.tabs.push(BEditorState::Idle(StartView::new()));
+                self.active = self.tabs.len() - 1;
+                self.discard = None;
+            }
+            BEditorMessage::TabClose(i) => self.request(Discard::CloseTab(i)),
+            BEditorMessage::Open(v) => self.open(v),
+            BEditorMessage::StartPicked(kind, Some(path)) => self.open(Recent { kind, path }),
+            BEditorMessage::StartCompare => self.show(BEditorState::DiffView(DiffView::new())),
+            BEditorMessage::StartMerge => self.show(BEditorState::MergeView(MergeView::new())),
+            BEditorMessage::FileDropped(path) => self.open(dropped(path)),
+            message => {
+                let opens = matches!(
+                    message,
+                    BEditorMessage::NbtViewOpen
+                        | BEditorMessage::NbtViewFilePicked(_)
+                        | BEditorMessage::WorldViewOpen
+                        | BEditorMessage::WorldViewFolderPicked(_)
+                        | BEditorMessage::PackViewOpen
+                        | BEditorMessage::PackViewFolderPicked(_)
+                );
+
+                let tab = self.active();
+                let command = tab.update(message);
+
+                let loaded = match (opens, tab) {
+                    (true, BEditorState::NbtView(v)) => {
+                        v.loaded_path().map(|v| (RecentKind::Nbt, v))
+                    }
+                    (true, BEditorState::WorldView(v)) => {
+                        v.loaded_path().map(|v| (RecentKind::World, v))
+                    }
+                    (true, BEditorState::PackView(v)) => {
+                        v.loaded_path().map(|v| (RecentKind::Pack, v))
+                    }
+                    _ => None,
+                };
+
+                if let Some((kind, path)) = loaded {
+                    remember(Recent { kind, path });
+                }
+
+                return command;
+            }
+        }
+
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, Self::Message> {
+        let mut bar = Row::new();
+
+        for (i, tab) in self.tabs.iter().enumerate() {
+            let title = format!(
+                "{}{}{}",
+                if i == self.active { "> " } else { "" },
+                tab.title(),
+                if tab.is_dirty() { "*" } else { "" }
+            );
+
+            bar = bar
+                .push(Button::new(Text::new(title)).on_press(BEditorMessage::TabSelect(i)))
+                .push(Button::new(Text::new("x")).on_press(BEditorMessage::TabClose(i)));
+        }
+
+        bar = bar
+            .push(Button::new(Text::new("+")).on_press(BEditorMessage::TabNew))
+            .push(Button::new(Text::new("Home")).on_press(BEditorMessage::GoHome));
+
+        let mut col = Column::new().push(bar);
+
+        if self.discard.is_some() {
+            col = col.push(
+                Row::new()
+                    .push(Text::new(format!(
+                        "{} has unsaved changes, discard them?",
+                        self.tabs[self.active].title()
+                    )))
+                    .push(
+                        Button::new(Text::new("Discard")).on_press(BEditorMessage::DiscardChanges),
+                    )
+                    .push(Button::new(Text::new("Cancel")).on_press(BEditorMessage::KeepChanges)),
+            );
+        }
+
+        col.push(self.tabs[self.active].view()).into()
+    }
+
+    fn subscription(&self) -> Subscription<Self::Message> {
+        event::listen_with(handle_event)
+    }
+}
+
+/// Maps window events to app wide messages, shortcuts also apply while an input is focused.
+/// What a dropped path is opened as. Folders are packs or worlds, the
+/// level.dat of a world opens the whole world and other files are Nbt.
+fn dropped(path: PathBuf) -> Recent {
+    if path.is_dir() {
+        let kind = match pack::is_pack(&path) {
+            true => RecentKind::Pack,
+            false => RecentKind::World,
+        };
+
+        return Recent { kind, path };
+    }
+
+    match path.parent() {
+        Some(dir)
+            if path.file_name().is_some_and(|v| v == "level.dat") && dir.join("db").is_dir() =>
+        {
+            Recent {
+                kind: RecentKind::World,
+                path: dir.to_path_buf(),
+            }
         }
+        _ => Recent {
+            kind: RecentKind::Nbt,
+            path,
+        },
     }
+}
 
-    fn update(&mut self, message: Self::Message) {
-        match &mut self.state {
-            BEditorState::Idle => {}
-            BEditorState::NbtView(v) => v.update(message),
+fn handle_event(event: Event, _status: event::Status) -> Option<BEditorMessage> {
+    match event {
+        Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+            shortcut(key, modifiers)
         }
+        Event::Window(_, window::Event::FileDropped(v)) => Some(BEditorMessage::FileDropped(v)),
+        _ => None,
+    }
+}
+
+fn shortcut(key: Key, modifiers: Modifiers) -> Option<BEditorMessage> {
+    if !modifiers.command() {
+        return None;
     }
 
-    fn view(&self) -> Element<Self::Message> {
-        match &self.state {
-            BEditorState::Idle => text("Idle").into(),
-            BEditorState::NbtView(v) => v.view(),
+    match key.as_ref() {
+        Key::Character(c) if c.eq_ignore_ascii_case("z") && modifiers.shift() => {
+            Some(BEditorMessage::Redo)
         }
+        Key::Character(c) if c.eq_ignore_ascii_case("z") => Some(BEditorMessage::Undo),
+        Key::Character(c) if c.eq_ignore_ascii_case("y") => Some(BEditorMessage::Redo),
+        _ => None,
     }
 }
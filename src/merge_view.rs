@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use beditor::backup;
+use beditor::document::{NbtDocument, NbtEndian, NbtHeader};
+use beditor::nbt_merge;
+use beditor::nbt_merge::NbtMerge;
+use beditor::nbt_path::NbtPath;
+use iced::widget::{Button, Column, PickList, Row, Scrollable, Text, TextInput};
+use iced::{theme, Alignment, Command, Element, Length};
+
+use crate::diff_view;
+use crate::messages::BEditorMessage;
+use crate::nbt_input::NbtInput;
+use crate::nbt_view::ERROR_COLOR;
+use crate::view::BEditorView;
+
+/// One of the three files of a merge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MergeInput {
+    /// The version both sides started from
+    Base,
+    Ours,
+    Theirs,
+}
+
+impl MergeInput {
+    pub const ALL: [MergeInput; 3] = [MergeInput::Base, MergeInput::Ours, MergeInput::Theirs];
+}
+
+impl std::fmt::Display for MergeInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                MergeInput::Base => "Base",
+                MergeInput::Ours => "Ours",
+                MergeInput::Theirs => "Theirs",
+            }
+        )
+    }
+}
+
+/// Merges the changes two versions made to a common base, the conflicts are
+/// resolved by hand before the result is saved.
+pub struct MergeView {
+    base: NbtInput,
+    ours: NbtInput,
+    theirs: NbtInput,
+    merge: Option<NbtMerge>,
+    /// Version picked for each conflict
+    choices: HashMap<NbtPath, MergeInput>,
+    save_path: String,
+    endian: NbtEndian,
+    header: NbtHeader,
+    /// Outcome of the last save
+    status: Option<Result<String, String>>,
+    /// Whether the resolved merge was written since the last choice
+    saved: bool,
+}
+
+impl MergeView {
+    fn input(&self, input: MergeInput) -> &NbtInput {
+        match input {
+            MergeInput::Base => &self.base,
+            MergeInput::Ours => &self.ours,
+            MergeInput::Theirs => &self.theirs,
+        }
+    }
+
+    fn input_mut(&mut self, input: MergeInput) -> &mut NbtInput {
+        match input {
+            MergeInput::Base => &mut self.base,
+            MergeInput::Ours => &mut self.ours,
+            MergeInput::Theirs => &mut self.theirs,
+        }
+    }
+
+    /// Merges again after one of the files was read, keeping the choices
+    /// for conflicts that are still there.
+    fn remerge(&mut self) {
+        self.merge = match (self.base.tag(), self.ours.tag(), self.theirs.tag()) {
+            (Some(base), Some(ours), Some(theirs)) => Some(nbt_merge::merge(base, ours, theirs)),
+            _ => None,
+        };
+
+        let conflicts = self.merge.as_ref().map(|v| &v.conflicts);
+        self.choices
+            .retain(|k, _| conflicts.is_some_and(|v| v.contains(k)));
+
+        // Save in the format of our version unless another one is picked
+        if let Some(v) = self.ours.document() {
+            self.endian = v.endian;
+            self.header = v.header;
+        }
+
+        self.saved = false;
+    }
+
+    /// The merged document with the picked versions of the conflicts.
+    fn resolved(&self) -> Result<NbtDocument, String> {
+        let Some(merge) = &self.merge else {
+            return Err(String::from("Open all three files first"));
+        };
+
+        let mut tag = merge.merged.clone();
+
+        for path in merge.conflicts.iter() {
+            let Some(input) = self.choices.get(path) else {
+                return Err(format!("The conflict at {path} isn't resolved yet"));
+            };
+
+            let Some(from) = self.input(*input).tag() else {
+                return Err(format!("{input} isn't loaded"));
+            };
+
+            nbt_merge::resolve(&mut tag, path, from)?;
+        }
+
+        let Some(ours) = self.ours.document() else {
+            return Err(String::from("Ours isn't loaded"));
+        };
+
+        // The first header field is the same for all versions of a file
+        let header_values = MergeInput::ALL
+            .iter()
+            .find_map(|v| self.input(*v).document().and_then(|v| v.header_values));
+
+        Ok(NbtDocument {
+            name: ours.name.clone(),
+            tag,
+            endian: self.endian,
+            header: self.header,
+            header_values,
+            layout: ours.layout.clone(),
+        })
+    }
+
+    fn save(&mut self) -> Result<String, String> {
+        let mut document = self.resolved()?;
+
+        backup::snapshot_level_dat(Path::new(&self.save_path))?;
+        document
+            .save(Path::new(&self.save_path))
+            .map_err(|e| e.to_string())?;
+
+        self.saved = true;
+
+        Ok(format!("Saved to {}", self.save_path))
+    }
+
+    fn conflict2element(&self, path: &NbtPath) -> Element<'_, BEditorMessage> {
+        let mut row = Row::new().push(Text::new(path.to_string()).width(Length::Fill));
+
+        for input in MergeInput::ALL {
+            let tag = self.input(input).tag().and_then(|v| path.get(v));
+
+            let style = match self.choices.get(path) == Some(&input) {
+                true => theme::Button::Primary,
+                false => theme::Button::Secondary,
+            };
+
+            row = row.push(
+                Button::new(Text::new(format!("{input}: {}", diff_view::summary(tag))))
+                    .style(style)
+                    .on_press(BEditorMessage::MergeViewResolve(path.clone(), input))
+                    .width(Length::Fill),
+            );
+        }
+
+        row.spacing(8).align_items(Alignment::Center).into()
+    }
+}
+
+impl BEditorView for MergeView {
+    fn new() -> Self {
+        Self {
+            base: NbtInput::new(),
+            ours: NbtInput::new(),
+            theirs: NbtInput::new(),
+            merge: None,
+            choices: HashMap::new(),
+            save_path: String::new(),
+            endian: NbtEndian::default(),
+            header: NbtHeader::default(),
+            status: None,
+            saved: false,
+        }
+    }
+
+    fn update(&mut self, message: BEditorMessage) -> Command<BEditorMessage> {
+        match message {
+            BEditorMessage::MergeViewInput(input, message) => {
+                let (loaded, command) = self
+                    .input_mut(input)
+                    .update(message, move |v| BEditorMessage::MergeViewInput(input, v));
+
+                if loaded {
+                    self.remerge();
+                }
+
+                return command;
+            }
+            BEditorMessage::MergeViewResolve(path, input) => {
+                self.choices.insert(path, input);
+                self.saved = false;
+            }
+            BEditorMessage::MergeViewSetSavePath(v) => self.save_path = v,
+            BEditorMessage::MergeViewSetEndian(v) => self.endian = v,
+            BEditorMessage::MergeViewSetHeader(v) => self.header = v,
+            BEditorMessage::MergeViewSave => self.status = Some(self.save()),
+            // Handled by the app or other views
+            _ => {}
+        }
+
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, BEditorMessage> {
+        let mut inputs = Row::new().spacing(16);
+
+        for input in MergeInput::ALL {
+            inputs = inputs.push(self.input(input).view(&input.to_string(), false, move |v| {
+                BEditorMessage::MergeViewInput(input, v)
+            }));
+        }
+
+        let mut conflicts = Column::new();
+
+        let summary = match &self.merge {
+            Some(merge) => {
+                for path in merge.conflicts.iter() {
+                    conflicts = conflicts.push(self.conflict2element(path));
+                }
+
+                format!(
+                    "{} changes merged, {} of {} conflicts resolved",
+                    merge.applied,
+                    merge
+                        .conflicts
+                        .iter()
+                        .filter(|v| self.choices.contains_key(*v))
+                        .count(),
+                    merge.conflicts.len()
+                )
+            }
+            None => String::from("Open the base and both changed versions to merge them"),
+        };
+
+        Column::new()
+            .push(inputs)
+            .push(Text::new(summary))
+            .push(
+                Row::new()
+                    .push(
+                        TextInput::new("Save Merged As", &self.save_path)
+                            .on_input(BEditorMessage::MergeViewSetSavePath)
+                            .on_submit(BEditorMessage::MergeViewSave),
+                    )
+                    .push(PickList::new(
+                        &NbtEndian::ALL[..],
+                        Some(self.endian),
+                        BEditorMessage::MergeViewSetEndian,
+                    ))
+                    .push(PickList::new(
+                        &NbtHeader::ALL[..],
+                        Some(self.header),
+                        BEditorMessage::MergeViewSetHeader,
+                    ))
+                    .push(Button::new(Text::new("Save")).on_press(BEditorMessage::MergeViewSave)),
+            )
+            .push(match &self.status {
+                None => Text::new(""),
+                Some(Ok(v)) => Text::new(v.clone()),
+                Some(Err(e)) => Text::new(e.clone()).style(ERROR_COLOR),
+            })
+            .push(
+                Scrollable::new(conflicts)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .width(Length::Fill)
+            .into()
+    }
+
+    fn title(&self) -> String {
+        match self.ours.tag() {
+            Some(_) => format!("Merge {}", self.ours.file_name()),
+            None => String::from("Merge"),
+        }
+    }
+
+    fn is_dirty(&self) -> bool {
+        !self.choices.is_empty() && !self.saved
+    }
+}
@@ -1,9 +1,137 @@
-use crate::nbt_view::{NbtEndian, NbtHeader};
+use std::path::PathBuf;
+
+use crate::config::{Recent, RecentKind};
+use crate::diff_view::DiffSide;
+use crate::merge_view::MergeInput;
+use crate::nbt_input::NbtInputMessage;
+use crate::world_rows::WorldGroup;
+use beditor::backup::Backup;
+use beditor::document::{NbtEndian, NbtHeader};
+use beditor::nbt_edit::NbtTagKind;
+use beditor::nbt_path::NbtPath;
+use beditor::nbt_search::{KindFilter, SearchMode};
 
 #[derive(Debug, Clone)]
 pub enum BEditorMessage {
     NbtViewSetPath(String),
+    /// Read the file at the typed path
+    NbtViewOpen,
+    /// Choose the file to open with the native file dialog
+    NbtViewPickFile,
+    NbtViewFilePicked(Option<PathBuf>),
     NbtViewSetEndian(NbtEndian),
     NbtViewSetHeader(NbtHeader),
     NbtViewRefresh,
+    /// Guess the endian and header from the contents of the file
+    NbtViewDetect,
+    /// Edit the scalar at the path, the value is the raw text of its input
+    NbtViewEditValue(NbtPath, String),
+    /// Enter in a value input, the next keystroke starts a new undo step
+    NbtViewSubmitValue,
+    /// Write the Nbt back to the file it was read from
+    NbtViewSave,
+    NbtViewSetSavePath(String),
+    NbtViewSaveAs,
+    NbtViewSetInsertKind(NbtTagKind),
+    /// Add a new child to the compound or list at the path
+    NbtViewInsert(NbtPath),
+    NbtViewRemove(NbtPath),
+    NbtViewDuplicate(NbtPath),
+    /// Move the list element or compound entry at the path to the given position
+    NbtViewMove(NbtPath, usize),
+    NbtViewStartRename(NbtPath),
+    NbtViewRenameInput(String),
+    NbtViewRenameSubmit,
+    NbtViewToggleExpand(NbtPath),
+    NbtViewExpandAll,
+    NbtViewCollapseAll,
+    /// Expand every compound and list above the given depth, collapsing the rest
+    NbtViewExpandToDepth(usize),
+    /// Copy the tag at the path to the clipboard as SNBT
+    NbtViewCopySnbt(NbtPath),
+    /// Copy the path of the tag in the form the search and command line take
+    NbtViewCopyPath(NbtPath),
+    /// Paste SNBT from the clipboard over the tag at the path, or into it if the flag is set
+    NbtViewPaste(NbtPath, bool),
+    NbtViewPasted(NbtPath, bool, Option<String>),
+    /// Write the document as JSON to a file chosen with the native file dialog
+    NbtViewExportJson,
+    NbtViewExportJsonTo(Option<PathBuf>),
+    /// Replace the document with one read from a JSON export
+    NbtViewImportJson,
+    NbtViewImportJsonFrom(Option<PathBuf>),
+    NbtViewToggleHex,
+    /// Highlight the bytes of the tag at the path in the hex view
+    NbtViewSelect(NbtPath),
+    /// Select the tag owning the byte at the offset
+    NbtViewSelectByte(usize),
+    NbtViewHexScrolled(f32, f32),
+    NbtViewSearch(String),
+    NbtViewSearchMode(SearchMode),
+    NbtViewSearchKind(KindFilter),
+    /// Jump to the next (true) or previous search hit
+    NbtViewSearchJump(bool),
+    /// Only show search hits and their ancestors
+    NbtViewSearchFilter(bool),
+    /// Vertical scroll offset and height of the tree viewport
+    NbtViewScrolled(f32, f32),
+    /// A file was dropped onto the window
+    FileDropped(PathBuf),
+    /// Show the start screen in the active tab
+    GoHome,
+    /// Go on even though the active tab has unsaved changes, or the file
+    /// shown inside it if the tab asked
+    DiscardChanges,
+    /// Keep the active tab after being asked about its unsaved changes
+    KeepChanges,
+    TabSelect(usize),
+    TabNew,
+    TabClose(usize),
+    /// Open a file or world in the matching view
+    Open(Recent),
+    StartPickNbt,
+    StartPickWorld,
+    StartPickPack,
+    StartPicked(RecentKind, Option<PathBuf>),
+    /// Copy a snapshot back into its world
+    StartRestore(Backup),
+    /// Open a new diff view
+    StartCompare,
+    DiffViewInput(DiffSide, NbtInputMessage),
+    /// Make the tag at the path on the given side the same as on the other one
+    DiffViewCopy(NbtPath, DiffSide),
+    /// Open a new merge view
+    StartMerge,
+    MergeViewInput(MergeInput, NbtInputMessage),
+    /// Take the version of the conflicting tag at the path from the given file
+    MergeViewResolve(NbtPath, MergeInput),
+    MergeViewSetSavePath(String),
+    MergeViewSetEndian(NbtEndian),
+    MergeViewSetHeader(NbtHeader),
+    MergeViewSave,
+    WorldViewSetPath(String),
+    /// Read the world folder at the typed path
+    WorldViewOpen,
+    WorldViewPickFolder,
+    WorldViewFolderPicked(Option<PathBuf>),
+    /// Only list the keys containing the text
+    WorldViewFilter(String),
+    /// Vertical scroll offset and height of the key list viewport
+    WorldViewScrolled(f32, f32),
+    /// Show or hide the keys of a dimension, chunk or category
+    WorldViewToggleGroup(WorldGroup),
+    /// Show the record stored under the key
+    WorldViewSelect(Vec<u8>),
+    WorldViewSelectLevelDat,
+    /// Show the root tag at the index of a record holding several
+    WorldViewSelectRoot(usize),
+    PackViewSetPath(String),
+    /// Read the pack folder at the typed path
+    PackViewOpen,
+    PackViewPickFolder,
+    PackViewFolderPicked(Option<PathBuf>),
+    /// Show the file at the path relative to the pack folder
+    PackViewSelect(PathBuf),
+    Undo,
+    Redo,
 }
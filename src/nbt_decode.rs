@@ -0,0 +1,772 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use bedrock_rs::nbt::NbtTag;
+
+use crate::document::{NbtEndian, NbtHeader};
+use crate::nbt_path::{NbtPath, NbtPathSegment};
+
+/// Compounds and lists nested deeper than this are rejected instead of overflowing the stack.
+pub const MAX_DEPTH: usize = 512;
+
+/// Bytes kept before and after the offset of a [`DecodeError`].
+pub const ERROR_CONTEXT: usize = 64;
+
+pub const TAG_END: u8 = 0;
+pub const TAG_BYTE: u8 = 1;
+pub const TAG_INT16: u8 = 2;
+pub const TAG_INT32: u8 = 3;
+pub const TAG_INT64: u8 = 4;
+pub const TAG_FLOAT32: u8 = 5;
+pub const TAG_FLOAT64: u8 = 6;
+pub const TAG_STRING: u8 = 8;
+pub const TAG_LIST: u8 = 9;
+pub const TAG_COMPOUND: u8 = 10;
+
+/// Name of a tag type id, `None` for ids Bedrock doesn't use.
+pub fn tag_type_name(id: u8) -> Option<&'static str> {
+    match id {
+        TAG_END => Some("End"),
+        TAG_BYTE => Some("Byte"),
+        TAG_INT16 => Some("Int16"),
+        TAG_INT32 => Some("Int32"),
+        TAG_INT64 => Some("Int64"),
+        TAG_FLOAT32 => Some("Float32"),
+        TAG_FLOAT64 => Some("Float64"),
+        TAG_STRING => Some("String"),
+        TAG_LIST => Some("List"),
+        TAG_COMPOUND => Some("Compound"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeErrorKind {
+    /// The data ended while `needed` more bytes were expected
+    UnexpectedEnd {
+        needed: usize,
+    },
+    /// A tag type byte that isn't allowed where it was found
+    UnexpectedTagType {
+        expected: &'static str,
+        found: u8,
+    },
+    InvalidUtf8,
+    NegativeLength(i32),
+    VarintTooLong,
+    TooDeep,
+}
+
+impl std::fmt::Display for DecodeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeErrorKind::UnexpectedEnd { needed } => {
+                write!(f, "Unexpected end of data, needed {needed} more bytes")
+            }
+            DecodeErrorKind::UnexpectedTagType { expected, found } => match tag_type_name(*found) {
+                Some(name) => write!(f, "Expected {expected} but found {name} ({found})"),
+                None => write!(f, "Expected {expected} but found unknown tag type {found}"),
+            },
+            DecodeErrorKind::InvalidUtf8 => write!(f, "Invalid UTF-8 in string"),
+            DecodeErrorKind::NegativeLength(v) => write!(f, "Negative list length {v}"),
+            DecodeErrorKind::VarintTooLong => write!(f, "Varint too long"),
+            DecodeErrorKind::TooDeep => write!(f, "Nbt nested deeper than {MAX_DEPTH} levels"),
+        }
+    }
+}
+
+/// Where and why decoding stopped, with everything read up to that point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    pub kind: DecodeErrorKind,
+    /// Offset of the first byte that couldn't be decoded
+    pub offset: usize,
+    /// Tag that was being read
+    pub path: NbtPath,
+    /// Root name and the tree decoded before the failure, if the root tag was started
+    pub partial: Option<(String, NbtTag)>,
+    /// Up to [`ERROR_CONTEXT`] bytes on both sides of `offset`
+    pub context: Vec<u8>,
+    /// Offset of the first byte of `context`
+    pub context_start: usize,
+}
+
+impl DecodeError {
+    /// Moves all offsets by `n` bytes, for data that was decoded after a header.
+    pub fn shift(&mut self, n: usize) {
+        self.offset += n;
+        self.context_start += n;
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at offset {} (0x{:x})",
+            self.kind, self.offset, self.offset
+        )?;
+
+        match self.path.depth() {
+            0 => write!(f, " in the root tag"),
+            _ => write!(f, " in {}", self.path),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Byte ranges of one tag in the decoded data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NbtSpan {
+    /// Offset of the tag type byte, list elements have none
+    pub tag_type: Option<usize>,
+    /// Length and bytes of the name, only the root and compound entries have one
+    pub name: Option<Range<usize>>,
+    /// Value of the tag, for compounds including the closing End byte
+    pub payload: Range<usize>,
+}
+
+impl NbtSpan {
+    pub fn start(&self) -> usize {
+        self.tag_type.unwrap_or(self.payload.start)
+    }
+
+    pub fn end(&self) -> usize {
+        self.payload.end
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        (self.start()..self.end()).contains(&offset)
+    }
+
+    fn shift(&mut self, n: usize) {
+        self.tag_type = self.tag_type.map(|v| v + n);
+        self.name = self.name.as_ref().map(|v| v.start + n..v.end + n);
+        self.payload = self.payload.start + n..self.payload.end + n;
+    }
+}
+
+/// What a file stores that the tag tree loses, so the file can be written
+/// back byte for byte.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NbtLayout {
+    /// Keys of each compound in the order they were read
+    pub keys: HashMap<NbtPath, Vec<String>>,
+    /// Element type of each empty list
+    pub empty_lists: HashMap<NbtPath, u8>,
+}
+
+impl NbtLayout {
+    /// Keys of the compound at `path` in the order they were read, the ones
+    /// that weren't read come last, sorted.
+    pub fn ordered_keys<'a>(
+        &self,
+        path: &NbtPath,
+        compound: &'a HashMap<String, NbtTag>,
+    ) -> Vec<&'a String> {
+        let mut keys: Vec<&String> = Vec::with_capacity(compound.len());
+        let mut seen = HashSet::new();
+
+        for key in self.keys.get(path).into_iter().flatten() {
+            // A file may repeat a key, only its last value was kept
+            if let Some((k, _)) = compound.get_key_value(key) {
+                if seen.insert(k) {
+                    keys.push(k);
+                }
+            }
+        }
+
+        let mut rest: Vec<&String> = compound.keys().filter(|k| !seen.contains(k)).collect();
+        rest.sort();
+        keys.extend(rest);
+
+        keys
+    }
+}
+
+/// Reads Nbt in any of the Bedrock encodings while keeping track of how many
+/// bytes were consumed, so a file can be probed for its format and the
+/// damaged part of a file located.
+pub struct NbtDecoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    endian: NbtEndian,
+    /// Tag that is being read
+    path: NbtPath,
+    /// Spans of the tags read so far, parents before their children
+    spans: Option<Vec<(NbtPath, NbtSpan)>>,
+    layout: Option<NbtLayout>,
+}
+
+impl<'a> NbtDecoder<'a> {
+    pub fn new(data: &'a [u8], endian: NbtEndian) -> Self {
+        Self {
+            data,
+            pos: 0,
+            endian,
+            path: NbtPath::root(),
+            spans: None,
+            layout: None,
+        }
+    }
+
+    /// Records the span of every tag that is read, see [`Self::into_spans`].
+    pub fn with_spans(mut self) -> Self {
+        self.spans = Some(Vec::new());
+        self
+    }
+
+    pub fn into_spans(self) -> Vec<(NbtPath, NbtSpan)> {
+        self.spans.unwrap_or_default()
+    }
+
+    /// Records the key order and empty list types, see [`Self::into_layout`].
+    pub fn with_layout(mut self) -> Self {
+        self.layout = Some(NbtLayout::default());
+        self
+    }
+
+    pub fn into_layout(self) -> NbtLayout {
+        self.layout.unwrap_or_default()
+    }
+
+    /// Number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn error_at(&self, offset: usize, kind: DecodeErrorKind) -> Box<DecodeError> {
+        let start = offset.saturating_sub(ERROR_CONTEXT).min(self.data.len());
+        let end = offset.saturating_add(ERROR_CONTEXT).min(self.data.len());
+
+        Box::new(DecodeError {
+            kind,
+            offset,
+            path: self.path.clone(),
+            partial: None,
+            context: self.data[start..end].to_vec(),
+            context_start: start,
+        })
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Box<DecodeError>> {
+        match self.data.get(self.pos..self.pos + n) {
+            Some(v) => {
+                self.pos += n;
+                Ok(v)
+            }
+            None => Err(self.error_at(
+                self.pos,
+                DecodeErrorKind::UnexpectedEnd {
+                    needed: self.pos + n - self.data.len(),
+                },
+            )),
+        }
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], Box<DecodeError>> {
+        let mut buf = [0; N];
+        buf.copy_from_slice(self.take(N)?);
+        Ok(buf)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, Box<DecodeError>> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Reads an unsigned LEB128 varint of at most `max_bytes` bytes.
+    fn read_varint(&mut self, max_bytes: usize) -> Result<u64, Box<DecodeError>> {
+        let start = self.pos;
+        let mut v = 0u64;
+
+        for i in 0..max_bytes {
+            let byte = self.read_u8()?;
+            v |= ((byte & 0x7f) as u64) << (7 * i);
+
+            if byte & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+
+        Err(self.error_at(start, DecodeErrorKind::VarintTooLong))
+    }
+
+    fn read_zigzag32(&mut self) -> Result<i32, Box<DecodeError>> {
+        let v = self.read_varint(5)? as u32;
+        Ok((v >> 1) as i32 ^ -((v & 1) as i32))
+    }
+
+    fn read_zigzag64(&mut self) -> Result<i64, Box<DecodeError>> {
+        let v = self.read_varint(10)?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    fn read_i16(&mut self) -> Result<i16, Box<DecodeError>> {
+        let buf = self.take_array()?;
+
+        Ok(match self.endian {
+            NbtEndian::Big => i16::from_be_bytes(buf),
+            NbtEndian::Little | NbtEndian::LittleNetwork => i16::from_le_bytes(buf),
+        })
+    }
+
+    fn read_i32(&mut self) -> Result<i32, Box<DecodeError>> {
+        match self.endian {
+            NbtEndian::Little => Ok(i32::from_le_bytes(self.take_array()?)),
+            NbtEndian::LittleNetwork => self.read_zigzag32(),
+            NbtEndian::Big => Ok(i32::from_be_bytes(self.take_array()?)),
+        }
+    }
+
+    fn read_i64(&mut self) -> Result<i64, Box<DecodeError>> {
+        match self.endian {
+            NbtEndian::Little => Ok(i64::from_le_bytes(self.take_array()?)),
+            NbtEndian::LittleNetwork => self.read_zigzag64(),
+            NbtEndian::Big => Ok(i64::from_be_bytes(self.take_array()?)),
+        }
+    }
+
+    fn read_f32(&mut self) -> Result<f32, Box<DecodeError>> {
+        let buf = self.take_array()?;
+
+        Ok(match self.endian {
+            NbtEndian::Big => f32::from_be_bytes(buf),
+            NbtEndian::Little | NbtEndian::LittleNetwork => f32::from_le_bytes(buf),
+        })
+    }
+
+    fn read_f64(&mut self) -> Result<f64, Box<DecodeError>> {
+        let buf = self.take_array()?;
+
+        Ok(match self.endian {
+            NbtEndian::Big => f64::from_be_bytes(buf),
+            NbtEndian::Little | NbtEndian::LittleNetwork => f64::from_le_bytes(buf),
+        })
+    }
+
+    pub fn read_string(&mut self) -> Result<String, Box<DecodeError>> {
+        let len = match self.endian {
+            NbtEndian::Little => u16::from_le_bytes(self.take_array()?) as usize,
+            NbtEndian::LittleNetwork => self.read_varint(5)? as usize,
+            NbtEndian::Big => u16::from_be_bytes(self.take_array()?) as usize,
+        };
+
+        let start = self.pos;
+
+        match String::from_utf8(self.take(len)?.to_vec()) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(self.error_at(
+                start + e.utf8_error().valid_up_to(),
+                DecodeErrorKind::InvalidUtf8,
+            )),
+        }
+    }
+
+    fn read_list_len(&mut self) -> Result<usize, Box<DecodeError>> {
+        let start = self.pos;
+        let len = self.read_i32()?;
+
+        match usize::try_from(len) {
+            Ok(v) => Ok(v),
+            Err(_) => Err(self.error_at(start, DecodeErrorKind::NegativeLength(len))),
+        }
+    }
+
+    /// Reads a tag type byte, failing with `expected` for ids Bedrock doesn't use.
+    fn read_tag_type(&mut self, expected: &'static str) -> Result<u8, Box<DecodeError>> {
+        let id = self.read_u8()?;
+
+        match tag_type_name(id) {
+            Some(_) => Ok(id),
+            None => Err(self.error_at(
+                self.pos - 1,
+                DecodeErrorKind::UnexpectedTagType {
+                    expected,
+                    found: id,
+                },
+            )),
+        }
+    }
+
+    /// Reads the payload of a tag whose type was checked by [`Self::read_tag_type`].
+    /// `tag_type` and `name` are where its type and name were read from.
+    fn read_payload(
+        &mut self,
+        id: u8,
+        depth: usize,
+        tag_type: Option<usize>,
+        name: Option<Range<usize>>,
+    ) -> Result<NbtTag, Box<DecodeError>> {
+        let start = self.pos;
+
+        // Pushed before the children so parents come first
+        let index = self.spans.as_mut().map(|spans| {
+            spans.push((
+                self.path.clone(),
+                NbtSpan {
+                    tag_type,
+                    name,
+                    payload: start..start,
+                },
+            ));
+            spans.len() - 1
+        });
+
+        let tag = self.read_value(id, depth)?;
+
+        if let (Some(spans), Some(index)) = (&mut self.spans, index) {
+            spans[index].1.payload.end = self.pos;
+        }
+
+        Ok(tag)
+    }
+
+    fn read_value(&mut self, id: u8, depth: usize) -> Result<NbtTag, Box<DecodeError>> {
+        if depth > MAX_DEPTH {
+            return Err(self.error_at(self.pos, DecodeErrorKind::TooDeep));
+        }
+
+        match id {
+            TAG_BYTE => Ok(NbtTag::Byte(self.read_u8()?)),
+            TAG_INT16 => Ok(NbtTag::Int16(self.read_i16()?)),
+            TAG_INT32 => Ok(NbtTag::Int32(self.read_i32()?)),
+            TAG_INT64 => Ok(NbtTag::Int64(self.read_i64()?)),
+            TAG_FLOAT32 => Ok(NbtTag::Float32(self.read_f32()?)),
+            TAG_FLOAT64 => Ok(NbtTag::Float64(self.read_f64()?)),
+            TAG_STRING => Ok(NbtTag::String(self.read_string()?)),
+            TAG_LIST => self.read_list(depth),
+            TAG_COMPOUND => self.read_compound(depth),
+            _ => Ok(NbtTag::Empty),
+        }
+    }
+
+    fn read_list(&mut self, depth: usize) -> Result<NbtTag, Box<DecodeError>> {
+        let element_start = self.pos;
+        let element = self.read_tag_type("a list element type")?;
+        let len = self.read_list_len()?;
+
+        if element == TAG_END && len > 0 {
+            return Err(self.error_at(
+                element_start,
+                DecodeErrorKind::UnexpectedTagType {
+                    expected: "an element type for a non-empty list",
+                    found: element,
+                },
+            ));
+        }
+
+        if let (Some(layout), 0) = (&mut self.layout, len) {
+            layout.empty_lists.insert(self.path.clone(), element);
+        }
+
+        // Every element takes at least one byte, don't trust the length any further
+        let mut list = Vec::with_capacity(len.min(self.data.len() - self.pos));
+
+        for i in 0..len {
+            self.path.push(NbtPathSegment::Index(i));
+            let tag = self.read_payload(element, depth + 1, None, None);
+            self.path.pop();
+
+            match tag {
+                Ok(v) => list.push(v),
+                Err(mut e) => {
+                    // Keep what was read of the failed element
+                    if let Some((_, v)) = e.partial.take() {
+                        list.push(v);
+                    }
+
+                    e.partial = Some((String::new(), NbtTag::List(list)));
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(NbtTag::List(list))
+    }
+
+    fn read_compound(&mut self, depth: usize) -> Result<NbtTag, Box<DecodeError>> {
+        let mut compound = HashMap::new();
+        let mut keys = Vec::new();
+
+        loop {
+            let type_start = self.pos;
+
+            let entry = self.read_tag_type("a tag type").and_then(|id| match id {
+                TAG_END => Ok(None),
+                id => Ok(Some((id, self.read_string()?))),
+            });
+
+            let (id, name) = match entry {
+                Ok(Some(v)) => v,
+                Ok(None) => break,
+                Err(mut e) => {
+                    e.partial = Some((String::new(), NbtTag::Compound(compound)));
+                    return Err(e);
+                }
+            };
+
+            let name_range = type_start + 1..self.pos;
+
+            if self.layout.is_some() {
+                keys.push(name.clone());
+            }
+
+            self.path.push(NbtPathSegment::Key(name.clone()));
+            let tag = self.read_payload(id, depth + 1, Some(type_start), Some(name_range));
+            self.path.pop();
+
+            match tag {
+                Ok(v) => {
+                    compound.insert(name, v);
+                }
+                Err(mut e) => {
+                    if let Some((_, v)) = e.partial.take() {
+                        compound.insert(name, v);
+                    }
+
+                    e.partial = Some((String::new(), NbtTag::Compound(compound)));
+                    return Err(e);
+                }
+            }
+        }
+
+        if let (Some(layout), false) = (&mut self.layout, keys.is_empty()) {
+            layout.keys.insert(self.path.clone(), keys);
+        }
+
+        Ok(NbtTag::Compound(compound))
+    }
+
+    /// Reads a named root tag.
+    pub fn read_root(&mut self) -> Result<(String, NbtTag), Box<DecodeError>> {
+        let id = self.read_tag_type("a root tag type")?;
+
+        if id == TAG_END {
+            return Err(self.error_at(
+                self.pos - 1,
+                DecodeErrorKind::UnexpectedTagType {
+                    expected: "a root tag type",
+                    found: id,
+                },
+            ));
+        }
+
+        let type_start = self.pos - 1;
+        let name = self.read_string()?;
+
+        match self.read_payload(id, 0, Some(type_start), Some(type_start + 1..self.pos)) {
+            Ok(v) => Ok((name, v)),
+            Err(mut e) => {
+                e.partial = e.partial.map(|(_, v)| (name, v));
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Decodes a whole file and returns the span of every tag in file offsets,
+/// parents before their children.
+pub fn decode_spans(
+    data: &[u8],
+    endian: NbtEndian,
+    header: NbtHeader,
+) -> Result<Vec<(NbtPath, NbtSpan)>, Box<DecodeError>> {
+    let start = header.size().min(data.len());
+    let mut decoder = NbtDecoder::new(&data[start..], endian).with_spans();
+
+    if let Err(mut e) = decoder.read_root() {
+        e.shift(start);
+        return Err(e);
+    }
+
+    let mut spans = decoder.into_spans();
+
+    for (_, span) in spans.iter_mut() {
+        span.shift(start);
+    }
+
+    Ok(spans)
+}
+
+/// Byte ranges of root tags stored back to back, like the block entities of
+/// a chunk in a world. Fails unless all of `data` is root tags.
+pub fn split_roots(data: &[u8], endian: NbtEndian) -> Result<Vec<Range<usize>>, Box<DecodeError>> {
+    let mut roots = Vec::new();
+    let mut start = 0;
+
+    while start < data.len() {
+        let mut decoder = NbtDecoder::new(&data[start..], endian);
+
+        if let Err(mut e) = decoder.read_root() {
+            e.shift(start);
+            return Err(e);
+        }
+
+        roots.push(start..start + decoder.position());
+        start += decoder.position();
+    }
+
+    Ok(roots)
+}
+
+/// Path of the innermost tag the byte at `offset` belongs to.
+pub fn tag_at(spans: &[(NbtPath, NbtSpan)], offset: usize) -> Option<&NbtPath> {
+    spans
+        .iter()
+        .filter(|(_, span)| span.contains(offset))
+        .max_by_key(|(path, _)| path.depth())
+        .map(|(path, _)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Root compound `""` holding `{a: Int16 1, l: [Int32 2, Int32 3]}` in Little Endian.
+    const LITTLE: [u8; 31] = [
+        10, 0, 0, // root compound
+        2, 1, 0, b'a', 1, 0, // a
+        9, 1, 0, b'l', 3, 2, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, // l
+        0, // end of root
+        0, 0, 0, 0, // after the root
+    ];
+
+    fn read(data: &[u8], endian: NbtEndian) -> Result<(String, NbtTag), Box<DecodeError>> {
+        NbtDecoder::new(data, endian).read_root()
+    }
+
+    #[test]
+    fn reads_each_endian() {
+        let big = [8, 0, 1, b'n', 0, 2, b'h', b'i'];
+        let network = [3, 1, b'n', 0x81, 0x01];
+
+        assert_eq!(
+            read(&big, NbtEndian::Big).unwrap(),
+            (String::from("n"), NbtTag::String(String::from("hi")))
+        );
+        assert_eq!(
+            read(&network, NbtEndian::LittleNetwork).unwrap(),
+            (String::from("n"), NbtTag::Int32(-65))
+        );
+
+        let (_, tag) = read(&LITTLE, NbtEndian::Little).unwrap();
+
+        assert_eq!(
+            NbtPath::root().key("l").index(1).get(&tag),
+            Some(&NbtTag::Int32(3))
+        );
+    }
+
+    #[test]
+    fn truncated_data_keeps_the_partial_tree() {
+        let e = read(&LITTLE[..24], NbtEndian::Little).unwrap_err();
+
+        assert_eq!(e.kind, DecodeErrorKind::UnexpectedEnd { needed: 2 });
+        assert_eq!(e.offset, 22);
+        assert_eq!(e.path, NbtPath::root().key("l").index(1));
+
+        let (name, tag) = e.partial.unwrap();
+
+        assert_eq!(name, "");
+        assert_eq!(NbtPath::root().key("a").get(&tag), Some(&NbtTag::Int16(1)));
+        assert_eq!(
+            NbtPath::root().key("l").get(&tag),
+            Some(&NbtTag::List(vec![NbtTag::Int32(2)]))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let negative = [9, 0, 0, 1, 0xff, 0xff, 0xff, 0xff];
+        let unknown = [10, 0, 0, 7, 0, 0];
+        let end_list = [9, 0, 0, 0, 1, 0, 0, 0];
+        let utf8 = [8, 0, 0, 1, 0, 0xff];
+
+        let kind = |data: &[u8]| read(data, NbtEndian::Little).unwrap_err().kind;
+
+        assert_eq!(kind(&negative), DecodeErrorKind::NegativeLength(-1));
+        assert!(matches!(
+            kind(&unknown),
+            DecodeErrorKind::UnexpectedTagType { found: 7, .. }
+        ));
+        assert!(matches!(
+            kind(&end_list),
+            DecodeErrorKind::UnexpectedTagType { found: 0, .. }
+        ));
+        assert_eq!(kind(&utf8), DecodeErrorKind::InvalidUtf8);
+        assert_eq!(
+            read(
+                &[3, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+                NbtEndian::LittleNetwork
+            )
+            .unwrap_err()
+            .kind,
+            DecodeErrorKind::VarintTooLong
+        );
+    }
+
+    #[test]
+    fn deep_nesting_fails() {
+        // Lists of lists, each with one element
+        let mut data = vec![9, 0, 0];
+
+        for _ in 0..=MAX_DEPTH {
+            data.extend_from_slice(&[9, 1, 0, 0, 0]);
+        }
+
+        let e = read(&data, NbtEndian::Little).unwrap_err();
+
+        assert_eq!(e.kind, DecodeErrorKind::TooDeep);
+        assert!(e.partial.is_some());
+    }
+
+    #[test]
+    fn roots_back_to_back() {
+        let root = &LITTLE[..27];
+        let data = [root, root].concat();
+
+        assert_eq!(
+            split_roots(&data, NbtEndian::Little).unwrap(),
+            [0..27, 27..54]
+        );
+        assert_eq!(
+            split_roots(&data[..30], NbtEndian::Little)
+                .unwrap_err()
+                .offset,
+            30
+        );
+    }
+
+    #[test]
+    fn spans_are_file_offsets() {
+        let data = [&[0; 8][..], &LITTLE[..27]].concat();
+        let spans = decode_spans(&data, NbtEndian::Little, NbtHeader::Normal).unwrap();
+
+        assert_eq!(spans[0].0, NbtPath::root());
+        assert_eq!(spans[0].1.start(), 8);
+        assert_eq!(spans[0].1.end(), 35);
+
+        let a = NbtPath::root().key("a");
+        let l1 = NbtPath::root().key("l").index(1);
+
+        assert_eq!(tag_at(&spans, 8 + 3), Some(&a));
+        assert_eq!(tag_at(&spans, 8 + 22), Some(&l1));
+        assert_eq!(tag_at(&spans, 8 + 26), Some(&NbtPath::root()));
+        assert_eq!(tag_at(&spans, 0), None);
+    }
+
+    #[test]
+    fn layout_records_key_order() {
+        let mut decoder = NbtDecoder::new(&LITTLE, NbtEndian::Little).with_layout();
+        decoder.read_root().unwrap();
+
+        let layout = decoder.into_layout();
+
+        assert_eq!(
+            layout.keys.get(&NbtPath::root()),
+            Some(&vec![String::from("a"), String::from("l")])
+        );
+        assert!(layout.empty_lists.is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::BTreeSet;
+use std::mem::discriminant;
+
+use bedrock_rs::nbt::NbtTag;
+
+use crate::nbt_path::NbtPath;
+
+/// How a tag differs between the left and the right tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    /// Only the right tree has the tag
+    Added,
+    /// Only the left tree has the tag
+    Removed,
+    /// Both have a scalar of the same type with different values
+    Changed,
+    /// Both have the tag but with different types
+    TypeChanged,
+}
+
+impl std::fmt::Display for DiffKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DiffKind::Added => "Added",
+                DiffKind::Removed => "Removed",
+                DiffKind::Changed => "Changed",
+                DiffKind::TypeChanged => "Type Changed",
+            }
+        )
+    }
+}
+
+/// One difference, the tags themselves are looked up with the path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NbtDiff {
+    pub path: NbtPath,
+    pub kind: DiffKind,
+}
+
+/// Compares two trees. Compounds and lists of the same type are compared
+/// entry by entry, list elements by index, so an inserted element shows up
+/// as changes to the ones after it. Differences are in the order they are
+/// shown with compound keys sorted.
+pub fn diff(left: &NbtTag, right: &NbtTag) -> Vec<NbtDiff> {
+    let mut diffs = Vec::new();
+
+    diff_into(left, right, NbtPath::root(), &mut diffs);
+
+    diffs
+}
+
+fn diff_into(left: &NbtTag, right: &NbtTag, path: NbtPath, diffs: &mut Vec<NbtDiff>) {
+    match (left, right) {
+        (NbtTag::Compound(l), NbtTag::Compound(r)) => {
+            let keys: BTreeSet<&String> = l.keys().chain(r.keys()).collect();
+
+            for key in keys {
+                let path = path.key(key.clone());
+
+                match (l.get(key), r.get(key)) {
+                    (Some(l), Some(r)) => diff_into(l, r, path, diffs),
+                    (Some(_), None) => diffs.push(NbtDiff {
+                        path,
+                        kind: DiffKind::Removed,
+                    }),
+                    (None, Some(_)) => diffs.push(NbtDiff {
+                        path,
+                        kind: DiffKind::Added,
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        // Lists of different element types are a type change of the whole list
+        (NbtTag::List(l), NbtTag::List(r))
+            if l.first()
+                .zip(r.first())
+                .is_none_or(|(l, r)| discriminant(l) == discriminant(r)) =>
+        {
+            for i in 0..l.len().max(r.len()) {
+                let path = path.index(i);
+
+                match (l.get(i), r.get(i)) {
+                    (Some(l), Some(r)) => diff_into(l, r, path, diffs),
+                    (Some(_), None) => diffs.push(NbtDiff {
+                        path,
+                        kind: DiffKind::Removed,
+                    }),
+                    (None, Some(_)) => diffs.push(NbtDiff {
+                        path,
+                        kind: DiffKind::Added,
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if discriminant(left) != discriminant(right) => diffs.push(NbtDiff {
+            path,
+            kind: DiffKind::TypeChanged,
+        }),
+        (NbtTag::List(_), NbtTag::List(_)) => diffs.push(NbtDiff {
+            path,
+            kind: DiffKind::TypeChanged,
+        }),
+        _ if !same_scalar(left, right) => diffs.push(NbtDiff {
+            path,
+            kind: DiffKind::Changed,
+        }),
+        _ => {}
+    }
+}
+
+/// Floats are compared by their bits so NaN equals itself.
+fn same_scalar(left: &NbtTag, right: &NbtTag) -> bool {
+    match (left, right) {
+        (NbtTag::Float32(l), NbtTag::Float32(r)) => l.to_bits() == r.to_bits(),
+        (NbtTag::Float64(l), NbtTag::Float64(r)) => l.to_bits() == r.to_bits(),
+        _ => left == right,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn compound(entries: &[(&str, NbtTag)]) -> NbtTag {
+        NbtTag::Compound(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    fn kinds(left: &NbtTag, right: &NbtTag) -> Vec<(String, DiffKind)> {
+        diff(left, right)
+            .into_iter()
+            .map(|v| (v.path.to_string(), v.kind))
+            .collect()
+    }
+
+    #[test]
+    fn compound_entries_in_key_order() {
+        let left = compound(&[
+            ("c", NbtTag::Byte(0)),
+            ("a", NbtTag::Int32(1)),
+            ("b", NbtTag::Int32(1)),
+        ]);
+        let right = compound(&[
+            ("d", NbtTag::Byte(0)),
+            ("a", NbtTag::Int32(2)),
+            ("b", NbtTag::Int16(1)),
+        ]);
+
+        let expected: Vec<(String, DiffKind)> = [
+            (NbtPath::root().key("a"), DiffKind::Changed),
+            (NbtPath::root().key("b"), DiffKind::TypeChanged),
+            (NbtPath::root().key("c"), DiffKind::Removed),
+            (NbtPath::root().key("d"), DiffKind::Added),
+        ]
+        .into_iter()
+        .map(|(p, k)| (p.to_string(), k))
+        .collect();
+
+        assert_eq!(kinds(&left, &right), expected);
+    }
+
+    #[test]
+    fn list_elements_by_index() {
+        let left = NbtTag::List(vec![NbtTag::Int32(1), NbtTag::Int32(2)]);
+        let right = NbtTag::List(vec![NbtTag::Int32(0), NbtTag::Int32(1), NbtTag::Int32(2)]);
+
+        assert_eq!(
+            diff(&left, &right)
+                .into_iter()
+                .map(|v| v.kind)
+                .collect::<Vec<_>>(),
+            [DiffKind::Changed, DiffKind::Changed, DiffKind::Added]
+        );
+    }
+
+    #[test]
+    fn lists_of_other_elements_change_type() {
+        let left = NbtTag::List(vec![NbtTag::Int32(1)]);
+        let right = NbtTag::List(vec![NbtTag::String(String::from("1"))]);
+
+        assert_eq!(
+            diff(&left, &right),
+            [NbtDiff {
+                path: NbtPath::root(),
+                kind: DiffKind::TypeChanged,
+            }]
+        );
+        assert!(diff(&NbtTag::List(Vec::new()), &left)
+            .iter()
+            .all(|v| v.kind == DiffKind::Added));
+    }
+
+    #[test]
+    fn nan_equals_itself() {
+        let nan = NbtTag::Float32(f32::NAN);
+        let tree = NbtTag::Compound(HashMap::from([(String::from("f"), nan.clone())]));
+
+        assert!(diff(&nan, &nan).is_empty());
+        assert!(diff(&tree, &tree).is_empty());
+        assert_eq!(diff(&NbtTag::Float64(0.0), &NbtTag::Float64(-0.0)).len(), 1);
+    }
+}
@@ -0,0 +1,586 @@
+use std::collections::HashMap;
+use std::mem;
+use std::mem::discriminant;
+
+use bedrock_rs::nbt::NbtTag;
+
+use crate::nbt_decode::NbtLayout;
+use crate::nbt_path::{NbtPath, NbtPathSegment};
+
+fn parse_integer(input: &str, min: i128, max: i128, kind: &str) -> Result<i128, String> {
+    let v = match input.trim().parse::<i128>() {
+        Ok(v) => v,
+        Err(_) => return Err(format!("Not a valid {kind}")),
+    };
+
+    if v < min || v > max {
+        return Err(format!("{kind} must be between {min} and {max}"));
+    }
+
+    Ok(v)
+}
+
+fn parse_float(input: &str, max: f64, kind: &str) -> Result<f64, String> {
+    let v = match input.trim().parse::<f64>() {
+        Ok(v) => v,
+        Err(_) => return Err(format!("Not a valid {kind}")),
+    };
+
+    if v.is_finite() && v.abs() > max {
+        return Err(format!("{kind} must be between {} and {max}", -max));
+    }
+
+    Ok(v)
+}
+
+/// Parses `input` as a new value for the scalar `current`, keeping its variant.
+pub fn parse_scalar(current: &NbtTag, input: &str) -> Result<NbtTag, String> {
+    match current {
+        NbtTag::Byte(_) => parse_integer(input, u8::MIN as i128, u8::MAX as i128, "Byte")
+            .map(|v| NbtTag::Byte(v as u8)),
+        NbtTag::Int16(_) => parse_integer(input, i16::MIN as i128, i16::MAX as i128, "Int16")
+            .map(|v| NbtTag::Int16(v as i16)),
+        NbtTag::Int32(_) => parse_integer(input, i32::MIN as i128, i32::MAX as i128, "Int32")
+            .map(|v| NbtTag::Int32(v as i32)),
+        NbtTag::Int64(_) => parse_integer(input, i64::MIN as i128, i64::MAX as i128, "Int64")
+            .map(|v| NbtTag::Int64(v as i64)),
+        NbtTag::Float32(_) => {
+            parse_float(input, f32::MAX as f64, "Float32").map(|v| NbtTag::Float32(v as f32))
+        }
+        NbtTag::Float64(_) => parse_float(input, f64::MAX, "Float64").map(NbtTag::Float64),
+        NbtTag::String(_) => Ok(NbtTag::String(input.to_string())),
+        NbtTag::List(_) | NbtTag::Compound(_) | NbtTag::Empty => {
+            Err(String::from("Only scalar tags can be edited"))
+        }
+    }
+}
+
+/// Formats the value of a scalar the way [`parse_scalar`] reads it back.
+pub fn scalar_to_string(tag: &NbtTag) -> Option<String> {
+    match tag {
+        NbtTag::Byte(v) => Some(v.to_string()),
+        NbtTag::Int16(v) => Some(v.to_string()),
+        NbtTag::Int32(v) => Some(v.to_string()),
+        NbtTag::Int64(v) => Some(v.to_string()),
+        NbtTag::Float32(v) => Some(v.to_string()),
+        NbtTag::Float64(v) => Some(v.to_string()),
+        NbtTag::String(v) => Some(v.clone()),
+        NbtTag::List(_) | NbtTag::Compound(_) | NbtTag::Empty => None,
+    }
+}
+
+/// Variant of an [`NbtTag`] without its value, used to create new tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NbtTagKind {
+    Byte,
+    Int16,
+    #[default]
+    Int32,
+    Int64,
+    Float32,
+    Float64,
+    String,
+    List,
+    Compound,
+}
+
+impl NbtTagKind {
+    pub const ALL: [NbtTagKind; 9] = [
+        NbtTagKind::Byte,
+        NbtTagKind::Int16,
+        NbtTagKind::Int32,
+        NbtTagKind::Int64,
+        NbtTagKind::Float32,
+        NbtTagKind::Float64,
+        NbtTagKind::String,
+        NbtTagKind::List,
+        NbtTagKind::Compound,
+    ];
+
+    pub fn of(tag: &NbtTag) -> Option<Self> {
+        match tag {
+            NbtTag::Byte(_) => Some(NbtTagKind::Byte),
+            NbtTag::Int16(_) => Some(NbtTagKind::Int16),
+            NbtTag::Int32(_) => Some(NbtTagKind::Int32),
+            NbtTag::Int64(_) => Some(NbtTagKind::Int64),
+            NbtTag::Float32(_) => Some(NbtTagKind::Float32),
+            NbtTag::Float64(_) => Some(NbtTagKind::Float64),
+            NbtTag::String(_) => Some(NbtTagKind::String),
+            NbtTag::List(_) => Some(NbtTagKind::List),
+            NbtTag::Compound(_) => Some(NbtTagKind::Compound),
+            NbtTag::Empty => None,
+        }
+    }
+
+    /// A new tag of this kind holding a zero value.
+    pub fn new_tag(&self) -> NbtTag {
+        match self {
+            NbtTagKind::Byte => NbtTag::Byte(0),
+            NbtTagKind::Int16 => NbtTag::Int16(0),
+            NbtTagKind::Int32 => NbtTag::Int32(0),
+            NbtTagKind::Int64 => NbtTag::Int64(0),
+            NbtTagKind::Float32 => NbtTag::Float32(0.0),
+            NbtTagKind::Float64 => NbtTag::Float64(0.0),
+            NbtTagKind::String => NbtTag::String(String::new()),
+            NbtTagKind::List => NbtTag::List(Vec::new()),
+            NbtTagKind::Compound => NbtTag::Compound(HashMap::new()),
+        }
+    }
+}
+
+impl std::fmt::Display for NbtTagKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                NbtTagKind::Byte => "Byte",
+                NbtTagKind::Int16 => "Int16",
+                NbtTagKind::Int32 => "Int32",
+                NbtTagKind::Int64 => "Int64",
+                NbtTagKind::Float32 => "Float32",
+                NbtTagKind::Float64 => "Float64",
+                NbtTagKind::String => "String",
+                NbtTagKind::List => "List",
+                NbtTagKind::Compound => "Compound",
+            }
+        )
+    }
+}
+
+fn kind_name(tag: &NbtTag) -> String {
+    match NbtTagKind::of(tag) {
+        Some(v) => v.to_string(),
+        None => String::from("Empty"),
+    }
+}
+
+/// Checks that `tag` may be stored in `list`, Bedrock lists only hold one variant.
+pub fn check_list_element(list: &[NbtTag], tag: &NbtTag) -> Result<(), String> {
+    match list.first() {
+        Some(first) if discriminant(first) != discriminant(tag) => Err(format!(
+            "A List of {} can't contain a {}",
+            kind_name(first),
+            kind_name(tag)
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that `tag` may replace the tag at `path`, which has to keep the
+/// type of the other elements if it is in a list.
+pub fn check_replace(root: &NbtTag, path: &NbtPath, tag: &NbtTag) -> Result<(), String> {
+    let Some((parent, NbtPathSegment::Index(i))) = path.split_last() else {
+        return Ok(());
+    };
+
+    match parent.get(root) {
+        Some(NbtTag::List(v)) => {
+            let others: Vec<NbtTag> = v
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| j != i)
+                .take(1)
+                .map(|(_, v)| v.clone())
+                .collect();
+
+            check_list_element(&others, tag)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn parent_mut<'a>(root: &'a mut NbtTag, path: &NbtPath) -> Result<&'a mut NbtTag, String> {
+    let Some((parent, _)) = path.split_last() else {
+        return Err(String::from("The root tag has no parent"));
+    };
+
+    match parent.get_mut(root) {
+        Some(v) => Ok(v),
+        None => Err(format!("No tag at {parent}")),
+    }
+}
+
+/// Inserts `tag` so that it ends up at `path`.
+pub fn insert(root: &mut NbtTag, path: &NbtPath, tag: NbtTag) -> Result<(), String> {
+    let last = path.split_last().map(|v| v.1.clone());
+
+    match (parent_mut(root, path)?, last) {
+        (NbtTag::Compound(v), Some(NbtPathSegment::Key(k))) => {
+            if v.contains_key(&k) {
+                return Err(format!("Key \"{k}\" already exists"));
+            }
+
+            v.insert(k, tag);
+            Ok(())
+        }
+        (NbtTag::List(v), Some(NbtPathSegment::Index(i))) => {
+            check_list_element(v, &tag)?;
+
+            if i > v.len() {
+                return Err(format!("Index {i} is out of bounds"));
+            }
+
+            v.insert(i, tag);
+            Ok(())
+        }
+        _ => Err(format!("Can't insert a tag at {path}")),
+    }
+}
+
+/// Removes the tag at `path` and returns it.
+pub fn remove(root: &mut NbtTag, path: &NbtPath) -> Result<NbtTag, String> {
+    let last = path.split_last().map(|v| v.1.clone());
+
+    let removed = match (parent_mut(root, path)?, last) {
+        (NbtTag::Compound(v), Some(NbtPathSegment::Key(k))) => v.remove(&k),
+        (NbtTag::List(v), Some(NbtPathSegment::Index(i))) if i < v.len() => Some(v.remove(i)),
+        _ => None,
+    };
+
+    match removed {
+        Some(v) => Ok(v),
+        None => Err(format!("No tag at {path}")),
+    }
+}
+
+/// Renames the compound entry at `path` to `key`, returning its new path. The
+/// entry keeps its place in the key order of `layout`, and the layout of the
+/// tags below it moves along.
+pub fn rename(
+    root: &mut NbtTag,
+    layout: &mut NbtLayout,
+    path: &NbtPath,
+    key: &str,
+) -> Result<NbtPath, String> {
+    let Some((parent, NbtPathSegment::Key(old))) = path.split_last() else {
+        return Err(String::from("Only compound entries can be renamed"));
+    };
+
+    if old == key {
+        return Ok(path.clone());
+    }
+
+    let Some(NbtTag::Compound(v)) = parent.get_mut(root) else {
+        return Err(String::from("Only compound entries can be renamed"));
+    };
+
+    if v.contains_key(key) {
+        return Err(format!("Key \"{key}\" already exists"));
+    }
+
+    let keys: Vec<String> = layout
+        .ordered_keys(&parent, v)
+        .into_iter()
+        .map(|k| match k == old {
+            true => key.to_string(),
+            false => k.clone(),
+        })
+        .collect();
+
+    let Some(tag) = v.remove(old) else {
+        return Err(format!("No tag at {path}"));
+    };
+
+    v.insert(key.to_string(), tag);
+
+    let renamed = parent.key(key);
+    layout.keys.insert(parent, keys);
+    rebase_layout(layout, path, &renamed);
+
+    Ok(renamed)
+}
+
+/// Moves the layout of the tags at and below `from` to `to`, dropping what
+/// was left at `to` by tags that were removed.
+fn rebase_layout(layout: &mut NbtLayout, from: &NbtPath, to: &NbtPath) {
+    layout.keys.retain(|k, _| !k.starts_with(to));
+    layout.empty_lists.retain(|k, _| !k.starts_with(to));
+
+    layout.keys = mem::take(&mut layout.keys)
+        .into_iter()
+        .map(|(k, v)| (k.rebase(from, to).unwrap_or(k), v))
+        .collect();
+    layout.empty_lists = mem::take(&mut layout.empty_lists)
+        .into_iter()
+        .map(|(k, v)| (k.rebase(from, to).unwrap_or(k), v))
+        .collect();
+}
+
+/// Position of the compound entry at `path` in the key order of `layout`,
+/// and the number of entries of its compound.
+pub fn key_position(root: &NbtTag, layout: &NbtLayout, path: &NbtPath) -> Option<(usize, usize)> {
+    let Some((parent, NbtPathSegment::Key(key))) = path.split_last() else {
+        return None;
+    };
+
+    let Some(NbtTag::Compound(v)) = parent.get(root) else {
+        return None;
+    };
+
+    let keys = layout.ordered_keys(&parent, v);
+
+    keys.iter().position(|k| *k == key).map(|i| (i, keys.len()))
+}
+
+/// Moves the compound entry at `path` to `index` in the key order of `layout`,
+/// the order it is shown and saved in.
+pub fn move_key(
+    root: &NbtTag,
+    layout: &mut NbtLayout,
+    path: &NbtPath,
+    index: usize,
+) -> Result<(), String> {
+    let Some((parent, NbtPathSegment::Key(key))) = path.split_last() else {
+        return Err(String::from(
+            "Only compound entries can be moved in the key order",
+        ));
+    };
+
+    let Some(NbtTag::Compound(v)) = parent.get(root) else {
+        return Err(String::from(
+            "Only compound entries can be moved in the key order",
+        ));
+    };
+
+    let mut keys: Vec<String> = layout
+        .ordered_keys(&parent, v)
+        .into_iter()
+        .cloned()
+        .collect();
+
+    let Some(i) = keys.iter().position(|k| k == key) else {
+        return Err(format!("No tag at {path}"));
+    };
+
+    if index >= keys.len() {
+        return Err(format!("Index {index} is out of bounds"));
+    }
+
+    let key = keys.remove(i);
+    keys.insert(index, key);
+    layout.keys.insert(parent, keys);
+
+    Ok(())
+}
+
+/// Moves the list element at `path` to `index`, returning its new path.
+pub fn move_to(root: &mut NbtTag, path: &NbtPath, index: usize) -> Result<NbtPath, String> {
+    let Some((parent, NbtPathSegment::Index(i))) = path.split_last() else {
+        return Err(String::from("Only list elements can be moved"));
+    };
+
+    let Some(NbtTag::List(v)) = parent.get_mut(root) else {
+        return Err(String::from("Only list elements can be moved"));
+    };
+
+    if *i >= v.len() || index >= v.len() {
+        return Err(format!("Index {index} is out of bounds"));
+    }
+
+    let tag = v.remove(*i);
+    v.insert(index, tag);
+
+    Ok(parent.index(index))
+}
+
+/// Finds a path for a new child of the container at `parent`, for compounds
+/// the key is `base` with a number appended until it is unused.
+pub fn free_child_path(root: &NbtTag, parent: &NbtPath, base: &str) -> Result<NbtPath, String> {
+    match parent.get(root) {
+        Some(NbtTag::Compound(v)) => {
+            let mut key = base.to_string();
+            let mut n = 1;
+
+            while v.contains_key(&key) {
+                key = format!("{base}_{n}");
+                n += 1;
+            }
+
+            Ok(parent.key(key))
+        }
+        Some(NbtTag::List(v)) => Ok(parent.index(v.len())),
+        Some(_) => Err(String::from("Only compounds and lists can have children")),
+        None => Err(format!("No tag at {parent}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> NbtTag {
+        NbtTag::Compound(HashMap::from([
+            (String::from("a"), NbtTag::Int32(1)),
+            (
+                String::from("b"),
+                NbtTag::Compound(HashMap::from([
+                    (String::from("x"), NbtTag::Byte(0)),
+                    (String::from("y"), NbtTag::Byte(0)),
+                ])),
+            ),
+            (
+                String::from("list"),
+                NbtTag::List(vec![NbtTag::Int16(1), NbtTag::Int16(2), NbtTag::Int16(3)]),
+            ),
+        ]))
+    }
+
+    fn keys(layout: &NbtLayout, path: &NbtPath) -> String {
+        layout.keys[path].join(",")
+    }
+
+    #[test]
+    fn scalars_keep_their_type_and_range() {
+        assert_eq!(
+            parse_scalar(&NbtTag::Byte(0), " 255 "),
+            Ok(NbtTag::Byte(255))
+        );
+        assert!(parse_scalar(&NbtTag::Byte(0), "256").is_err());
+        assert!(parse_scalar(&NbtTag::Int16(0), "-32769").is_err());
+        assert_eq!(
+            parse_scalar(&NbtTag::Int64(0), "-9223372036854775808"),
+            Ok(NbtTag::Int64(i64::MIN))
+        );
+        assert!(parse_scalar(&NbtTag::Int32(0), "1.5").is_err());
+        assert_eq!(
+            parse_scalar(&NbtTag::Float32(0.0), "1.5"),
+            Ok(NbtTag::Float32(1.5))
+        );
+        assert!(parse_scalar(&NbtTag::Float32(0.0), "1e39").is_err());
+        assert_eq!(
+            parse_scalar(&NbtTag::String(String::new()), " a "),
+            Ok(NbtTag::String(String::from(" a ")))
+        );
+        assert!(parse_scalar(&NbtTag::List(Vec::new()), "1").is_err());
+    }
+
+    #[test]
+    fn insert_checks_keys_and_list_types() {
+        let mut root = sample();
+        let list = NbtPath::root().key("list");
+
+        insert(&mut root, &NbtPath::root().key("c"), NbtTag::Byte(1)).unwrap();
+        assert!(insert(&mut root, &NbtPath::root().key("a"), NbtTag::Byte(1)).is_err());
+
+        insert(&mut root, &list.index(0), NbtTag::Int16(0)).unwrap();
+        assert!(insert(&mut root, &list.index(1), NbtTag::Int32(0)).is_err());
+        assert!(insert(&mut root, &list.index(9), NbtTag::Int16(0)).is_err());
+        assert!(insert(
+            &mut root,
+            &NbtPath::root().key("a").key("b"),
+            NbtTag::Byte(0)
+        )
+        .is_err());
+
+        assert_eq!(NbtPath::root().key("c").get(&root), Some(&NbtTag::Byte(1)));
+        assert_eq!(list.index(0).get(&root), Some(&NbtTag::Int16(0)));
+    }
+
+    #[test]
+    fn remove_returns_the_tag() {
+        let mut root = sample();
+        let list = NbtPath::root().key("list");
+
+        assert_eq!(remove(&mut root, &list.index(1)), Ok(NbtTag::Int16(2)));
+        assert_eq!(list.index(1).get(&root), Some(&NbtTag::Int16(3)));
+        assert!(remove(&mut root, &list.index(2)).is_err());
+
+        assert_eq!(
+            remove(&mut root, &NbtPath::root().key("a")),
+            Ok(NbtTag::Int32(1))
+        );
+        assert!(remove(&mut root, &NbtPath::root().key("a")).is_err());
+        assert!(remove(&mut root, &NbtPath::root()).is_err());
+    }
+
+    #[test]
+    fn rename_keeps_the_place_of_the_key() {
+        let mut root = sample();
+        let mut layout = NbtLayout::default();
+        layout.keys.insert(
+            NbtPath::root(),
+            vec![String::from("list"), String::from("b"), String::from("a")],
+        );
+        layout.keys.insert(
+            NbtPath::root().key("b"),
+            vec![String::from("y"), String::from("x")],
+        );
+
+        let path = rename(&mut root, &mut layout, &NbtPath::root().key("b"), "c").unwrap();
+
+        assert_eq!(path, NbtPath::root().key("c"));
+        assert!(path.key("x").get(&root).is_some());
+        assert_eq!(keys(&layout, &NbtPath::root()), "list,c,a");
+        assert_eq!(keys(&layout, &path), "y,x");
+        assert!(!layout.keys.contains_key(&NbtPath::root().key("b")));
+
+        assert!(rename(&mut root, &mut layout, &path, "a").is_err());
+        assert!(rename(
+            &mut root,
+            &mut layout,
+            &NbtPath::root().key("list").index(0),
+            "a"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn move_to_reorders_lists() {
+        let mut root = sample();
+        let list = NbtPath::root().key("list");
+
+        assert_eq!(move_to(&mut root, &list.index(0), 2), Ok(list.index(2)));
+        assert_eq!(
+            list.get(&root),
+            Some(&NbtTag::List(vec![
+                NbtTag::Int16(2),
+                NbtTag::Int16(3),
+                NbtTag::Int16(1)
+            ]))
+        );
+        assert!(move_to(&mut root, &list.index(0), 3).is_err());
+        assert!(move_to(&mut root, &NbtPath::root().key("a"), 0).is_err());
+    }
+
+    #[test]
+    fn move_key_reorders_the_layout() {
+        let root = sample();
+        let mut layout = NbtLayout::default();
+        let path = NbtPath::root().key("list");
+
+        assert_eq!(key_position(&root, &layout, &path), Some((2, 3)));
+
+        move_key(&root, &mut layout, &path, 0).unwrap();
+        assert_eq!(keys(&layout, &NbtPath::root()), "list,a,b");
+        assert_eq!(key_position(&root, &layout, &path), Some((0, 3)));
+
+        assert!(move_key(&root, &mut layout, &path, 3).is_err());
+        assert!(move_key(&root, &mut layout, &path.index(0), 0).is_err());
+    }
+
+    #[test]
+    fn free_child_paths_are_unused() {
+        let mut root = sample();
+
+        assert_eq!(
+            free_child_path(&root, &NbtPath::root(), "a"),
+            Ok(NbtPath::root().key("a_1"))
+        );
+        assert_eq!(
+            free_child_path(&root, &NbtPath::root(), "c"),
+            Ok(NbtPath::root().key("c"))
+        );
+
+        insert(&mut root, &NbtPath::root().key("a_1"), NbtTag::Byte(0)).unwrap();
+        assert_eq!(
+            free_child_path(&root, &NbtPath::root(), "a"),
+            Ok(NbtPath::root().key("a_2"))
+        );
+
+        let list = NbtPath::root().key("list");
+        assert_eq!(free_child_path(&root, &list, "a"), Ok(list.index(3)));
+        assert!(free_child_path(&root, &NbtPath::root().key("a"), "a").is_err());
+        assert!(free_child_path(&root, &NbtPath::root().key("d"), "a").is_err());
+    }
+}
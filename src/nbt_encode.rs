@@ -0,0 +1,319 @@
+//! Writes Nbt in the Bedrock encodings, the counterpart of [`NbtDecoder`].
+//! Compounds are written in the key order of an [`NbtLayout`], so a file
+//! that is read and written unchanged keeps its bytes.
+//!
+//! [`NbtDecoder`]: crate::nbt_decode::NbtDecoder
+
+use bedrock_rs::nbt::NbtTag;
+
+use crate::document::NbtEndian;
+use crate::nbt_decode::{
+    NbtLayout, TAG_BYTE, TAG_COMPOUND, TAG_END, TAG_FLOAT32, TAG_FLOAT64, TAG_INT16, TAG_INT32,
+    TAG_INT64, TAG_LIST, TAG_STRING,
+};
+use crate::nbt_path::{NbtPath, NbtPathSegment};
+
+fn tag_type(tag: &NbtTag) -> u8 {
+    match tag {
+        NbtTag::Byte(_) => TAG_BYTE,
+        NbtTag::Int16(_) => TAG_INT16,
+        NbtTag::Int32(_) => TAG_INT32,
+        NbtTag::Int64(_) => TAG_INT64,
+        NbtTag::Float32(_) => TAG_FLOAT32,
+        NbtTag::Float64(_) => TAG_FLOAT64,
+        NbtTag::String(_) => TAG_STRING,
+        NbtTag::List(_) => TAG_LIST,
+        NbtTag::Compound(_) => TAG_COMPOUND,
+        NbtTag::Empty => TAG_END,
+    }
+}
+
+struct NbtEncoder<'a> {
+    out: Vec<u8>,
+    endian: NbtEndian,
+    layout: &'a NbtLayout,
+    /// Tag that is being written
+    path: NbtPath,
+}
+
+impl NbtEncoder<'_> {
+    fn error(&self, message: &str) -> String {
+        match self.path.depth() {
+            0 => format!("{message} in the root tag"),
+            _ => format!("{message} in {}", self.path),
+        }
+    }
+
+    fn write_varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.out.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+
+        self.out.push(v as u8);
+    }
+
+    fn write_i16(&mut self, v: i16) {
+        match self.endian {
+            NbtEndian::Big => self.out.extend_from_slice(&v.to_be_bytes()),
+            NbtEndian::Little | NbtEndian::LittleNetwork => {
+                self.out.extend_from_slice(&v.to_le_bytes())
+            }
+        }
+    }
+
+    fn write_i32(&mut self, v: i32) {
+        match self.endian {
+            NbtEndian::Little => self.out.extend_from_slice(&v.to_le_bytes()),
+            NbtEndian::LittleNetwork => self.write_varint(((v << 1) ^ (v >> 31)) as u32 as u64),
+            NbtEndian::Big => self.out.extend_from_slice(&v.to_be_bytes()),
+        }
+    }
+
+    fn write_i64(&mut self, v: i64) {
+        match self.endian {
+            NbtEndian::Little => self.out.extend_from_slice(&v.to_le_bytes()),
+            NbtEndian::LittleNetwork => self.write_varint(((v << 1) ^ (v >> 63)) as u64),
+            NbtEndian::Big => self.out.extend_from_slice(&v.to_be_bytes()),
+        }
+    }
+
+    fn write_f32(&mut self, v: f32) {
+        match self.endian {
+            NbtEndian::Big => self.out.extend_from_slice(&v.to_be_bytes()),
+            NbtEndian::Little | NbtEndian::LittleNetwork => {
+                self.out.extend_from_slice(&v.to_le_bytes())
+            }
+        }
+    }
+
+    fn write_f64(&mut self, v: f64) {
+        match self.endian {
+            NbtEndian::Big => self.out.extend_from_slice(&v.to_be_bytes()),
+            NbtEndian::Little | NbtEndian::LittleNetwork => {
+                self.out.extend_from_slice(&v.to_le_bytes())
+            }
+        }
+    }
+
+    fn write_string(&mut self, v: &str) -> Result<(), String> {
+        match (self.endian, u16::try_from(v.len())) {
+            (NbtEndian::LittleNetwork, _) => self.write_varint(v.len() as u64),
+            (NbtEndian::Little, Ok(len)) => self.out.extend_from_slice(&len.to_le_bytes()),
+            (NbtEndian::Big, Ok(len)) => self.out.extend_from_slice(&len.to_be_bytes()),
+            (_, Err(_)) => {
+                return Err(self.error(&format!("String of {} bytes is too long", v.len())))
+            }
+        }
+
+        self.out.extend_from_slice(v.as_bytes());
+
+        Ok(())
+    }
+
+    fn write_payload(&mut self, tag: &NbtTag) -> Result<(), String> {
+        match tag {
+            NbtTag::Byte(v) => self.out.push(*v),
+            NbtTag::Int16(v) => self.write_i16(*v),
+            NbtTag::Int32(v) => self.write_i32(*v),
+            NbtTag::Int64(v) => self.write_i64(*v),
+            NbtTag::Float32(v) => self.write_f32(*v),
+            NbtTag::Float64(v) => self.write_f64(*v),
+            NbtTag::String(v) => self.write_string(v)?,
+            NbtTag::List(v) => self.write_list(v)?,
+            NbtTag::Compound(v) => {
+                for key in self.layout.ordered_keys(&self.path, v) {
+                    self.out.push(tag_type(&v[key]));
+                    self.write_string(key)?;
+
+                    self.path.push(NbtPathSegment::Key(key.clone()));
+                    self.write_payload(&v[key])?;
+                    self.path.pop();
+                }
+
+                self.out.push(TAG_END);
+            }
+            NbtTag::Empty => return Err(self.error("Empty tags can't be written")),
+        }
+
+        Ok(())
+    }
+
+    fn write_list(&mut self, list: &[NbtTag]) -> Result<(), String> {
+        let element = match list.first() {
+            Some(v) => tag_type(v),
+            None => self
+                .layout
+                .empty_lists
+                .get(&self.path)
+                .copied()
+                .unwrap_or(TAG_END),
+        };
+
+        if list.iter().any(|v| tag_type(v) != element) {
+            return Err(self.error("List elements of different types"));
+        }
+
+        let Ok(len) = i32::try_from(list.len()) else {
+            return Err(self.error(&format!("List of {} elements is too long", list.len())));
+        };
+
+        self.out.push(element);
+        self.write_i32(len);
+
+        for (i, v) in list.iter().enumerate() {
+            self.path.push(NbtPathSegment::Index(i));
+            self.write_payload(v)?;
+            self.path.pop();
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes `tag` as a root tag called `name`.
+pub fn encode_root(
+    name: &str,
+    tag: &NbtTag,
+    endian: NbtEndian,
+    layout: &NbtLayout,
+) -> Result<Vec<u8>, String> {
+    let mut encoder = NbtEncoder {
+        out: Vec::new(),
+        endian,
+        layout,
+        path: NbtPath::root(),
+    };
+
+    encoder.out.push(tag_type(tag));
+    encoder.write_string(name)?;
+    encoder.write_payload(tag)?;
+
+    Ok(encoder.out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::nbt_decode::NbtDecoder;
+
+    fn compound(entries: &[(&str, NbtTag)]) -> NbtTag {
+        NbtTag::Compound(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    /// Every tag type, with keys out of order and an empty list of compounds.
+    fn sample() -> (NbtTag, NbtLayout) {
+        let tag = compound(&[
+            ("z", NbtTag::Byte(0xff)),
+            ("short", NbtTag::Int16(-2)),
+            ("int", NbtTag::Int32(i32::MIN)),
+            ("long", NbtTag::Int64(-1)),
+            ("nan", NbtTag::Float32(f32::from_bits(0xffc0_0001))),
+            ("double", NbtTag::Float64(-0.0)),
+            ("text", NbtTag::String(String::from("\u{e9}t\u{e9}"))),
+            (
+                "list",
+                NbtTag::List(vec![compound(&[("a", NbtTag::Int32(300))])]),
+            ),
+            ("empty", NbtTag::List(Vec::new())),
+        ]);
+
+        let mut layout = NbtLayout::default();
+        layout.keys.insert(
+            NbtPath::root(),
+            [
+                "z", "short", "int", "long", "nan", "double", "text", "list", "empty",
+            ]
+            .map(String::from)
+            .to_vec(),
+        );
+        layout.keys.insert(
+            NbtPath::root().key("list").index(0),
+            vec![String::from("a")],
+        );
+        layout
+            .empty_lists
+            .insert(NbtPath::root().key("empty"), TAG_COMPOUND);
+
+        (tag, layout)
+    }
+
+    #[test]
+    fn writes_little_endian() {
+        let tag = compound(&[("b", NbtTag::Byte(1))]);
+        let data = encode_root("", &tag, NbtEndian::Little, &NbtLayout::default()).unwrap();
+
+        assert_eq!(data, [10, 0, 0, 1, 1, 0, b'b', 1, 0]);
+    }
+
+    #[test]
+    fn writes_network_varints() {
+        let tag = NbtTag::Int32(-65);
+        let data = encode_root("ab", &tag, NbtEndian::LittleNetwork, &NbtLayout::default());
+
+        assert_eq!(data.unwrap(), [3, 2, b'a', b'b', 0x81, 0x01]);
+    }
+
+    #[test]
+    fn decoded_files_are_written_back_unchanged() {
+        let (tag, layout) = sample();
+
+        for endian in NbtEndian::ALL {
+            let data = encode_root("root", &tag, endian, &layout).unwrap();
+
+            let mut decoder = NbtDecoder::new(&data, endian).with_layout();
+            let (name, decoded) = decoder.read_root().unwrap();
+            let decoded_layout = decoder.into_layout();
+
+            assert_eq!(decoded_layout, layout, "{endian:?}");
+            assert_eq!(
+                encode_root(&name, &decoded, endian, &decoded_layout).unwrap(),
+                data,
+                "{endian:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn keys_missing_from_the_layout_come_last_sorted() {
+        let tag = compound(&[
+            ("c", NbtTag::Byte(0)),
+            ("b", NbtTag::Byte(0)),
+            ("a", NbtTag::Byte(0)),
+        ]);
+
+        let mut layout = NbtLayout::default();
+        layout.keys.insert(
+            NbtPath::root(),
+            vec![String::from("c"), String::from("gone")],
+        );
+
+        // Every entry is the type, a name of one byte and the value
+        let data = encode_root("", &tag, NbtEndian::Little, &layout).unwrap();
+        let keys: Vec<u8> = data[3..18].chunks(5).map(|v| v[3]).collect();
+
+        assert_eq!(data.len(), 3 + 3 * 5 + 1);
+        assert_eq!(keys, b"cab");
+    }
+
+    #[test]
+    fn rejects_what_nbt_cannot_hold() {
+        let layout = NbtLayout::default();
+
+        let mixed = NbtTag::List(vec![NbtTag::Byte(0), NbtTag::Int16(0)]);
+        let empty = NbtTag::Compound(HashMap::from([(String::from("a"), NbtTag::Empty)]));
+        let long = NbtTag::String("a".repeat(u16::MAX as usize + 1));
+
+        assert!(encode_root("", &mixed, NbtEndian::Little, &layout).is_err());
+        assert!(encode_root("", &empty, NbtEndian::Little, &layout).is_err());
+        assert!(encode_root("", &long, NbtEndian::Big, &layout).is_err());
+        assert!(encode_root("", &long, NbtEndian::LittleNetwork, &layout).is_ok());
+    }
+}
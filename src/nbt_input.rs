@@ -0,0 +1,214 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use beditor::backup;
+use beditor::detect;
+use beditor::document::{NbtDocument, NbtEndian, NbtHeader};
+use beditor::history::History;
+use bedrock_rs::nbt::NbtTag;
+use iced::widget::{Button, Column, PickList, Row, Text, TextInput};
+use iced::{Command, Element, Length};
+
+use crate::messages::BEditorMessage;
+use crate::nbt_view;
+use crate::nbt_view::ERROR_COLOR;
+
+/// What an [`NbtInput`] was asked to do.
+#[derive(Debug, Clone)]
+pub enum NbtInputMessage {
+    SetPath(String),
+    /// Read the file at the typed path, guessing its format
+    Open,
+    PickFile,
+    FilePicked(Option<PathBuf>),
+    SetEndian(NbtEndian),
+    SetHeader(NbtHeader),
+    Save,
+}
+
+/// One of the files a diff or merge is made of, read in its own endian and header.
+pub struct NbtInput {
+    path: String,
+    endian: NbtEndian,
+    header: NbtHeader,
+    pub nbt: Result<NbtDocument, String>,
+    /// Changes made to the file by the view it is shown in
+    pub history: History,
+    /// Outcome of the last save or change
+    pub status: Option<Result<String, String>>,
+}
+
+impl NbtInput {
+    pub fn new() -> Self {
+        Self {
+            path: String::new(),
+            endian: NbtEndian::default(),
+            header: NbtHeader::default(),
+            nbt: Err(String::new()),
+            history: History::new(),
+            status: None,
+        }
+    }
+
+    /// Reads the file, dropping changes. With `detect` the format is guessed
+    /// first, otherwise the selected one is used.
+    fn load(&mut self, detect: bool) {
+        self.history = History::new();
+        self.status = None;
+
+        let data = match fs::read(&self.path) {
+            Ok(v) => v,
+            Err(e) => {
+                self.nbt = Err(format!("Error reading File: {e:?}"));
+                return;
+            }
+        };
+
+        if detect {
+            let file_name = Path::new(&self.path).file_name().and_then(|v| v.to_str());
+
+            if let Some(v) = detect::detect(&data, file_name) {
+                self.endian = v.endian;
+                self.header = v.header;
+            }
+        }
+
+        self.nbt = NbtDocument::parse(data, self.endian, self.header).map_err(|e| e.to_string());
+    }
+
+    /// Switches to the selected endian and header. Without changes the file
+    /// is read again in that format, edited Nbt is kept and will be saved in it.
+    fn reformat(&mut self) {
+        let (true, Ok(document)) = (self.history.is_dirty(), &mut self.nbt) else {
+            self.load(false);
+            return;
+        };
+
+        document.endian = self.endian;
+        document.header = self.header;
+
+        self.status = Some(Ok(format!(
+            "Kept the edited Nbt, it will be saved as {} with {}",
+            self.endian, self.header
+        )));
+    }
+
+    fn save(&mut self) -> Result<String, String> {
+        let Ok(document) = &mut self.nbt else {
+            return Err(String::from("No Nbt loaded"));
+        };
+
+        backup::snapshot_level_dat(Path::new(&self.path))?;
+        document
+            .save(Path::new(&self.path))
+            .map_err(|e| e.to_string())?;
+
+        self.history.mark_saved();
+
+        Ok(format!("Saved to {}", self.path))
+    }
+
+    pub fn document(&self) -> Option<&NbtDocument> {
+        self.nbt.as_ref().ok()
+    }
+
+    pub fn tag(&self) -> Option<&NbtTag> {
+        self.document().map(|v| &v.tag)
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.nbt.is_ok() && self.history.is_dirty()
+    }
+
+    pub fn file_name(&self) -> String {
+        match Path::new(&self.path).file_name() {
+            Some(v) => v.to_string_lossy().to_string(),
+            None => String::from("?"),
+        }
+    }
+
+    /// Handles a message sent by [`NbtInput::view`], `wrap` turns it back into
+    /// the message of the view this input belongs to. Returns whether the
+    /// file was read again or its format changed.
+    pub fn update(
+        &mut self,
+        message: NbtInputMessage,
+        wrap: impl Fn(NbtInputMessage) -> BEditorMessage + Send + 'static,
+    ) -> (bool, Command<BEditorMessage>) {
+        match message {
+            NbtInputMessage::SetPath(v) => self.path = v,
+            NbtInputMessage::Open => self.load(true),
+            NbtInputMessage::PickFile => {
+                let command = Command::perform(nbt_view::pick_file(), move |v| {
+                    wrap(NbtInputMessage::FilePicked(v))
+                });
+
+                return (false, command);
+            }
+            NbtInputMessage::FilePicked(Some(v)) => {
+                self.path = v.to_string_lossy().to_string();
+                self.load(true);
+            }
+            NbtInputMessage::FilePicked(None) => return (false, Command::none()),
+            NbtInputMessage::SetEndian(v) => {
+                self.endian = v;
+                self.reformat();
+            }
+            NbtInputMessage::SetHeader(v) => {
+                self.header = v;
+                self.reformat();
+            }
+            NbtInputMessage::Save => {
+                self.status = Some(self.save());
+                return (false, Command::none());
+            }
+        }
+
+        (true, Command::none())
+    }
+
+    /// Renders the path, format and status of the file, with a Save button if `save` is set.
+    pub fn view(
+        &self,
+        title: &str,
+        save: bool,
+        wrap: impl Fn(NbtInputMessage) -> BEditorMessage + Copy + 'static,
+    ) -> Element<'_, BEditorMessage> {
+        let mut row = Row::new()
+            .push(
+                TextInput::new("Your Path", &self.path)
+                    .on_input(move |v| wrap(NbtInputMessage::SetPath(v)))
+                    .on_submit(wrap(NbtInputMessage::Open)),
+            )
+            .push(Button::new(Text::new("Browse...")).on_press(wrap(NbtInputMessage::PickFile)));
+
+        if save {
+            row = row.push(Button::new(Text::new("Save")).on_press(wrap(NbtInputMessage::Save)));
+        }
+
+        Column::new()
+            .push(Text::new(title.to_string()))
+            .push(row)
+            .push(
+                Row::new()
+                    .push(PickList::new(
+                        &NbtEndian::ALL[..],
+                        Some(self.endian),
+                        move |v| wrap(NbtInputMessage::SetEndian(v)),
+                    ))
+                    .push(PickList::new(
+                        &NbtHeader::ALL[..],
+                        Some(self.header),
+                        move |v| wrap(NbtInputMessage::SetHeader(v)),
+                    )),
+            )
+            .push(match (&self.nbt, &self.status) {
+                (Err(e), _) => Text::new(e.clone()).style(ERROR_COLOR),
+                (Ok(_), Some(Err(e))) => Text::new(e.clone()).style(ERROR_COLOR),
+                (Ok(_), Some(Ok(v))) => Text::new(v.clone()),
+                (Ok(_), None) => Text::new(""),
+            })
+            .width(Length::Fill)
+            .into()
+    }
+}
@@ -0,0 +1,233 @@
+use bedrock_rs::nbt::NbtTag;
+
+use crate::nbt_diff;
+use crate::nbt_diff::DiffKind;
+use crate::nbt_edit;
+use crate::nbt_path::{NbtPath, NbtPathSegment};
+
+/// Outcome of a three-way merge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NbtMerge {
+    /// The base with every change that doesn't conflict applied, conflicting
+    /// tags are left as they are in the base
+    pub merged: NbtTag,
+    /// Tags both sides changed in different ways
+    pub conflicts: Vec<NbtPath>,
+    /// Number of changes that were applied
+    pub applied: usize,
+}
+
+/// Paths that changed from `base` to `side`, none of them below another.
+/// Adding or removing list elements counts as a change of the whole list,
+/// so the indices of the other side can't shift under it.
+fn changes(base: &NbtTag, side: &NbtTag) -> Vec<NbtPath> {
+    let mut paths: Vec<NbtPath> = Vec::new();
+
+    for diff in nbt_diff::diff(base, side) {
+        let path = match (diff.kind, diff.path.split_last()) {
+            (DiffKind::Added | DiffKind::Removed, Some((parent, NbtPathSegment::Index(_)))) => {
+                parent
+            }
+            _ => diff.path,
+        };
+
+        if paths.iter().any(|v| path.starts_with(v)) {
+            continue;
+        }
+
+        paths.retain(|v| !v.starts_with(&path));
+        paths.push(path);
+    }
+
+    paths
+}
+
+/// Makes the tag at `path` in `root` be `tag`, removing it for `None`.
+fn put(root: &mut NbtTag, path: &NbtPath, tag: Option<NbtTag>) -> Result<(), String> {
+    if let Some(v) = path.get_mut(root) {
+        match tag {
+            Some(tag) => *v = tag,
+            None => {
+                nbt_edit::remove(root, path)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    match tag {
+        Some(tag) => nbt_edit::insert(root, path, tag),
+        None => Ok(()),
+    }
+}
+
+/// Merges the changes `ours` and `theirs` made to `base`. Changes to
+/// different tags are combined, the same change made on both sides is applied
+/// once, and tags changed differently on both sides become conflicts.
+pub fn merge(base: &NbtTag, ours: &NbtTag, theirs: &NbtTag) -> NbtMerge {
+    let our_changes = changes(base, ours);
+    let their_changes = changes(base, theirs);
+
+    let mut conflicts: Vec<NbtPath> = Vec::new();
+
+    for a in our_changes.iter() {
+        for b in their_changes.iter() {
+            let top = match (a.starts_with(b), b.starts_with(a)) {
+                (true, _) => b,
+                (_, true) => a,
+                _ => continue,
+            };
+
+            if top.get(ours) != top.get(theirs) && !conflicts.contains(top) {
+                conflicts.push(top.clone());
+            }
+        }
+    }
+
+    let mut merged = base.clone();
+    let mut applied = 0;
+
+    for path in our_changes.iter() {
+        if conflicts.iter().any(|v| path.starts_with(v)) {
+            continue;
+        }
+
+        match put(&mut merged, path, path.get(ours).cloned()) {
+            Ok(_) => applied += 1,
+            // Left for the user to pick, like a conflict
+            Err(_) => conflicts.push(path.clone()),
+        }
+    }
+
+    for path in their_changes.iter() {
+        // Changes at or below one of ours either conflict or were the same
+        if our_changes.iter().any(|v| path.starts_with(v))
+            || conflicts.iter().any(|v| path.starts_with(v))
+        {
+            continue;
+        }
+
+        match put(&mut merged, path, path.get(theirs).cloned()) {
+            Ok(_) => applied += 1,
+            Err(_) => conflicts.push(path.clone()),
+        }
+    }
+
+    NbtMerge {
+        merged,
+        conflicts,
+        applied,
+    }
+}
+
+/// Resolves the conflict at `path` by taking the tag from `from`, which is
+/// the base or one of the sides.
+pub fn resolve(merged: &mut NbtTag, path: &NbtPath, from: &NbtTag) -> Result<(), String> {
+    put(merged, path, path.get(from).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compound(entries: &[(&str, NbtTag)]) -> NbtTag {
+        NbtTag::Compound(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    fn base() -> NbtTag {
+        compound(&[
+            ("a", NbtTag::Int32(1)),
+            ("b", NbtTag::Int32(2)),
+            ("list", NbtTag::List(vec![NbtTag::Byte(0), NbtTag::Byte(1)])),
+        ])
+    }
+
+    /// The base with the tag at `path` replaced, or removed for `None`.
+    fn with(path: &NbtPath, tag: Option<NbtTag>) -> NbtTag {
+        let mut root = base();
+        put(&mut root, path, tag).unwrap();
+        root
+    }
+
+    #[test]
+    fn changes_to_different_tags_are_combined() {
+        let a = NbtPath::root().key("a");
+        let b = NbtPath::root().key("b");
+
+        let ours = with(&a, Some(NbtTag::Int32(10)));
+        let theirs = with(&b, None);
+
+        let result = merge(&base(), &ours, &theirs);
+
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.applied, 2);
+        assert_eq!(a.get(&result.merged), Some(&NbtTag::Int32(10)));
+        assert_eq!(b.get(&result.merged), None);
+    }
+
+    #[test]
+    fn the_same_change_is_applied_once() {
+        let c = NbtPath::root().key("c");
+        let side = with(&c, Some(NbtTag::Int32(3)));
+
+        let result = merge(&base(), &side, &side);
+
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.applied, 1);
+        assert_eq!(result.merged, side);
+    }
+
+    #[test]
+    fn different_changes_to_a_tag_conflict() {
+        let a = NbtPath::root().key("a");
+
+        let ours = with(&a, Some(NbtTag::Int32(10)));
+        let theirs = with(&a, Some(NbtTag::Int32(20)));
+
+        let mut result = merge(&base(), &ours, &theirs);
+
+        assert_eq!(result.conflicts, std::slice::from_ref(&a));
+        assert_eq!(a.get(&result.merged), Some(&NbtTag::Int32(1)));
+
+        resolve(&mut result.merged, &a, &theirs).unwrap();
+        assert_eq!(result.merged, theirs);
+    }
+
+    #[test]
+    fn added_list_elements_conflict_with_element_changes() {
+        let list = NbtPath::root().key("list");
+
+        let ours = with(
+            &list,
+            Some(NbtTag::List(vec![
+                NbtTag::Byte(9),
+                NbtTag::Byte(0),
+                NbtTag::Byte(1),
+            ])),
+        );
+        let theirs = with(&list.index(1), Some(NbtTag::Byte(5)));
+
+        let result = merge(&base(), &ours, &theirs);
+
+        assert_eq!(result.conflicts, std::slice::from_ref(&list));
+        assert_eq!(list.get(&result.merged), list.get(&base()));
+    }
+
+    #[test]
+    fn removal_conflicts_with_a_change_below() {
+        let list = NbtPath::root().key("list");
+
+        let ours = with(&list, None);
+        let theirs = with(&list.index(0), Some(NbtTag::Byte(7)));
+
+        let result = merge(&base(), &ours, &theirs);
+
+        assert_eq!(result.conflicts, [list]);
+        assert_eq!(result.applied, 0);
+    }
+}
@@ -0,0 +1,132 @@
+use bedrock_rs::nbt::NbtTag;
+
+use crate::nbt_query::NbtPathQuery;
+
+/// One step from a tag to one of its children.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NbtPathSegment {
+    /// Key of a compound entry
+    Key(String),
+    /// Index of a list element
+    Index(usize),
+}
+
+/// Location of a tag inside an Nbt tree, relative to the root tag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct NbtPath(Vec<NbtPathSegment>);
+
+impl NbtPath {
+    pub fn root() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn key(&self, key: impl Into<String>) -> Self {
+        let mut path = self.clone();
+        path.0.push(NbtPathSegment::Key(key.into()));
+        path
+    }
+
+    pub fn index(&self, index: usize) -> Self {
+        let mut path = self.clone();
+        path.0.push(NbtPathSegment::Index(index));
+        path
+    }
+
+    pub fn push(&mut self, segment: NbtPathSegment) {
+        self.0.push(segment);
+    }
+
+    pub fn pop(&mut self) -> Option<NbtPathSegment> {
+        self.0.pop()
+    }
+
+    /// Number of levels the tag is below the root.
+    pub fn depth(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the tag at this path is `other` or below it.
+    pub fn starts_with(&self, other: &NbtPath) -> bool {
+        self.0.starts_with(&other.0)
+    }
+
+    /// The path with `from`, which it has to start with, replaced by `to`.
+    pub fn rebase(&self, from: &NbtPath, to: &NbtPath) -> Option<NbtPath> {
+        match self.starts_with(from) {
+            true => {
+                let mut path = to.clone();
+                path.0.extend_from_slice(&self.0[from.0.len()..]);
+                Some(path)
+            }
+            false => None,
+        }
+    }
+
+    /// Splits the path into the path of the parent and the last segment.
+    pub fn split_last(&self) -> Option<(NbtPath, &NbtPathSegment)> {
+        self.0
+            .split_last()
+            .map(|(last, parent)| (NbtPath(parent.to_vec()), last))
+    }
+
+    /// Reads a path the way it is displayed, like `a.b[3].c`. Keys holding
+    /// `.`, `[`, `]`, `"` or `*` are written in double quotes, an empty text is the root.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match NbtPathQuery::parse(text)?.as_path() {
+            Some(v) => Ok(v),
+            None => Err(format!(
+                "\"{text}\" has wildcards, it has to name a single tag"
+            )),
+        }
+    }
+
+    pub fn get<'a>(&self, tag: &'a NbtTag) -> Option<&'a NbtTag> {
+        let mut current = tag;
+
+        for segment in self.0.iter() {
+            current = match (segment, current) {
+                (NbtPathSegment::Key(k), NbtTag::Compound(v)) => v.get(k)?,
+                (NbtPathSegment::Index(i), NbtTag::List(v)) => v.get(*i)?,
+                _ => return None,
+            };
+        }
+
+        Some(current)
+    }
+
+    pub fn get_mut<'a>(&self, tag: &'a mut NbtTag) -> Option<&'a mut NbtTag> {
+        let mut current = tag;
+
+        for segment in self.0.iter() {
+            current = match (segment, current) {
+                (NbtPathSegment::Key(k), NbtTag::Compound(v)) => v.get_mut(k)?,
+                (NbtPathSegment::Index(i), NbtTag::List(v)) => v.get_mut(*i)?,
+                _ => return None,
+            };
+        }
+
+        Some(current)
+    }
+}
+
+impl std::fmt::Display for NbtPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                NbtPathSegment::Key(k) => {
+                    if i != 0 {
+                        write!(f, ".")?;
+                    }
+
+                    match k.is_empty() || k.contains(['.', '[', ']', '"', '*']) {
+                        true => write!(f, "\"{}\"", k.replace('\\', "\\\\").replace('"', "\\\""))?,
+                        false => write!(f, "{k}")?,
+                    }
+                }
+                NbtPathSegment::Index(v) => write!(f, "[{v}]")?,
+            }
+        }
+
+        Ok(())
+    }
+}
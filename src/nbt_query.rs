@@ -0,0 +1,327 @@
+use std::collections::HashSet;
+
+use bedrock_rs::nbt::NbtTag;
+
+use crate::nbt_path::{NbtPath, NbtPathSegment};
+
+/// One step of a path query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuerySegment {
+    /// Key of a compound entry
+    Key(String),
+    /// Index of a list element
+    Index(usize),
+    /// `*`, every compound entry and list element
+    AnyChild,
+    /// `[*]`, every list element
+    AnyIndex,
+    /// `**`, the tag itself and everything below it
+    Descendants,
+}
+
+/// A path that may match several tags, like `Inventory[*].Name` or `**.Count`.
+/// Paths without wildcards are written the same way as [`NbtPath`]s.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NbtPathQuery(Vec<QuerySegment>);
+
+impl NbtPathQuery {
+    /// Reads a query like `a.b[3].c`, `a.*.c`, `a[*]` or `**.c`. Keys holding
+    /// `.`, `[`, `]`, `"` or `*` are written in double quotes, an empty text is the root.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut chars = text.chars().peekable();
+        // Whether the next segment has to be a key, either at the start or after a '.'
+        let mut key_next = true;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '.' if key_next => return Err(format!("Missing key before '.' in \"{text}\"")),
+                '.' => key_next = true,
+                '[' if segments.is_empty() || !key_next => {
+                    let mut index = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c) => index.push(c),
+                            None => return Err(format!("Missing ']' in \"{text}\"")),
+                        }
+                    }
+
+                    segments.push(match index.trim() {
+                        "*" => QuerySegment::AnyIndex,
+                        v => match v.parse::<usize>() {
+                            Ok(v) => QuerySegment::Index(v),
+                            Err(_) => return Err(format!("Invalid list index \"{index}\"")),
+                        },
+                    });
+
+                    key_next = false;
+                }
+                _ if !key_next => {
+                    return Err(format!("Expected '.' or '[' before '{c}' in \"{text}\""))
+                }
+                '"' => {
+                    let mut key = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(c) => key.push(c),
+                                None => return Err(format!("Unterminated key in \"{text}\"")),
+                            },
+                            Some(c) => key.push(c),
+                            None => return Err(format!("Unterminated key in \"{text}\"")),
+                        }
+                    }
+
+                    segments.push(QuerySegment::Key(key));
+                    key_next = false;
+                }
+                '[' | ']' => return Err(format!("Expected a key before '{c}' in \"{text}\"")),
+                c => {
+                    let mut key = String::from(c);
+
+                    while let Some(c) = chars.next_if(|c| !matches!(c, '.' | '[' | ']' | '"')) {
+                        key.push(c);
+                    }
+
+                    segments.push(match key.as_str() {
+                        "*" => QuerySegment::AnyChild,
+                        "**" => QuerySegment::Descendants,
+                        _ => QuerySegment::Key(key),
+                    });
+
+                    key_next = false;
+                }
+            }
+        }
+
+        if key_next && !segments.is_empty() {
+            return Err(format!("Missing key after '.' in \"{text}\""));
+        }
+
+        Ok(Self(segments))
+    }
+
+    /// The single path this query names, `None` if it has wildcards.
+    pub fn as_path(&self) -> Option<NbtPath> {
+        let mut path = NbtPath::root();
+
+        for segment in self.0.iter() {
+            path.push(match segment {
+                QuerySegment::Key(k) => NbtPathSegment::Key(k.clone()),
+                QuerySegment::Index(i) => NbtPathSegment::Index(*i),
+                _ => return None,
+            });
+        }
+
+        Some(path)
+    }
+
+    /// Paths of the tags below and including `tag` the query matches, in the
+    /// order they are shown with compound keys sorted.
+    pub fn matches(&self, tag: &NbtTag) -> Vec<NbtPath> {
+        let mut paths = Vec::new();
+        let mut seen = HashSet::new();
+
+        match_into(tag, NbtPath::root(), &self.0, &mut seen, &mut paths);
+
+        paths
+    }
+}
+
+fn match_into(
+    tag: &NbtTag,
+    path: NbtPath,
+    segments: &[QuerySegment],
+    seen: &mut HashSet<NbtPath>,
+    paths: &mut Vec<NbtPath>,
+) {
+    let Some((first, rest)) = segments.split_first() else {
+        // "**.**" and the like reach the same tag more than once
+        if seen.insert(path.clone()) {
+            paths.push(path);
+        }
+
+        return;
+    };
+
+    match (first, tag) {
+        (QuerySegment::Key(k), NbtTag::Compound(v)) => {
+            if let Some(nbt) = v.get(k) {
+                match_into(nbt, path.key(k.clone()), rest, seen, paths);
+            }
+        }
+        (QuerySegment::Index(i), NbtTag::List(v)) => {
+            if let Some(nbt) = v.get(*i) {
+                match_into(nbt, path.index(*i), rest, seen, paths);
+            }
+        }
+        (QuerySegment::AnyChild, _) => {
+            for (child, nbt) in children(tag, &path) {
+                match_into(nbt, child, rest, seen, paths);
+            }
+        }
+        (QuerySegment::AnyIndex, NbtTag::List(v)) => {
+            for (i, nbt) in v.iter().enumerate() {
+                match_into(nbt, path.index(i), rest, seen, paths);
+            }
+        }
+        (QuerySegment::Descendants, _) => {
+            match_into(tag, path.clone(), rest, seen, paths);
+
+            for (child, nbt) in children(tag, &path) {
+                match_into(nbt, child, segments, seen, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The compound entries sorted by key, or the list elements, with their paths.
+fn children<'a>(tag: &'a NbtTag, path: &NbtPath) -> Vec<(NbtPath, &'a NbtTag)> {
+    match tag {
+        NbtTag::List(v) => v
+            .iter()
+            .enumerate()
+            .map(|(i, nbt)| (path.index(i), nbt))
+            .collect(),
+        NbtTag::Compound(v) => {
+            let mut keys: Vec<&String> = v.keys().collect();
+            keys.sort();
+
+            keys.into_iter()
+                .map(|k| (path.key(k.clone()), &v[k]))
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compound(entries: &[(&str, NbtTag)]) -> NbtTag {
+        NbtTag::Compound(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    fn item(name: &str, count: u8) -> NbtTag {
+        compound(&[
+            ("Name", NbtTag::String(name.to_string())),
+            ("Count", NbtTag::Byte(count)),
+        ])
+    }
+
+    fn player() -> NbtTag {
+        compound(&[
+            (
+                "Inventory",
+                NbtTag::List(vec![item("minecraft:dirt", 64), item("minecraft:torch", 3)]),
+            ),
+            ("Offhand", item("minecraft:shield", 1)),
+            ("Health", NbtTag::Int16(20)),
+        ])
+    }
+
+    fn matches(query: &str) -> Vec<NbtPath> {
+        NbtPathQuery::parse(query).unwrap().matches(&player())
+    }
+
+    #[test]
+    fn parses_keys_indices_and_wildcards() {
+        use QuerySegment::*;
+
+        let query = NbtPathQuery::parse(r#"a[2]."b.c"[*].*.**"#).unwrap();
+
+        assert_eq!(
+            query.0,
+            [
+                Key(String::from("a")),
+                Index(2),
+                Key(String::from("b.c")),
+                AnyIndex,
+                AnyChild,
+                Descendants,
+            ]
+        );
+        assert_eq!(
+            NbtPathQuery::parse(r#""\"*\"""#).unwrap().0,
+            [Key(String::from("\"*\""))]
+        );
+        assert_eq!(NbtPathQuery::parse("[0]").unwrap().0, [Index(0)]);
+        assert!(NbtPathQuery::parse("").unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        for query in [
+            "a.", ".a", "a..b", "a[1", "a[x]", "a]", "a\"b\"", "\"a", "a[0]b",
+        ] {
+            assert!(NbtPathQuery::parse(query).is_err(), "{query}");
+        }
+    }
+
+    #[test]
+    fn only_plain_queries_are_paths() {
+        let path = NbtPathQuery::parse("Inventory[1].Name").unwrap().as_path();
+
+        assert_eq!(
+            path,
+            Some(NbtPath::root().key("Inventory").index(1).key("Name"))
+        );
+        assert_eq!(NbtPathQuery::parse("Inventory[*]").unwrap().as_path(), None);
+        assert_eq!(
+            NbtPathQuery::parse("").unwrap().as_path(),
+            Some(NbtPath::root())
+        );
+    }
+
+    #[test]
+    fn wildcards_match_in_display_order() {
+        let inventory = NbtPath::root().key("Inventory");
+
+        assert_eq!(
+            matches("Inventory[*].Name"),
+            [
+                inventory.index(0).key("Name"),
+                inventory.index(1).key("Name")
+            ]
+        );
+        assert_eq!(
+            matches("**.Count"),
+            [
+                inventory.index(0).key("Count"),
+                inventory.index(1).key("Count"),
+                NbtPath::root().key("Offhand").key("Count"),
+            ]
+        );
+        assert_eq!(
+            matches("*"),
+            [
+                NbtPath::root().key("Health"),
+                inventory,
+                NbtPath::root().key("Offhand")
+            ]
+        );
+        assert!(matches("Health[*]").is_empty());
+        assert!(matches("Missing.**").is_empty());
+    }
+
+    #[test]
+    fn repeated_descendants_match_each_tag_once() {
+        let all = matches("**");
+        let twice = matches("**.**");
+
+        assert_eq!(all.len(), 1 + 3 + 2 + 2 * 2 + 2);
+        assert_eq!(twice.len(), all.len());
+        assert_eq!(all[0], NbtPath::root());
+    }
+}
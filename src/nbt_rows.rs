@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+
+use bedrock_rs::nbt::NbtTag;
+
+use beditor::nbt_decode::NbtLayout;
+use beditor::nbt_path::NbtPath;
+
+/// What a line of the flattened tree shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NbtRowKind {
+    /// The tag itself, for compounds and lists the opening line
+    Tag,
+    /// Closing bracket of an expanded compound or list
+    End(&'static str),
+}
+
+/// One line of an Nbt tree as it is shown in the NbtView.
+#[derive(Debug, Clone)]
+pub struct NbtRow {
+    pub path: NbtPath,
+    /// Key of compound entries and the name of the root, empty for list elements
+    pub name: String,
+    pub kind: NbtRowKind,
+}
+
+/// Flattens `tag` into the lines shown for it, only descending into the
+/// compounds and lists in `expanded`. Compound entries are in the key order of
+/// `layout`, the order they will be saved in. Tags `keep` returns false for
+/// are left out together with their children.
+pub fn flatten(
+    name: &str,
+    tag: &NbtTag,
+    layout: &NbtLayout,
+    expanded: &HashSet<NbtPath>,
+    keep: &dyn Fn(&NbtPath) -> bool,
+) -> Vec<NbtRow> {
+    let mut rows = Vec::new();
+
+    flatten_into(
+        name.to_string(),
+        tag,
+        NbtPath::root(),
+        layout,
+        expanded,
+        keep,
+        &mut rows,
+    );
+
+    rows
+}
+
+fn flatten_into(
+    name: String,
+    tag: &NbtTag,
+    path: NbtPath,
+    layout: &NbtLayout,
+    expanded: &HashSet<NbtPath>,
+    keep: &dyn Fn(&NbtPath) -> bool,
+    rows: &mut Vec<NbtRow>,
+) {
+    if !keep(&path) {
+        return;
+    }
+
+    let end = match tag {
+        NbtTag::List(_) => "]",
+        NbtTag::Compound(_) => "}",
+        _ => "",
+    };
+
+    let open = !end.is_empty() && expanded.contains(&path);
+
+    rows.push(NbtRow {
+        path: path.clone(),
+        name,
+        kind: NbtRowKind::Tag,
+    });
+
+    if !open {
+        return;
+    }
+
+    match tag {
+        NbtTag::List(v) => {
+            for (i, nbt) in v.iter().enumerate() {
+                let path = path.index(i);
+                flatten_into(String::new(), nbt, path, layout, expanded, keep, rows);
+            }
+        }
+        NbtTag::Compound(v) => {
+            for key in layout.ordered_keys(&path, v) {
+                flatten_into(
+                    key.clone(),
+                    &v[key],
+                    path.key(key.clone()),
+                    layout,
+                    expanded,
+                    keep,
+                    rows,
+                );
+            }
+        }
+        _ => {}
+    }
+
+    rows.push(NbtRow {
+        path,
+        name: String::new(),
+        kind: NbtRowKind::End(end),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn compound_rows_follow_the_layout() {
+        let tag = NbtTag::Compound(HashMap::from([
+            (String::from("b"), NbtTag::Byte(0)),
+            (String::from("c"), NbtTag::Byte(0)),
+            (String::from("a"), NbtTag::Byte(0)),
+        ]));
+
+        let mut layout = NbtLayout::default();
+        layout
+            .keys
+            .insert(NbtPath::root(), vec![String::from("c"), String::from("a")]);
+
+        let expanded = HashSet::from([NbtPath::root()]);
+        let rows = flatten("", &tag, &layout, &expanded, &|_| true);
+        let names: Vec<&str> = rows.iter().map(|v| v.name.as_str()).collect();
+
+        assert_eq!(names, ["", "c", "a", "b", ""]);
+    }
+}
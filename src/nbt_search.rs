@@ -0,0 +1,204 @@
+use bedrock_rs::nbt::NbtTag;
+use regex::Regex;
+
+use crate::nbt_decode::NbtLayout;
+use crate::nbt_edit;
+use crate::nbt_edit::NbtTagKind;
+use crate::nbt_path::NbtPath;
+use crate::nbt_query::NbtPathQuery;
+
+/// How the text of a search is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// Found anywhere in keys and strings ignoring case, numbers have to be equal
+    #[default]
+    Text,
+    Regex,
+    /// A path query like `**.Count`, see [`NbtPathQuery`]
+    Path,
+}
+
+impl SearchMode {
+    pub const ALL: [SearchMode; 3] = [SearchMode::Text, SearchMode::Regex, SearchMode::Path];
+}
+
+impl std::fmt::Display for SearchMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SearchMode::Text => "Text",
+                SearchMode::Regex => "Regex",
+                SearchMode::Path => "Path",
+            }
+        )
+    }
+}
+
+/// Restricts a search to tags of one kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KindFilter {
+    #[default]
+    Any,
+    Kind(NbtTagKind),
+}
+
+impl KindFilter {
+    pub const ALL: [KindFilter; 10] = [
+        KindFilter::Any,
+        KindFilter::Kind(NbtTagKind::Byte),
+        KindFilter::Kind(NbtTagKind::Int16),
+        KindFilter::Kind(NbtTagKind::Int32),
+        KindFilter::Kind(NbtTagKind::Int64),
+        KindFilter::Kind(NbtTagKind::Float32),
+        KindFilter::Kind(NbtTagKind::Float64),
+        KindFilter::Kind(NbtTagKind::String),
+        KindFilter::Kind(NbtTagKind::List),
+        KindFilter::Kind(NbtTagKind::Compound),
+    ];
+
+    fn accepts(&self, tag: &NbtTag) -> bool {
+        match self {
+            KindFilter::Any => true,
+            KindFilter::Kind(kind) => NbtTagKind::of(tag) == Some(*kind),
+        }
+    }
+}
+
+impl std::fmt::Display for KindFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KindFilter::Any => write!(f, "Any Type"),
+            KindFilter::Kind(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    /// Lowercased text, matched anywhere and ignoring case
+    Text(String),
+    Regex(Regex),
+    Path(NbtPathQuery),
+}
+
+/// What to look for in keys, strings and numbers.
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pattern: Pattern,
+    kind: KindFilter,
+}
+
+impl SearchQuery {
+    /// Builds a query from the text of the search bar.
+    pub fn new(text: &str, mode: SearchMode, kind: KindFilter) -> Result<Self, String> {
+        let pattern = match mode {
+            SearchMode::Text => Pattern::Text(text.to_lowercase()),
+            SearchMode::Regex => match Regex::new(text) {
+                Ok(v) => Pattern::Regex(v),
+                Err(e) => return Err(format!("Invalid regex: {e}")),
+            },
+            SearchMode::Path => Pattern::Path(NbtPathQuery::parse(text)?),
+        };
+
+        Ok(Self { pattern, kind })
+    }
+
+    fn matches_text(&self, text: &str) -> bool {
+        match &self.pattern {
+            Pattern::Text(v) => text.to_lowercase().contains(v.as_str()),
+            Pattern::Regex(v) => v.is_match(text),
+            Pattern::Path(_) => false,
+        }
+    }
+
+    /// Numbers match when they are equal to the searched one, so 1 doesn't
+    /// find every 10 and 21. A regex is matched against the written number.
+    fn matches_number(&self, tag: &NbtTag) -> bool {
+        let Some(text) = nbt_edit::scalar_to_string(tag) else {
+            return false;
+        };
+
+        match &self.pattern {
+            Pattern::Text(v) => match (v.parse::<f64>(), text.parse::<f64>()) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => false,
+            },
+            Pattern::Regex(v) => v.is_match(&text),
+            Pattern::Path(_) => false,
+        }
+    }
+}
+
+/// A tag whose key or value matches a query, path queries mark both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NbtHit {
+    pub path: NbtPath,
+    pub key: bool,
+    pub value: bool,
+}
+
+/// Finds the tags below and including the root matching `query`, in the order
+/// they are shown with compound keys in the order of `layout`.
+pub fn search(name: &str, tag: &NbtTag, layout: &NbtLayout, query: &SearchQuery) -> Vec<NbtHit> {
+    if let Pattern::Path(v) = &query.pattern {
+        return v
+            .matches(tag)
+            .into_iter()
+            .filter(|path| path.get(tag).is_some_and(|v| query.kind.accepts(v)))
+            .map(|path| NbtHit {
+                path,
+                key: true,
+                value: true,
+            })
+            .collect();
+    }
+
+    let mut hits = Vec::new();
+
+    search_into(name, tag, NbtPath::root(), layout, query, &mut hits);
+
+    hits
+}
+
+fn search_into(
+    name: &str,
+    tag: &NbtTag,
+    path: NbtPath,
+    layout: &NbtLayout,
+    query: &SearchQuery,
+    hits: &mut Vec<NbtHit>,
+) {
+    if query.kind.accepts(tag) {
+        let key = !name.is_empty() && query.matches_text(name);
+
+        let value = match tag {
+            NbtTag::String(v) => query.matches_text(v),
+            NbtTag::List(_) | NbtTag::Compound(_) | NbtTag::Empty => false,
+            _ => query.matches_number(tag),
+        };
+
+        if key || value {
+            hits.push(NbtHit {
+                path: path.clone(),
+                key,
+                value,
+            });
+        }
+    }
+
+    match tag {
+        NbtTag::List(v) => {
+            for (i, nbt) in v.iter().enumerate() {
+                search_into("", nbt, path.index(i), layout, query, hits);
+            }
+        }
+        NbtTag::Compound(v) => {
+            for key in layout.ordered_keys(&path, v) {
+                search_into(key, &v[key], path.key(key.clone()), layout, query, hits);
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,249 +1,1369 @@
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 
-use bedrock_rs::core::read::ByteStreamRead;
-use bedrock_rs::nbt::big_endian::NbtBigEndian;
-use bedrock_rs::nbt::little_endian::NbtLittleEndian;
-use bedrock_rs::nbt::little_endian_network::NbtLittleEndianNetwork;
+use beditor::backup;
+use beditor::detect;
+use beditor::detect::Detection;
+use beditor::document::{DocumentError, NbtDocument, NbtEndian, NbtHeader};
+use beditor::history::{History, NbtCommand};
+use beditor::json;
+use beditor::nbt_decode;
+use beditor::nbt_decode::{DecodeError, NbtLayout, NbtSpan};
+use beditor::nbt_edit;
+use beditor::nbt_edit::NbtTagKind;
+use beditor::nbt_path::{NbtPath, NbtPathSegment};
+use beditor::nbt_search;
+use beditor::nbt_search::{KindFilter, NbtHit, SearchMode, SearchQuery};
+use beditor::snbt;
 use bedrock_rs::nbt::NbtTag;
-use iced::widget::{Column, Row, Scrollable, Text, TextInput};
-use iced::{Element, Length, Padding, Sandbox};
+use iced::widget::scrollable::AbsoluteOffset;
+use iced::widget::{scrollable, Button, Checkbox, Column, Row, Scrollable, Space, Text, TextInput};
+use iced::{theme, Alignment, Color, Command, Element, Font, Length, Padding};
 
 use crate::messages::BEditorMessage;
+use crate::nbt_rows;
+use crate::nbt_rows::{NbtRow, NbtRowKind};
 use crate::view::BEditorView;
 
-pub const INDENTATION: f32 = 3.0;
+pub const INDENTATION: f32 = 16.0;
+pub const ROW_HEIGHT: f32 = 32.0;
+/// Assumed height of the tree until the first scroll event reports the real one
+const DEFAULT_VIEWPORT_HEIGHT: f32 = 1080.0;
+pub const EDIT_WIDTH: f32 = 200.0;
+pub const ERROR_COLOR: Color = Color::from_rgb(0.8, 0.2, 0.2);
+const EXPAND_DEPTHS: [usize; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+/// Bytes per line of hex dumps
+const HEX_LINE: usize = 16;
+const HEX_ROW_HEIGHT: f32 = 20.0;
+const HEX_TYPE_COLOR: Color = Color::from_rgb(0.3, 0.5, 0.9);
+const HEX_NAME_COLOR: Color = Color::from_rgb(0.2, 0.7, 0.3);
+const HEX_PAYLOAD_COLOR: Color = Color::from_rgb(0.9, 0.5, 0.1);
+const HIT_COLOR: Color = Color::from_rgb(0.8, 0.6, 0.0);
+/// The hit that was jumped to last
+const CURRENT_HIT_COLOR: Color = Color::from_rgb(0.9, 0.3, 0.0);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum NbtEndian {
-    #[default]
-    Little,
-    LittleNetwork,
-    Big,
+pub struct NbtView {
+    path: String,
+    /// Bytes the document is read from instead of the file at `path`, for
+    /// records of a world
+    data: Option<Vec<u8>>,
+    nbt: Result<NbtDocument, String>,
+    endian: NbtEndian,
+    header: NbtHeader,
+    /// Text of scalar inputs that were edited, with the reason it was rejected if invalid
+    edits: HashMap<NbtPath, (String, Option<String>)>,
+    save_path: String,
+    /// Outcome of the last save or structural edit
+    status: Option<Result<String, String>>,
+    /// Kind of the tags created by the "+" buttons
+    insert_kind: NbtTagKind,
+    /// Compound entry being renamed and the name typed so far
+    renaming: Option<(NbtPath, String)>,
+    history: History,
+    /// Compounds and lists whose children are shown
+    expanded: HashSet<NbtPath>,
+    /// The tree flattened into the lines that are shown
+    rows: Vec<NbtRow>,
+    scroll_offset: f32,
+    viewport_height: f32,
+    /// Result of the last format detection, cleared when the format is picked by hand
+    detection: Option<Detection>,
+    /// Where reading the file failed, the tree read up to there is shown read only
+    failure: Option<DecodeError>,
+    /// The file as it was last read or written, or as the edited tree would
+    /// be saved, shown in the hex view
+    bytes: Vec<u8>,
+    /// Where each tag is in `bytes`
+    spans: Vec<(NbtPath, NbtSpan)>,
+    /// Why the edited tree couldn't be encoded, the hex view shows the file then
+    hex_error: Option<String>,
+    show_hex: bool,
+    /// Tag highlighted in the hex view
+    selected: Option<NbtPath>,
+    hex_scroll_offset: f32,
+    hex_viewport_height: f32,
+    search: String,
+    search_mode: SearchMode,
+    search_kind: KindFilter,
+    /// Matches of the search in the order they are shown
+    search_hits: Vec<NbtHit>,
+    /// Index of every hit in `search_hits` by path
+    search_index: HashMap<NbtPath, usize>,
+    /// Compounds and lists with hits below them
+    search_ancestors: HashSet<NbtPath>,
+    /// Why the search text is invalid
+    search_error: Option<String>,
+    /// Hit that was jumped to last
+    search_current: Option<usize>,
+    /// Only show the hits, their children and ancestors
+    search_filter: bool,
 }
 
-impl NbtEndian {
-    const ALL: [NbtEndian; 3] = [NbtEndian::Little, NbtEndian::LittleNetwork, NbtEndian::Big];
-}
+impl NbtView {
+    /// The bytes the document is read from.
+    fn read_data(&self) -> std::io::Result<Vec<u8>> {
+        match &self.data {
+            Some(v) => Ok(v.clone()),
+            None => fs::read(&self.path),
+        }
+    }
 
-impl std::fmt::Display for NbtEndian {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                NbtEndian::Little => "Little Endian",
-                NbtEndian::LittleNetwork => "Little Endian Network",
-                NbtEndian::Big => "Big Endian",
-            }
-        )
+    fn parse_nbt(&self) -> Result<NbtDocument, DocumentError> {
+        match self.read_data() {
+            Ok(v) => NbtDocument::parse(v, self.endian, self.header),
+            Err(e) => Err(DocumentError::Read(e)),
+        }
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum NbtHeader {
-    #[default]
-    None,
-    Normal,
-    LevelDat,
-}
+    /// Shows a freshly parsed document, or why it couldn't be parsed.
+    fn set_parsed(&mut self, result: Result<NbtDocument, DocumentError>) {
+        self.failure = None;
 
-impl NbtHeader {
-    const ALL: [NbtHeader; 3] = [NbtHeader::None, NbtHeader::Normal, NbtHeader::LevelDat];
-}
+        self.nbt = match result {
+            Ok(v) => Ok(v),
+            Err(e) => {
+                let message = e.to_string();
+
+                if let DocumentError::Decode(e) = e {
+                    self.failure = Some(*e);
+                }
 
-impl std::fmt::Display for NbtHeader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                NbtHeader::None => "No Header",
-                NbtHeader::Normal => "Normal Header",
-                NbtHeader::LevelDat => "Level.dat Header",
+                Err(message)
             }
-        )
+        };
     }
-}
 
-pub struct NbtView {
-    path: String,
-    nbt: Result<(String, NbtTag, Option<(i32, i32)>), String>,
-    endian: NbtEndian,
-    header: NbtHeader,
-}
+    /// Root name and tag that are shown, the partial tree if the file is damaged.
+    fn tree(&self) -> Option<(&str, &NbtTag)> {
+        match (&self.nbt, &self.failure) {
+            (Ok(document), _) => Some((&document.name, &document.tag)),
+            (Err(_), Some(failure)) => failure.partial.as_ref().map(|(n, t)| (n.as_str(), t)),
+            (Err(_), None) => None,
+        }
+    }
 
-impl NbtView {
-    fn parse_nbt(&self) -> Result<(String, NbtTag, Option<(i32, i32)>), String> {
-        let data = match fs::read(self.path.clone()) {
+    /// Key order of the shown tree, the partial tree of a damaged file has none.
+    fn layout(&self) -> &NbtLayout {
+        static UNKNOWN: LazyLock<NbtLayout> = LazyLock::new(NbtLayout::default);
+
+        match &self.nbt {
+            Ok(document) => &document.layout,
+            Err(_) => &UNKNOWN,
+        }
+    }
+
+    /// Position of the compound entry at `path` among its siblings, and their number.
+    fn key_position(&self, path: &NbtPath) -> Option<(usize, usize)> {
+        let document = self.nbt.as_ref().ok()?;
+        nbt_edit::key_position(&document.tag, &document.layout, path)
+    }
+
+    /// Whether the shown tree can be edited, the partial tree of a damaged file can't.
+    fn editable(&self) -> bool {
+        self.nbt.is_ok()
+    }
+
+    /// Writes the document to `path`, which is shown from then on. A record
+    /// of a world stays shown as the record, `path` gets a copy of it.
+    fn save_nbt(&mut self, path: String) -> Result<String, String> {
+        let Ok(document) = &mut self.nbt else {
+            return Err(String::from("No Nbt loaded"));
+        };
+
+        backup::snapshot_level_dat(Path::new(&path))?;
+        document.save(Path::new(&path)).map_err(|e| e.to_string())?;
+
+        // A record stays bound to its world, the file is only a copy of it
+        if self.data.is_some() {
+            return Ok(match self.history.is_dirty() {
+                true => format!(
+                    "Saved a copy to {path}, the edits aren't saved to the world until Save"
+                ),
+                false => format!("Saved a copy to {path}"),
+            });
+        }
+
+        self.path = path.clone();
+        self.history.mark_saved();
+        self.load_bytes();
+
+        Ok(format!("Saved to {path}"))
+    }
+
+    fn export_json(&self, path: &Path) -> Result<String, String> {
+        let Ok(document) = &self.nbt else {
+            return Err(String::from("No Nbt loaded"));
+        };
+
+        match fs::write(path, json::to_json(document)) {
+            Ok(_) => Ok(format!("Exported JSON to {}", path.display())),
+            Err(e) => Err(format!("Error writing File: {e:?}")),
+        }
+    }
+
+    /// Replaces the document with a JSON export. It isn't written anywhere
+    /// until it's saved, Save As is prefilled with the path of the original file.
+    fn import_json(&mut self, path: &Path) -> Result<String, String> {
+        let text = match fs::read_to_string(path) {
             Ok(v) => v,
             Err(e) => {
                 return Err(format!("Error reading File: {e:?}"));
             }
         };
 
-        let mut stream = ByteStreamRead::from(data);
+        let document = json::from_json(&text).map_err(|e| e.to_string())?;
 
-        let mut header = None;
+        self.endian = document.endian;
+        self.header = document.header;
+        self.set_parsed(Ok(document));
+        self.detection = None;
+        self.path = String::new();
+        self.save_path = match path.extension().is_some_and(|v| v == "json") {
+            true => path.with_extension("").to_string_lossy().to_string(),
+            false => String::new(),
+        };
 
-        match self.header {
-            NbtHeader::None => {}
-            NbtHeader::Normal | NbtHeader::LevelDat => {
-                let first = match stream.read_i32le() {
-                    Ok(v) => v.0,
-                    Err(e) => {
-                        return Err(format!("Error reading Nbt header: {e:?}"));
-                    }
+        self.history = History::new();
+        self.history.mark_unsaved();
+        self.load_bytes();
+        self.expand_to_depth(Some(1));
+        self.scroll_offset = 0.0;
+        self.edits.clear();
+        self.renaming = None;
+
+        Ok(format!("Imported JSON from {}", path.display()))
+    }
+
+    /// Renders one line of the flattened tree.
+    fn row2element<'a>(&'a self, row: &'a NbtRow, root: &'a NbtTag) -> Element<'a, BEditorMessage> {
+        let padding = Padding {
+            top: 0.0,
+            right: 0.0,
+            bottom: 0.0,
+            left: row.path.depth() as f32 * INDENTATION,
+        };
+
+        let line = match (row.kind, row.path.get(root)) {
+            (NbtRowKind::End(end), _) => Row::new().push(Text::new(end)),
+            (NbtRowKind::Tag, None) => Row::new(),
+            (NbtRowKind::Tag, Some(tag)) => {
+                let mut line = Row::new();
+
+                if self.show_hex {
+                    let style = match self.selected.as_ref() == Some(&row.path) {
+                        true => theme::Button::Primary,
+                        false => theme::Button::Text,
+                    };
+
+                    line = line.push(
+                        Button::new(Text::new("#"))
+                            .style(style)
+                            .on_press(BEditorMessage::NbtViewSelect(row.path.clone())),
+                    );
+                }
+
+                let (key, value) = self.highlight(&row.path);
+                let line = line.push(self.name2element(&row.name, &row.path, key));
+
+                let scalar = |line, prefix, suffix| {
+                    self.scalar2element(line, prefix, suffix, tag, &row.path, value)
                 };
 
-                let second = match stream.read_i32le() {
-                    Ok(v) => v.0,
-                    Err(e) => {
-                        return Err(format!("Error reading Nbt header: {e:?}"));
+                let line = match tag {
+                    NbtTag::Byte(_) => scalar(line, "Byte(", ")"),
+                    NbtTag::Int16(_) => scalar(line, "Int16(", ")"),
+                    NbtTag::Int32(_) => scalar(line, "Int32(", ")"),
+                    NbtTag::Int64(_) => scalar(line, "Int64(", ")"),
+                    NbtTag::Float32(_) => scalar(line, "Float32(", ")"),
+                    NbtTag::Float64(_) => scalar(line, "Float64(", ")"),
+                    NbtTag::String(_) => scalar(line, "\"", "\""),
+                    NbtTag::List(v) => self.container2element(line, &row.path, "[", "]", v.len()),
+                    NbtTag::Compound(v) => {
+                        self.container2element(line, &row.path, "{", "}", v.len())
                     }
+                    NbtTag::Empty => line.push(Text::new(String::from("EMPTY"))),
                 };
 
-                header = Some((first, second))
+                self.controls2element(line, &row.path)
             }
+        };
+
+        line.padding(padding)
+            .height(Length::Fixed(ROW_HEIGHT))
+            .align_items(Alignment::Center)
+            .into()
+    }
+
+    /// Renders the rows inside the scrolled viewport, the rows above and below
+    /// are replaced by empty space of the same height.
+    fn rows2elements<'a>(&'a self, root: &'a NbtTag) -> Element<'a, BEditorMessage> {
+        let first = ((self.scroll_offset / ROW_HEIGHT).floor() as usize).min(self.rows.len());
+        let count = (self.viewport_height / ROW_HEIGHT).ceil() as usize + 1;
+        let last = (first + count).min(self.rows.len());
+
+        let mut col =
+            Column::new().push(Space::with_height(Length::Fixed(first as f32 * ROW_HEIGHT)));
+
+        for row in self.rows[first..last].iter() {
+            col = col.push(self.row2element(row, root));
+        }
+
+        col.push(Space::with_height(Length::Fixed(
+            (self.rows.len() - last) as f32 * ROW_HEIGHT,
+        )))
+        .into()
+    }
+
+    /// Appends the expand toggle and the opening bracket of a compound or list, a
+    /// collapsed one is closed on the same line and shows its number of children.
+    fn container2element<'a>(
+        &self,
+        row: Row<'a, BEditorMessage>,
+        path: &NbtPath,
+        open: &str,
+        close: &str,
+        len: usize,
+    ) -> Row<'a, BEditorMessage> {
+        let expanded = self.expanded.contains(path);
+
+        let row = row.push(
+            Button::new(Text::new(if expanded { "v" } else { ">" }))
+                .on_press(BEditorMessage::NbtViewToggleExpand(path.clone())),
+        );
+
+        let row = match expanded {
+            true => row.push(Text::new(open.to_string())),
+            false => row.push(Text::new(format!("{open} {len} entries {close}"))),
+        };
+
+        if !self.editable() {
+            return row;
         }
 
-        match self.endian {
-            NbtEndian::Little => match NbtTag::nbt_deserialize::<NbtLittleEndian>(&mut stream) {
-                Ok(v) => Ok((v.0, v.1, header)),
-                Err(e) => Err(format!("Error parsing Nbt: {e:?}")),
+        row.push(Button::new(Text::new("+")).on_press(BEditorMessage::NbtViewInsert(path.clone())))
+            .push(
+                Button::new(Text::new("Paste Into"))
+                    .on_press(BEditorMessage::NbtViewPaste(path.clone(), true)),
+            )
+    }
+
+    /// Renders the key of a compound entry, as an input while it is being renamed.
+    fn name2element<'a>(
+        &'a self,
+        name: &str,
+        path: &NbtPath,
+        color: Option<Color>,
+    ) -> Element<'a, BEditorMessage> {
+        match &self.renaming {
+            Some((p, v)) if p == path => Row::new()
+                .push(
+                    TextInput::new("Name", v)
+                        .on_input(BEditorMessage::NbtViewRenameInput)
+                        .on_submit(BEditorMessage::NbtViewRenameSubmit)
+                        .width(Length::Fixed(EDIT_WIDTH)),
+                )
+                .push(Text::new(": "))
+                .into(),
+            _ if name.is_empty() => Text::new("").into(),
+            _ => match color {
+                Some(v) => Text::new(format!("{name}: ")).style(v).into(),
+                None => Text::new(format!("{name}: ")).into(),
             },
-            NbtEndian::LittleNetwork => {
-                match NbtTag::nbt_deserialize::<NbtLittleEndianNetwork>(&mut stream) {
-                    Ok(v) => Ok((v.0, v.1, header)),
-                    Err(e) => Err(format!("Error parsing Nbt: {e:?}")),
+        }
+    }
+
+    /// Appends the buttons to copy the tag or its path, paste over, rename,
+    /// reorder, duplicate and remove the tag at `path`. Read only trees only
+    /// get the copy buttons.
+    fn controls2element<'a>(
+        &self,
+        row: Row<'a, BEditorMessage>,
+        path: &NbtPath,
+    ) -> Row<'a, BEditorMessage> {
+        let mut row = row
+            .push(
+                Button::new(Text::new("Copy"))
+                    .on_press(BEditorMessage::NbtViewCopySnbt(path.clone())),
+            )
+            .push(
+                Button::new(Text::new("Copy Path"))
+                    .on_press(BEditorMessage::NbtViewCopyPath(path.clone())),
+            );
+
+        if !self.editable() {
+            return row;
+        }
+
+        row = row.push(
+            Button::new(Text::new("Paste"))
+                .on_press(BEditorMessage::NbtViewPaste(path.clone(), false)),
+        );
+
+        match path.split_last() {
+            None => return row,
+            Some((_, NbtPathSegment::Key(_))) => {
+                row = row.push(
+                    Button::new(Text::new("Rename"))
+                        .on_press(BEditorMessage::NbtViewStartRename(path.clone())),
+                );
+
+                if let Some((i, len)) = self.key_position(path) {
+                    row = row.push(move_buttons(path, i, len));
                 }
             }
-            NbtEndian::Big => match NbtTag::nbt_deserialize::<NbtBigEndian>(&mut stream) {
-                Ok(v) => Ok((v.0, v.1, header)),
-                Err(e) => Err(format!("Error parsing Nbt: {e:?}")),
-            },
+            Some((parent, NbtPathSegment::Index(i))) => {
+                let len = match self.tree().and_then(|(_, v)| parent.get(v)) {
+                    Some(NbtTag::List(v)) => v.len(),
+                    _ => 0,
+                };
+
+                row = row.push(move_buttons(path, *i, len));
+            }
         }
+
+        row.push(
+            Button::new(Text::new("Duplicate"))
+                .on_press(BEditorMessage::NbtViewDuplicate(path.clone())),
+        )
+        .push(
+            Button::new(Text::new("Remove")).on_press(BEditorMessage::NbtViewRemove(path.clone())),
+        )
     }
 
-    fn nbt2elements(&self, name: String, tag: NbtTag, indent: u32) -> Element<BEditorMessage> {
-        let padding = Padding {
-            top: 0.0,
-            right: 0.0,
-            bottom: 0.0,
-            left: indent as f32 * INDENTATION,
+    /// Renders a scalar as an input, showing the pending text and its error while
+    /// editing. `color` marks search hits on the text around the input. The
+    /// input is disabled in read only trees.
+    fn scalar2element<'a>(
+        &self,
+        row: Row<'a, BEditorMessage>,
+        prefix: &str,
+        suffix: &str,
+        tag: &NbtTag,
+        path: &NbtPath,
+        color: Option<Color>,
+    ) -> Row<'a, BEditorMessage> {
+        let (value, error) = match self.edits.get(path) {
+            Some((text, error)) => (text.clone(), error.clone()),
+            None => (nbt_edit::scalar_to_string(tag).unwrap_or_default(), None),
         };
 
-        match tag {
-            NbtTag::Byte(v) => Column::new()
-                .push(Text::new(format!(
-                    "{name}{}Byte({v})",
-                    if !name.is_empty() { ": " } else { "" }
-                )))
-                .padding(padding)
-                .into(),
-            NbtTag::Int16(v) => Column::new()
-                .push(Text::new(format!(
-                    "{name}{}Int16({v})",
-                    if !name.is_empty() { ": " } else { "" }
-                )))
-                .padding(padding)
-                .into(),
-            NbtTag::Int32(v) => Column::new()
-                .push(Text::new(format!(
-                    "{name}{}Int32({v})",
-                    if !name.is_empty() { ": " } else { "" }
-                )))
-                .padding(padding)
-                .into(),
-            NbtTag::Int64(v) => Column::new()
-                .push(Text::new(format!(
-                    "{name}{}Int64({v})",
-                    if !name.is_empty() { ": " } else { "" }
-                )))
-                .padding(padding)
-                .into(),
-            NbtTag::Float32(v) => Column::new()
-                .push(Text::new(format!(
-                    "{name}{}Float32({v})",
-                    if !name.is_empty() { ": " } else { "" }
-                )))
-                .padding(padding)
-                .into(),
-            NbtTag::Float64(v) => Column::new()
-                .push(Text::new(format!(
-                    "{name}{}Float64({v})",
-                    if !name.is_empty() { ": " } else { "" }
-                )))
-                .padding(padding)
-                .into(),
-            NbtTag::String(v) => Column::new()
-                .push(Text::new(format!(
-                    "{name}{}\"{v}\"",
-                    if !name.is_empty() { ": " } else { "" }
-                )))
-                .padding(padding)
-                .into(),
-            NbtTag::List(v) => {
-                let col = Column::new();
+        let path = path.clone();
 
-                let mut col = col.push(Text::new(format!(
-                    "{name}{}[",
-                    if !name.is_empty() { ": " } else { "" }
-                )));
+        let text = |v: &str| match color {
+            Some(color) => Text::new(v.to_string()).style(color),
+            None => Text::new(v.to_string()),
+        };
+
+        let input = TextInput::new("", &value).width(Length::Fixed(EDIT_WIDTH));
+
+        let input = match self.editable() {
+            true => input
+                .on_input(move |s| BEditorMessage::NbtViewEditValue(path.clone(), s))
+                .on_submit(BEditorMessage::NbtViewSubmitValue),
+            false => input,
+        };
+
+        let mut row = row.push(text(prefix)).push(input).push(text(suffix));
+
+        if let Some(e) = error {
+            row = row.push(Text::new(format!(" {e}")).style(ERROR_COLOR));
+        }
+
+        row
+    }
+
+    fn edit_value(&mut self, path: NbtPath, input: String) {
+        let Ok(NbtDocument { tag, layout, .. }) = &mut self.nbt else {
+            return;
+        };
+
+        let Some(target) = path.get(tag) else {
+            return;
+        };
+
+        let error = match nbt_edit::parse_scalar(target, &input) {
+            Ok(v) => {
+                let command = NbtCommand::SetValue {
+                    path: path.clone(),
+                    old: target.clone(),
+                    new: v,
+                };
+
+                self.history.apply_typed(tag, layout, command).err()
+            }
+            Err(e) => Some(e),
+        };
+
+        self.edits.insert(path, (input, error));
+        self.refresh_bytes();
 
-                for nbt in v.iter() {
-                    col = col.push(self.nbt2elements("".to_string(), nbt.clone(), indent + 1));
+        // Only the highlights follow, rows don't vanish from the filter while typing
+        self.run_search();
+    }
+
+    /// Applies a structural change to the loaded tree, reporting failures in the status line.
+    fn execute(&mut self, command: impl FnOnce(&NbtTag) -> Result<NbtCommand, String>) {
+        let Ok(NbtDocument { tag, layout, .. }) = &mut self.nbt else {
+            return;
+        };
+
+        // Paths of pending inputs may point to other tags after the change
+        self.edits.clear();
+        self.renaming = None;
+
+        let result = command(tag).and_then(|v| {
+            // Start renaming new compound entries right away
+            if let NbtCommand::Insert { path, .. } = &v {
+                if let Some((_, NbtPathSegment::Key(k))) = path.split_last() {
+                    self.renaming = Some((path.clone(), k.clone()));
                 }
+            }
 
-                col = col.push(Text::new(String::from("]")));
+            self.history.apply(tag, layout, v)
+        });
 
-                col.padding(padding).into()
+        match result {
+            Ok(_) => self.status = None,
+            Err(e) => {
+                self.renaming = None;
+                self.status = Some(Err(e));
             }
-            NbtTag::Compound(v) => {
-                let mut col = Column::new();
+        }
+
+        self.refresh_bytes();
+        self.rebuild_rows();
+    }
 
-                col = col.push(Text::new(format!(
-                    "{name}{}{{",
-                    if !name.is_empty() { ": " } else { "" }
+    fn undo(&mut self, redo: bool) {
+        let Ok(NbtDocument { tag, layout, .. }) = &mut self.nbt else {
+            return;
+        };
+
+        self.edits.clear();
+        self.renaming = None;
+
+        let result = match redo {
+            false => self.history.undo(tag, layout),
+            true => self.history.redo(tag, layout),
+        };
+
+        self.status = result.err().map(Err);
+        self.refresh_bytes();
+        self.rebuild_rows();
+    }
+
+    /// Reads the file again after the endian or header changed. Edits are
+    /// replayed on the new tree, if that fails the edited tree is kept and
+    /// will be written in the new format.
+    fn reformat(&mut self) {
+        if !self.history.is_dirty() {
+            self.reload();
+            return;
+        }
+
+        let replayed = self
+            .parse_nbt()
+            .map_err(|e| e.to_string())
+            .and_then(|mut v| {
+                self.history.replay(&mut v.tag, &mut v.layout)?;
+                Ok(v)
+            });
+
+        match replayed {
+            Ok(v) => {
+                self.nbt = Ok(v);
+                self.failure = None;
+                self.status = None;
+            }
+            Err(_) => {
+                if let Ok(document) = &mut self.nbt {
+                    document.endian = self.endian;
+                    document.header = self.header;
+                }
+
+                self.status = Some(Ok(format!(
+                    "Kept the edited Nbt, it will be saved as {} with {}",
+                    self.endian, self.header
                 )));
+            }
+        }
+
+        self.edits.clear();
+        self.renaming = None;
+        self.load_bytes();
+        self.rebuild_rows();
+    }
 
-                for (str, nbt) in v.iter() {
-                    col = col.push(self.nbt2elements(str.clone(), nbt.clone(), indent + 1));
+    /// Expands every compound and list less than `depth` levels below the root.
+    fn expand_to_depth(&mut self, depth: Option<usize>) {
+        self.expanded.clear();
+
+        if let Some((_, tag)) = self.tree() {
+            let mut expanded = HashSet::new();
+            collect_containers(tag, NbtPath::root(), depth, &mut expanded);
+            self.expanded = expanded;
+        }
+
+        self.rebuild_rows();
+    }
+
+    /// Flattens the tree again after its structure or the expanded tags
+    /// changed, searching it again first.
+    fn rebuild_rows(&mut self) {
+        self.run_search();
+
+        let rows = match self.tree() {
+            Some((name, tag)) => {
+                nbt_rows::flatten(name, tag, self.layout(), &self.expanded, &|v| {
+                    self.keep_row(v)
+                })
+            }
+            None => Vec::new(),
+        };
+
+        self.rows = rows;
+    }
+
+    /// Searches the shown tree for the text in the search bar.
+    fn run_search(&mut self) {
+        self.search_error = None;
+
+        let hits = match (self.search.is_empty(), self.tree()) {
+            (false, Some((name, tag))) => {
+                match SearchQuery::new(&self.search, self.search_mode, self.search_kind) {
+                    Ok(query) => nbt_search::search(name, tag, self.layout(), &query),
+                    Err(e) => {
+                        self.search_error = Some(e);
+                        Vec::new()
+                    }
                 }
+            }
+            _ => Vec::new(),
+        };
+
+        self.search_index = hits
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.path.clone(), i))
+            .collect();
+        self.search_ancestors.clear();
 
-                col = col.push(Text::new(format!("}}")));
+        for hit in hits.iter() {
+            let mut parent = hit.path.split_last().map(|(v, _)| v);
 
-                col.padding(padding).into()
+            while let Some(v) = parent {
+                parent = v.split_last().map(|(v, _)| v);
+                self.search_ancestors.insert(v);
             }
-            NbtTag::Empty => Column::new()
-                .push(Text::new(format!("{name}: EMPTY")))
-                .padding(padding)
-                .into(),
         }
+
+        self.search_current = self.search_current.filter(|v| *v < hits.len());
+        self.search_hits = hits;
+    }
+
+    /// While filtering, whether the tag is a hit, inside one or above one.
+    fn keep_row(&self, path: &NbtPath) -> bool {
+        if !self.search_filter || self.search.is_empty() || self.search_error.is_some() {
+            return true;
+        }
+
+        let mut parent = Some(path.clone());
+
+        while let Some(v) = parent {
+            if self.search_index.contains_key(&v) {
+                return true;
+            }
+
+            parent = v.split_last().map(|(v, _)| v);
+        }
+
+        self.search_ancestors.contains(path)
+    }
+
+    /// Applies a change of the search and shows the new hits.
+    fn update_search(&mut self) {
+        self.search_current = None;
+        self.run_search();
+
+        // Expand the way to every hit so the filtered tree shows them
+        if self.search_filter {
+            self.expanded.extend(self.search_ancestors.iter().cloned());
+        }
+
+        self.rebuild_rows();
+    }
+
+    /// Moves to the next or previous hit, showing it in the tree.
+    fn jump(&mut self, forward: bool) -> Command<BEditorMessage> {
+        let len = self.search_hits.len();
+
+        if len == 0 {
+            return Command::none();
+        }
+
+        let next = match (self.search_current, forward) {
+            (None, true) => 0,
+            (None, false) => len - 1,
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+        };
+
+        self.search_current = Some(next);
+
+        let path = self.search_hits[next].path.clone();
+        self.reveal(&path)
+    }
+
+    /// Colors of the key and value of the tag at `path` if it's a search hit.
+    fn highlight(&self, path: &NbtPath) -> (Option<Color>, Option<Color>) {
+        let Some(i) = self.search_index.get(path) else {
+            return (None, None);
+        };
+
+        let color = match self.search_current == Some(*i) {
+            true => CURRENT_HIT_COLOR,
+            false => HIT_COLOR,
+        };
+
+        let hit = &self.search_hits[*i];
+
+        (hit.key.then_some(color), hit.value.then_some(color))
+    }
+
+    /// Guesses the endian and header of the file, selecting them if it succeeds.
+    fn detect_format(&mut self) {
+        let Ok(data) = self.read_data() else {
+            self.detection = None;
+            return;
+        };
+
+        let file_name = Path::new(&self.path).file_name().and_then(|v| v.to_str());
+
+        self.detection = detect::detect(&data, file_name);
+
+        if let Some(v) = self.detection {
+            self.endian = v.endian;
+            self.header = v.header;
+        }
+    }
+
+    /// Opens the file at the current path, guessing its format.
+    fn open(&mut self) {
+        self.detect_format();
+        self.reload();
+    }
+
+    /// Opens the file at `path`, guessing its format.
+    pub fn open_path(&mut self, path: &Path) {
+        self.path = path.to_string_lossy().to_string();
+        self.open();
+    }
+
+    /// Shows `data` read from a world record called `name`. Records are
+    /// always Little Endian without a header.
+    pub fn open_record(&mut self, name: String, data: Vec<u8>) {
+        self.path = name;
+        self.data = Some(data);
+        self.endian = NbtEndian::Little;
+        self.header = NbtHeader::None;
+        self.detection = None;
+        self.reload();
+    }
+
+    /// The document as it would be saved.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        match &self.nbt {
+            Ok(v) => v.to_bytes().map_err(|e| e.to_string()),
+            Err(_) => Err(String::from("No Nbt loaded")),
+        }
+    }
+
+    /// Marks the record as saved after the world view wrote `data` for it.
+    pub fn saved_record(&mut self, data: Vec<u8>) {
+        self.data = Some(data);
+        self.history.mark_saved();
+        self.load_bytes();
+    }
+
+    pub fn set_status(&mut self, status: Result<String, String>) {
+        self.status = Some(status);
+    }
+
+    /// Path of the file that is shown, if it could be read.
+    pub fn loaded_path(&self) -> Option<PathBuf> {
+        match (&self.nbt, &self.data) {
+            (Ok(_), None) => Some(PathBuf::from(&self.path)),
+            _ => None,
+        }
+    }
+
+    /// Reads the file for the hex view and finds the bytes of every tag. An
+    /// edited tree is encoded the way it would be saved instead.
+    fn load_bytes(&mut self) {
+        self.hex_error = None;
+
+        let encoded = match (&self.nbt, self.history.is_dirty()) {
+            (Ok(document), true) => Some(document.to_bytes().map_err(|e| e.to_string())),
+            _ => None,
+        };
+
+        self.bytes = match encoded {
+            Some(Ok(v)) => v,
+            Some(Err(e)) => {
+                self.hex_error = Some(e);
+                self.read_data().unwrap_or_default()
+            }
+            None => self.read_data().unwrap_or_default(),
+        };
+
+        self.spans = match self.hex_error {
+            Some(_) => Vec::new(),
+            None => {
+                nbt_decode::decode_spans(&self.bytes, self.endian, self.header).unwrap_or_default()
+            }
+        };
     }
+
+    /// Encodes the edited tree again if the hex view shows it.
+    fn refresh_bytes(&mut self) {
+        if self.show_hex {
+            self.load_bytes();
+        }
+    }
+
+    fn span_of(&self, path: &NbtPath) -> Option<&NbtSpan> {
+        self.spans.iter().find(|(p, _)| p == path).map(|(_, v)| v)
+    }
+
+    /// Highlights the bytes of the tag at `path` and scrolls them into view.
+    fn select(&mut self, path: NbtPath) -> Command<BEditorMessage> {
+        let line = self.span_of(&path).map(|v| v.start() / HEX_LINE);
+        self.selected = Some(path);
+
+        match line {
+            Some(line) => scrollable::scroll_to(
+                hex_id(),
+                AbsoluteOffset {
+                    x: 0.0,
+                    y: (line as f32 * HEX_ROW_HEIGHT - self.hex_viewport_height / 2.0).max(0.0),
+                },
+            ),
+            None => Command::none(),
+        }
+    }
+
+    /// Selects the tag the byte at `offset` belongs to, expanding and
+    /// scrolling the tree to show it.
+    fn select_byte(&mut self, offset: usize) -> Command<BEditorMessage> {
+        let Some(path) = nbt_decode::tag_at(&self.spans, offset).cloned() else {
+            return Command::none();
+        };
+
+        self.selected = Some(path.clone());
+        self.reveal(&path)
+    }
+
+    /// Expands the ancestors of the tag at `path` and scrolls the tree to it.
+    fn reveal(&mut self, path: &NbtPath) -> Command<BEditorMessage> {
+        let mut parent = path.split_last().map(|(v, _)| v);
+
+        while let Some(v) = parent {
+            parent = v.split_last().map(|(v, _)| v);
+            self.expanded.insert(v);
+        }
+
+        self.rebuild_rows();
+
+        let index = self
+            .rows
+            .iter()
+            .position(|v| v.path == *path && v.kind == NbtRowKind::Tag);
+
+        match index {
+            Some(i) => scrollable::scroll_to(
+                tree_id(),
+                AbsoluteOffset {
+                    x: 0.0,
+                    y: (i as f32 * ROW_HEIGHT - self.viewport_height / 2.0).max(0.0),
+                },
+            ),
+            None => Command::none(),
+        }
+    }
+
+    /// Renders the lines of the hex view inside the scrolled viewport,
+    /// coloring the type, name and value bytes of the selected tag.
+    fn hex2element(&self) -> Element<'_, BEditorMessage> {
+        let lines = self.bytes.len().div_ceil(HEX_LINE);
+        let first = ((self.hex_scroll_offset / HEX_ROW_HEIGHT).floor() as usize).min(lines);
+        let count = (self.hex_viewport_height / HEX_ROW_HEIGHT).ceil() as usize + 1;
+        let last = (first + count).min(lines);
+
+        let span = self.selected.as_ref().and_then(|v| self.span_of(v));
+        let clickable = !self.spans.is_empty();
+
+        let mut col = Column::new().push(Space::with_height(Length::Fixed(
+            first as f32 * HEX_ROW_HEIGHT,
+        )));
+
+        for line in first..last {
+            let start = line * HEX_LINE;
+            let bytes = &self.bytes[start..(start + HEX_LINE).min(self.bytes.len())];
+
+            let mut row =
+                Row::new().push(Text::new(format!("{start:08x}  ")).font(Font::MONOSPACE));
+
+            for (i, byte) in bytes.iter().enumerate() {
+                let offset = start + i;
+
+                let color = span.and_then(|v| match v {
+                    _ if v.tag_type == Some(offset) => Some(HEX_TYPE_COLOR),
+                    NbtSpan {
+                        name: Some(name), ..
+                    } if name.contains(&offset) => Some(HEX_NAME_COLOR),
+                    _ if v.payload.contains(&offset) => Some(HEX_PAYLOAD_COLOR),
+                    _ => None,
+                });
+
+                let text = Text::new(format!("{byte:02x} ")).font(Font::MONOSPACE);
+                let text = match color {
+                    Some(v) => text.style(v),
+                    None => text,
+                };
+
+                let button = Button::new(text).style(theme::Button::Text).padding(0);
+
+                row = row.push(match clickable {
+                    true => button.on_press(BEditorMessage::NbtViewSelectByte(offset)),
+                    false => button,
+                });
+            }
+
+            let ascii: String = bytes
+                .iter()
+                .map(|v| match v.is_ascii_graphic() || *v == b' ' {
+                    true => *v as char,
+                    false => '.',
+                })
+                .collect();
+
+            col = col.push(
+                row.push(Text::new(format!(" {ascii}")).font(Font::MONOSPACE))
+                    .height(Length::Fixed(HEX_ROW_HEIGHT))
+                    .align_items(Alignment::Center),
+            );
+        }
+
+        col.push(Space::with_height(Length::Fixed(
+            (lines - last) as f32 * HEX_ROW_HEIGHT,
+        )))
+        .into()
+    }
+
+    /// Reads the file from disk, dropping all edits.
+    fn reload(&mut self) {
+        self.set_parsed(self.parse_nbt());
+        self.load_bytes();
+        self.history = History::new();
+        self.expand_to_depth(Some(1));
+        self.scroll_offset = 0.0;
+        self.edits.clear();
+        self.renaming = None;
+        self.status = None;
+    }
+}
+
+pub async fn pick_file() -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .add_filter("Nbt", &["nbt", "dat", "dat_old", "mcstructure"])
+        .add_filter("All Files", &["*"])
+        .pick_file()
+        .await
+        .map(|v| v.path().to_path_buf())
+}
+
+pub async fn pick_json_file() -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .add_filter("JSON", &["json"])
+        .pick_file()
+        .await
+        .map(|v| v.path().to_path_buf())
+}
+
+pub async fn pick_json_save(file_name: String) -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .add_filter("JSON", &["json"])
+        .set_file_name(file_name)
+        .save_file()
+        .await
+        .map(|v| v.path().to_path_buf())
+}
+
+/// Buttons moving the tag at `path` from position `i` one step up or down
+/// among its `len` siblings.
+fn move_buttons<'a>(path: &NbtPath, i: usize, len: usize) -> Row<'a, BEditorMessage> {
+    let up = Button::new(Text::new("Up"));
+    let up = match i.checked_sub(1) {
+        Some(to) => up.on_press(BEditorMessage::NbtViewMove(path.clone(), to)),
+        None => up,
+    };
+
+    let down = Button::new(Text::new("Down"));
+    let down = match i + 1 < len {
+        true => down.on_press(BEditorMessage::NbtViewMove(path.clone(), i + 1)),
+        false => down,
+    };
+
+    Row::new().push(up).push(down)
+}
+
+fn tree_id() -> scrollable::Id {
+    scrollable::Id::new("nbt-tree")
+}
+
+fn hex_id() -> scrollable::Id {
+    scrollable::Id::new("nbt-hex")
+}
+
+/// Renders the message of a decode error above a hex dump of the bytes around it.
+fn failure2element<'a>(failure: &DecodeError) -> Element<'a, BEditorMessage> {
+    let mut col = Column::new()
+        .push(Text::new(format!("Error parsing Nbt: {failure}")).style(ERROR_COLOR))
+        .push(Text::new(match failure.partial {
+            Some(_) => "The tags read before the error are shown below, read only:",
+            None => "No tags could be read before the error.",
+        }));
+
+    let start = failure.context_start - failure.context_start % HEX_LINE;
+    let end = failure.context_start + failure.context.len();
+
+    for line in (start..end.max(start + 1)).step_by(HEX_LINE) {
+        let mut row = Row::new().push(Text::new(format!("{line:08x}  ")).font(Font::MONOSPACE));
+        let mut ascii = String::new();
+
+        for offset in line..line + HEX_LINE {
+            let byte = offset
+                .checked_sub(failure.context_start)
+                .and_then(|i| failure.context.get(i));
+
+            let text = match byte {
+                Some(v) => Text::new(format!("{v:02x} ")),
+                None => Text::new("   "),
+            }
+            .font(Font::MONOSPACE);
+
+            row = match offset == failure.offset {
+                true => row.push(text.style(ERROR_COLOR)),
+                false => row.push(text),
+            };
+
+            ascii.push(match byte {
+                Some(v) if v.is_ascii_graphic() || *v == b' ' => *v as char,
+                Some(_) => '.',
+                None => ' ',
+            });
+        }
+
+        col = col.push(row.push(Text::new(format!(" |{ascii}|")).font(Font::MONOSPACE)));
+    }
+
+    col.into()
+}
+
+fn collect_containers(
+    tag: &NbtTag,
+    path: NbtPath,
+    depth: Option<usize>,
+    out: &mut HashSet<NbtPath>,
+) {
+    if depth.is_some_and(|v| path.depth() >= v) {
+        return;
+    }
+
+    match tag {
+        NbtTag::List(v) => {
+            for (i, nbt) in v.iter().enumerate() {
+                collect_containers(nbt, path.index(i), depth, out);
+            }
+        }
+        NbtTag::Compound(v) => {
+            for (str, nbt) in v.iter() {
+                collect_containers(nbt, path.key(str.clone()), depth, out);
+            }
+        }
+        _ => return,
+    }
+
+    out.insert(path);
 }
 
 impl BEditorView for NbtView {
     fn new() -> Self {
         Self {
             path: String::new(),
+            data: None,
             nbt: Err(String::from("")),
             endian: Default::default(),
             header: NbtHeader::None,
+            edits: HashMap::new(),
+            save_path: String::new(),
+            status: None,
+            insert_kind: NbtTagKind::default(),
+            renaming: None,
+            history: History::new(),
+            expanded: HashSet::new(),
+            rows: Vec::new(),
+            scroll_offset: 0.0,
+            viewport_height: DEFAULT_VIEWPORT_HEIGHT,
+            detection: None,
+            failure: None,
+            bytes: Vec::new(),
+            spans: Vec::new(),
+            hex_error: None,
+            show_hex: false,
+            selected: None,
+            hex_scroll_offset: 0.0,
+            hex_viewport_height: DEFAULT_VIEWPORT_HEIGHT,
+            search: String::new(),
+            search_mode: SearchMode::Text,
+            search_kind: KindFilter::Any,
+            search_hits: Vec::new(),
+            search_index: HashMap::new(),
+            search_ancestors: HashSet::new(),
+            search_error: None,
+            search_current: None,
+            search_filter: false,
         }
     }
 
-    fn update(&mut self, message: BEditorMessage) {
+    fn update(&mut self, message: BEditorMessage) -> Command<BEditorMessage> {
         match message {
             BEditorMessage::NbtViewSetPath(v) => self.path = v,
-            BEditorMessage::NbtViewSetEndian(v) => self.endian = v,
-            BEditorMessage::NbtViewSetHeader(v) => self.header = v,
-            BEditorMessage::NbtViewRefresh => {}
+            BEditorMessage::NbtViewOpen => self.open(),
+            BEditorMessage::NbtViewPickFile => {
+                return Command::perform(pick_file(), BEditorMessage::NbtViewFilePicked);
+            }
+            BEditorMessage::NbtViewFilePicked(Some(v)) => self.open_path(&v),
+            BEditorMessage::NbtViewFilePicked(None) => {}
+            BEditorMessage::NbtViewSetEndian(v) => {
+                self.endian = v;
+                self.detection = None;
+                self.reformat();
+            }
+            BEditorMessage::NbtViewSetHeader(v) => {
+                self.header = v;
+                self.detection = None;
+                self.reformat();
+            }
+            BEditorMessage::NbtViewDetect => {
+                self.detect_format();
+                self.reformat();
+            }
+            BEditorMessage::NbtViewRefresh => self.reload(),
+            BEditorMessage::NbtViewEditValue(path, v) => self.edit_value(path, v),
+            BEditorMessage::NbtViewSubmitValue => self.history.end_typing(),
+            BEditorMessage::NbtViewSave => {
+                self.status = Some(match self.data {
+                    Some(_) => Err(String::from("Records are saved by the world view")),
+                    None => self.save_nbt(self.path.clone()),
+                });
+            }
+            BEditorMessage::NbtViewSetSavePath(v) => self.save_path = v,
+            BEditorMessage::NbtViewSaveAs => {
+                self.status = Some(self.save_nbt(self.save_path.clone()));
+            }
+            BEditorMessage::NbtViewSetInsertKind(v) => self.insert_kind = v,
+            BEditorMessage::NbtViewInsert(parent) => {
+                let kind = self.insert_kind;
+                self.execute(|tag| {
+                    Ok(NbtCommand::Insert {
+                        path: nbt_edit::free_child_path(tag, &parent, "new_tag")?,
+                        tag: kind.new_tag(),
+                    })
+                });
+            }
+            BEditorMessage::NbtViewRemove(path) => self.execute(|tag| match path.get(tag) {
+                Some(v) => Ok(NbtCommand::Remove {
+                    path: path.clone(),
+                    tag: v.clone(),
+                }),
+                None => Err(format!("No tag at {path}")),
+            }),
+            BEditorMessage::NbtViewDuplicate(path) => self.execute(|tag| {
+                let Some(copy) = path.get(tag).cloned() else {
+                    return Err(format!("No tag at {path}"));
+                };
+
+                let new = match path.split_last() {
+                    Some((parent, NbtPathSegment::Key(k))) => {
+                        nbt_edit::free_child_path(tag, &parent, &format!("{k}_copy"))?
+                    }
+                    Some((parent, NbtPathSegment::Index(i))) => parent.index(i + 1),
+                    None => return Err(String::from("The root tag can't be duplicated")),
+                };
+
+                Ok(NbtCommand::Insert {
+                    path: new,
+                    tag: copy,
+                })
+            }),
+            BEditorMessage::NbtViewMove(path, index) => {
+                let command = match self.key_position(&path) {
+                    Some((from, _)) => NbtCommand::MoveKey {
+                        path,
+                        from,
+                        to: index,
+                    },
+                    None => NbtCommand::Move { path, index },
+                };
+
+                self.execute(|_| Ok(command))
+            }
+            BEditorMessage::NbtViewStartRename(path) => {
+                if let Some((_, NbtPathSegment::Key(k))) = path.split_last() {
+                    self.renaming = Some((path.clone(), k.clone()));
+                }
+            }
+            BEditorMessage::NbtViewRenameInput(v) => {
+                if let Some((_, name)) = &mut self.renaming {
+                    *name = v;
+                }
+            }
+            BEditorMessage::NbtViewRenameSubmit => {
+                if let Some((path, key)) = self.renaming.take() {
+                    self.execute(|_| Ok(NbtCommand::Rename { path, key }));
+                }
+            }
+            BEditorMessage::NbtViewToggleExpand(path) => {
+                if !self.expanded.remove(&path) {
+                    self.expanded.insert(path);
+                }
+                self.rebuild_rows();
+            }
+            BEditorMessage::NbtViewExpandAll => self.expand_to_depth(None),
+            BEditorMessage::NbtViewCollapseAll => {
+                self.expanded.clear();
+                self.rebuild_rows();
+            }
+            BEditorMessage::NbtViewExpandToDepth(v) => self.expand_to_depth(Some(v)),
+            BEditorMessage::NbtViewScrolled(offset, height) => {
+                self.scroll_offset = offset;
+                self.viewport_height = height;
+            }
+            BEditorMessage::NbtViewCopySnbt(path) => {
+                if let Some(tag) = self.tree().and_then(|(_, v)| path.get(v)) {
+                    match snbt::to_snbt(tag, true) {
+                        Ok(v) => return iced::clipboard::write(v),
+                        Err(e) => self.status = Some(Err(format!("Error copying {path}: {e}"))),
+                    }
+                }
+            }
+            BEditorMessage::NbtViewCopyPath(path) => {
+                return iced::clipboard::write(path.to_string());
+            }
+            BEditorMessage::NbtViewPaste(path, into) => {
+                return iced::clipboard::read(move |v| {
+                    BEditorMessage::NbtViewPasted(path.clone(), into, v)
+                });
+            }
+            BEditorMessage::NbtViewPasted(path, into, text) => {
+                self.execute(|tag| {
+                    let Some(text) = text else {
+                        return Err(String::from("The clipboard holds no text"));
+                    };
+
+                    let new = match snbt::parse_snbt(&text) {
+                        Ok(v) => v,
+                        Err(e) => return Err(format!("Invalid SNBT at {e}")),
+                    };
+
+                    if into {
+                        return Ok(NbtCommand::Insert {
+                            path: nbt_edit::free_child_path(tag, &path, "pasted")?,
+                            tag: new,
+                        });
+                    }
+
+                    let Some(old) = path.get(tag) else {
+                        return Err(format!("No tag at {path}"));
+                    };
+
+                    nbt_edit::check_replace(tag, &path, &new)?;
+
+                    Ok(NbtCommand::SetValue {
+                        path: path.clone(),
+                        old: old.clone(),
+                        new,
+                    })
+                });
+            }
+            BEditorMessage::NbtViewExportJson => {
+                return Command::perform(
+                    pick_json_save(format!("{}.json", self.title())),
+                    BEditorMessage::NbtViewExportJsonTo,
+                );
+            }
+            BEditorMessage::NbtViewExportJsonTo(Some(v)) => {
+                self.status = Some(self.export_json(&v));
+            }
+            BEditorMessage::NbtViewExportJsonTo(None) => {}
+            BEditorMessage::NbtViewImportJson => {
+                return Command::perform(pick_json_file(), BEditorMessage::NbtViewImportJsonFrom);
+            }
+            BEditorMessage::NbtViewImportJsonFrom(Some(v)) => {
+                self.status = Some(self.import_json(&v));
+            }
+            BEditorMessage::NbtViewImportJsonFrom(None) => {}
+            BEditorMessage::NbtViewToggleHex => {
+                self.show_hex = !self.show_hex;
+                self.refresh_bytes();
+            }
+            BEditorMessage::NbtViewSelect(path) => return self.select(path),
+            BEditorMessage::NbtViewSelectByte(offset) => return self.select_byte(offset),
+            BEditorMessage::NbtViewHexScrolled(offset, height) => {
+                self.hex_scroll_offset = offset;
+                self.hex_viewport_height = height;
+            }
+            BEditorMessage::NbtViewSearch(v) => {
+                self.search = v;
+                self.update_search();
+            }
+            BEditorMessage::NbtViewSearchMode(v) => {
+                self.search_mode = v;
+                self.update_search();
+            }
+            BEditorMessage::NbtViewSearchKind(v) => {
+                self.search_kind = v;
+                self.update_search();
+            }
+            BEditorMessage::NbtViewSearchJump(forward) => return self.jump(forward),
+            BEditorMessage::NbtViewSearchFilter(v) => {
+                self.search_filter = v;
+                self.update_search();
+            }
+            BEditorMessage::Undo => self.undo(false),
+            BEditorMessage::Redo => self.undo(true),
+            // Handled by the app or other views
+            _ => {}
         }
 
-        self.nbt = self.parse_nbt();
+        Command::none()
+    }
+
+    fn title(&self) -> String {
+        match Path::new(&self.path).file_name() {
+            Some(v) => v.to_string_lossy().to_string(),
+            None => String::from("Nbt"),
+        }
     }
 
-    fn view(&self) -> Element<BEditorMessage> {
+    fn is_dirty(&self) -> bool {
+        self.nbt.is_ok() && self.history.is_dirty()
+    }
+
+    fn view(&self) -> Element<'_, BEditorMessage> {
         let padding = Padding {
             top: 0.0,
             right: 0.0,
@@ -251,68 +1371,238 @@ impl BEditorView for NbtView {
             left: INDENTATION,
         };
 
+        // Records of a world are picked in the world view, not opened by path
+        let mut source = Row::new();
+
+        if self.data.is_none() {
+            source = source
+                .push(
+                    TextInput::new("Your Path", &self.path)
+                        .on_input(BEditorMessage::NbtViewSetPath)
+                        .on_submit(BEditorMessage::NbtViewOpen),
+                )
+                .push(
+                    iced::widget::Button::new(Text::new("Open"))
+                        .on_press(BEditorMessage::NbtViewOpen),
+                )
+                .push(
+                    iced::widget::Button::new(Text::new("Browse..."))
+                        .on_press(BEditorMessage::NbtViewPickFile),
+                )
+                .width(Length::Fill);
+        }
+
         Column::new()
             .push(
                 Row::new()
-                    .push(
-                        TextInput::new("Your Path", &self.path)
-                            .on_input(BEditorMessage::NbtViewSetPath),
-                    )
+                    .push(source)
                     .push(iced::widget::PickList::new(
                         &NbtEndian::ALL[..],
                         Some(self.endian),
-                        |s| BEditorMessage::NbtViewSetEndian(s),
+                        BEditorMessage::NbtViewSetEndian,
                     ))
                     .push(iced::widget::PickList::new(
                         &NbtHeader::ALL[..],
                         Some(self.header),
-                        |s| BEditorMessage::NbtViewSetHeader(s),
+                        BEditorMessage::NbtViewSetHeader,
                     ))
+                    .push(
+                        iced::widget::Button::new(Text::new("Detect"))
+                            .on_press(BEditorMessage::NbtViewDetect),
+                    )
                     .push(
                         iced::widget::Button::new(Text::new("Refresh"))
                             .on_press(BEditorMessage::NbtViewRefresh),
                     ),
             )
+            .push(match self.detection {
+                Some(v) => Text::new(format!(
+                    "Detected {} with {} ({:.0}% confidence)",
+                    v.endian,
+                    v.header,
+                    v.confidence * 100.0
+                )),
+                None => Text::new(""),
+            })
             .push(
-                Scrollable::new(match &self.nbt {
-                    Ok(v) => {
-                        let col = Column::new();
-
-                        let col = match v.clone().2 {
-                            None => col,
-                            Some(v) => match self.header {
-                                NbtHeader::None => col,
-                                NbtHeader::Normal => {
-                                    let col = col.push(Text::new(String::from("Header: {")));
-
-                                    let col2 = Column::new();
-                                    let col2 = col2.push(Text::new(format!("First: {}", v.0)));
-                                    let col2 = col2.push(Text::new(format!("Length: {}", v.1)));
-
-                                    let col = col.push(col2.padding(padding));
-
-                                    col.push(Text::new(String::from("}")))
-                                }
-                                NbtHeader::LevelDat => {
-                                    let col = col.push(Text::new(String::from("Header: {")));
-
-                                    let col2 = Column::new();
-                                    let col2 =
-                                        col2.push(Text::new(format!("Format Version: {}", v.0)));
-                                    let col2 = col2.push(Text::new(format!("Length: {}", v.1)));
-
-                                    let col = col.push(col2.padding(padding));
-
-                                    col.push(Text::new(String::from("}")))
+                Row::new()
+                    .push(
+                        iced::widget::Button::new(Text::new("Save"))
+                            .on_press(BEditorMessage::NbtViewSave),
+                    )
+                    .push(
+                        TextInput::new("Save As Path", &self.save_path)
+                            .on_input(BEditorMessage::NbtViewSetSavePath)
+                            .on_submit(BEditorMessage::NbtViewSaveAs),
+                    )
+                    .push(
+                        iced::widget::Button::new(Text::new("Save As"))
+                            .on_press(BEditorMessage::NbtViewSaveAs),
+                    )
+                    .push(
+                        Button::new(Text::new("Export JSON"))
+                            .on_press(BEditorMessage::NbtViewExportJson),
+                    )
+                    .push(
+                        Button::new(Text::new("Import JSON"))
+                            .on_press(BEditorMessage::NbtViewImportJson),
+                    )
+                    .push(
+                        Button::new(Text::new(match self.show_hex {
+                            true => "Hide Hex",
+                            false => "Show Hex",
+                        }))
+                        .on_press(BEditorMessage::NbtViewToggleHex),
+                    )
+                    .push(Button::new(Text::new("Undo")).on_press(BEditorMessage::Undo))
+                    .push(Button::new(Text::new("Redo")).on_press(BEditorMessage::Redo))
+                    .push(
+                        Button::new(Text::new("Expand All"))
+                            .on_press(BEditorMessage::NbtViewExpandAll),
+                    )
+                    .push(
+                        Button::new(Text::new("Collapse All"))
+                            .on_press(BEditorMessage::NbtViewCollapseAll),
+                    )
+                    .push(
+                        iced::widget::PickList::new(&EXPAND_DEPTHS[..], None::<usize>, |s| {
+                            BEditorMessage::NbtViewExpandToDepth(s)
+                        })
+                        .placeholder("Expand to Depth"),
+                    )
+                    .push(Text::new("New Tag:"))
+                    .push(iced::widget::PickList::new(
+                        &NbtTagKind::ALL[..],
+                        Some(self.insert_kind),
+                        BEditorMessage::NbtViewSetInsertKind,
+                    )),
+            )
+            .push(
+                Row::new()
+                    .push(
+                        TextInput::new("Search keys and values", &self.search)
+                            .on_input(BEditorMessage::NbtViewSearch)
+                            .on_submit(BEditorMessage::NbtViewSearchJump(true)),
+                    )
+                    .push(iced::widget::PickList::new(
+                        &SearchMode::ALL[..],
+                        Some(self.search_mode),
+                        BEditorMessage::NbtViewSearchMode,
+                    ))
+                    .push(iced::widget::PickList::new(
+                        &KindFilter::ALL[..],
+                        Some(self.search_kind),
+                        BEditorMessage::NbtViewSearchKind,
+                    ))
+                    .push(
+                        Button::new(Text::new("Previous"))
+                            .on_press(BEditorMessage::NbtViewSearchJump(false)),
+                    )
+                    .push(
+                        Button::new(Text::new("Next"))
+                            .on_press(BEditorMessage::NbtViewSearchJump(true)),
+                    )
+                    .push(
+                        Checkbox::new("Only Matches", self.search_filter)
+                            .on_toggle(BEditorMessage::NbtViewSearchFilter),
+                    )
+                    .push(match (&self.search_error, self.search_current) {
+                        (Some(e), _) => Text::new(e.clone()).style(ERROR_COLOR),
+                        _ if self.search.is_empty() => Text::new(""),
+                        _ if self.search_hits.is_empty() => Text::new("No matches"),
+                        (None, Some(i)) => {
+                            Text::new(format!("{} of {}", i + 1, self.search_hits.len()))
+                        }
+                        (None, None) => Text::new(format!("{} matches", self.search_hits.len())),
+                    })
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+            )
+            .push(match &self.status {
+                None => Text::new(""),
+                Some(Ok(v)) => Text::new(v.clone()),
+                Some(Err(e)) => Text::new(e.clone()).style(ERROR_COLOR),
+            })
+            .push(match &self.nbt {
+                Ok(NbtDocument {
+                    header_values: Some(v),
+                    ..
+                }) => match self.header {
+                    NbtHeader::None => Column::new(),
+                    NbtHeader::Normal => Column::new()
+                        .push(Text::new(String::from("Header: {")))
+                        .push(
+                            Column::new()
+                                .push(Text::new(format!("First: {}", v.0)))
+                                .push(Text::new(format!("Length: {}", v.1)))
+                                .padding(padding),
+                        )
+                        .push(Text::new(String::from("}"))),
+                    NbtHeader::LevelDat => Column::new()
+                        .push(Text::new(String::from("Header: {")))
+                        .push(
+                            Column::new()
+                                .push(Text::new(format!("Format Version: {}", v.0)))
+                                .push(Text::new(format!("Length: {}", v.1)))
+                                .padding(padding),
+                        )
+                        .push(Text::new(String::from("}"))),
+                },
+                _ => Column::new(),
+            })
+            .push(match &self.failure {
+                Some(v) => failure2element(v),
+                None => Column::new().into(),
+            })
+            .push(
+                Row::new()
+                    .push(
+                        Scrollable::new(match (&self.nbt, self.tree()) {
+                            (_, Some((_, tag))) => self.rows2elements(tag),
+                            (Ok(_), None) => Column::new().into(),
+                            (Err(e), None) => Text::new(e.clone()).into(),
+                        })
+                        .id(tree_id())
+                        .on_scroll(|v| {
+                            BEditorMessage::NbtViewScrolled(
+                                v.absolute_offset().y,
+                                v.bounds().height,
+                            )
+                        })
+                        .width(Length::Fill)
+                        .height(Length::Fill),
+                    )
+                    .push(match self.show_hex {
+                        true => Column::new()
+                            .push(
+                                Row::new()
+                                    .push(Text::new("Type ").style(HEX_TYPE_COLOR))
+                                    .push(Text::new("Name ").style(HEX_NAME_COLOR))
+                                    .push(Text::new("Value").style(HEX_PAYLOAD_COLOR)),
+                            )
+                            .push(match (&self.hex_error, self.history.is_dirty()) {
+                                (Some(e), _) => Text::new(format!(
+                                    "Showing the saved bytes, the edited Nbt can't be encoded: {e}"
+                                ))
+                                .style(ERROR_COLOR),
+                                (None, true) => {
+                                    Text::new("Unsaved, as the edited Nbt would be written")
                                 }
-                            },
-                        };
-
-                        col.push(self.nbt2elements(v.clone().0, v.clone().1, 1))
-                    }
-                    Err(e) => Column::new().push(Text::new(format!("{e}"))),
-                })
-                .width(Length::Fill),
+                                (None, false) => Text::new(""),
+                            })
+                            .push(
+                                Scrollable::new(self.hex2element())
+                                    .id(hex_id())
+                                    .on_scroll(|v| {
+                                        BEditorMessage::NbtViewHexScrolled(
+                                            v.absolute_offset().y,
+                                            v.bounds().height,
+                                        )
+                                    })
+                                    .height(Length::Fill),
+                            ),
+                        false => Column::new(),
+                    }),
             )
             .width(Length::Fill)
             .into()
@@ -0,0 +1,118 @@
+//! Resource and behavior packs, folders with a `manifest.json` whose Nbt
+//! files, like the structures of a behavior pack, can be edited.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// Extensions of the files in a pack that hold Nbt.
+pub const NBT_EXTENSIONS: [&str; 3] = ["mcstructure", "nbt", "dat"];
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// A pack folder and the Nbt files in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pack {
+    pub dir: PathBuf,
+    /// Name from the manifest, or the folder name
+    pub name: String,
+    /// Paths relative to `dir`, sorted
+    pub files: Vec<PathBuf>,
+}
+
+fn is_nbt(path: &Path) -> bool {
+    path.extension()
+        .and_then(|v| v.to_str())
+        .is_some_and(|v| NBT_EXTENSIONS.iter().any(|e| v.eq_ignore_ascii_case(e)))
+}
+
+fn find_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            find_files(root, &path, files)?;
+        } else if is_nbt(&path) {
+            if let Ok(v) = path.strip_prefix(root) {
+                files.push(v.to_path_buf());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Name of the pack in `header.name` of its manifest.
+fn manifest_name(text: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(text).ok()?;
+
+    match value.get("header")?.get("name")? {
+        Value::String(v) if !v.trim().is_empty() => Some(v.trim().to_string()),
+        _ => None,
+    }
+}
+
+/// Whether `dir` is a pack folder, one with a manifest.
+pub fn is_pack(dir: &Path) -> bool {
+    dir.join(MANIFEST_FILE).is_file()
+}
+
+impl Pack {
+    /// Reads the pack folder at `dir`. Packs shipped as `.mcpack` are zip
+    /// archives and have to be extracted first.
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        if dir.is_file() {
+            return Err(format!(
+                "{} is a file, extract the pack into a folder first",
+                dir.display()
+            ));
+        }
+
+        let manifest = match fs::read_to_string(dir.join(MANIFEST_FILE)) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("Error reading {MANIFEST_FILE}: {e:?}")),
+        };
+
+        let mut files = Vec::new();
+
+        if let Err(e) = find_files(dir, dir, &mut files) {
+            return Err(format!("Error reading pack: {e:?}"));
+        }
+
+        files.sort();
+
+        let name = manifest_name(&manifest).unwrap_or_else(|| {
+            dir.file_name()
+                .map_or(String::new(), |v| v.to_string_lossy().to_string())
+        });
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            name,
+            files,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_from_manifest() {
+        let text = r#"{"format_version": 2, "header": {"name": " Castles ", "uuid": "x"}}"#;
+
+        assert_eq!(manifest_name(text).as_deref(), Some("Castles"));
+        assert_eq!(manifest_name(r#"{"header": {"name": ""}}"#), None);
+        assert_eq!(manifest_name("not json"), None);
+    }
+
+    #[test]
+    fn nbt_files_by_extension() {
+        assert!(is_nbt(Path::new("structures/house.mcstructure")));
+        assert!(is_nbt(Path::new("HOUSE.NBT")));
+        assert!(!is_nbt(Path::new("manifest.json")));
+        assert!(!is_nbt(Path::new("mcstructure")));
+    }
+}
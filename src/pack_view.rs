@@ -0,0 +1,189 @@
+use std::path::{Path, PathBuf};
+
+use beditor::pack::Pack;
+use iced::widget::{Button, Column, Row, Scrollable, Text, TextInput};
+use iced::{theme, Alignment, Command, Element, Length};
+
+use crate::messages::BEditorMessage;
+use crate::nbt_view::{NbtView, ERROR_COLOR};
+use crate::start_view;
+use crate::view::BEditorView;
+
+const FILE_LIST_WIDTH: f32 = 360.0;
+
+/// Lists the Nbt files of a resource or behavior pack folder and edits the selected one.
+pub struct PackView {
+    path: String,
+    pack: Result<Pack, String>,
+    /// Path of the shown file relative to the pack folder
+    selected: Option<PathBuf>,
+    /// File to show once the unsaved changes of the selected one are discarded
+    pending: Option<PathBuf>,
+    file: NbtView,
+}
+
+impl PackView {
+    fn open(&mut self) {
+        self.pack = Pack::open(Path::new(&self.path));
+        self.selected = None;
+        self.pending = None;
+        self.file = NbtView::new();
+    }
+
+    /// Opens the pack folder at `path`.
+    pub fn open_path(&mut self, path: &Path) {
+        self.path = path.to_string_lossy().to_string();
+        self.open();
+    }
+
+    /// Path of the pack folder that is shown, if it could be read.
+    pub fn loaded_path(&self) -> Option<PathBuf> {
+        self.pack.as_ref().ok().map(|v| v.dir.clone())
+    }
+
+    fn show(&mut self, file: PathBuf) {
+        let Ok(pack) = &self.pack else {
+            return;
+        };
+
+        self.file = NbtView::new();
+        self.file.open_path(&pack.dir.join(&file));
+        self.selected = Some(file);
+        self.pending = None;
+    }
+
+    /// Shows `file`, asking first if the selected one has unsaved changes.
+    fn select(&mut self, file: PathBuf) {
+        match self.file.is_dirty() {
+            true => self.pending = Some(file),
+            false => self.show(file),
+        }
+    }
+
+    fn files2element(&self) -> Element<'_, BEditorMessage> {
+        let pack = match &self.pack {
+            Ok(v) => v,
+            Err(e) => return Text::new(e.clone()).style(ERROR_COLOR).into(),
+        };
+
+        let mut col = Column::new().push(Text::new(format!(
+            "{}, {} Nbt files",
+            pack.name,
+            pack.files.len()
+        )));
+
+        for file in pack.files.iter() {
+            let style = match self.selected.as_ref() == Some(file) {
+                true => theme::Button::Primary,
+                false => theme::Button::Text,
+            };
+
+            col = col.push(
+                Button::new(Text::new(file.to_string_lossy().to_string()))
+                    .style(style)
+                    .on_press(BEditorMessage::PackViewSelect(file.clone()))
+                    .width(Length::Fill),
+            );
+        }
+
+        Scrollable::new(col).height(Length::Fill).into()
+    }
+}
+
+impl BEditorView for PackView {
+    fn new() -> Self {
+        Self {
+            path: String::new(),
+            pack: Err(String::new()),
+            selected: None,
+            pending: None,
+            file: NbtView::new(),
+        }
+    }
+
+    fn update(&mut self, message: BEditorMessage) -> Command<BEditorMessage> {
+        match message {
+            BEditorMessage::PackViewSetPath(v) => self.path = v,
+            BEditorMessage::PackViewOpen => self.open(),
+            BEditorMessage::PackViewPickFolder => {
+                return Command::perform(
+                    start_view::pick_folder(),
+                    BEditorMessage::PackViewFolderPicked,
+                );
+            }
+            BEditorMessage::PackViewFolderPicked(Some(v)) => self.open_path(&v),
+            BEditorMessage::PackViewFolderPicked(None) => {}
+            BEditorMessage::PackViewSelect(v) => self.select(v),
+            BEditorMessage::DiscardChanges => {
+                if let Some(v) = self.pending.take() {
+                    self.show(v);
+                }
+            }
+            BEditorMessage::KeepChanges => self.pending = None,
+            // Everything else is for the file that is shown
+            message => return self.file.update(message),
+        }
+
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, BEditorMessage> {
+        let files = Column::new()
+            .push(
+                Row::new()
+                    .push(
+                        TextInput::new("Pack Folder", &self.path)
+                            .on_input(BEditorMessage::PackViewSetPath)
+                            .on_submit(BEditorMessage::PackViewOpen),
+                    )
+                    .push(Button::new(Text::new("Open")).on_press(BEditorMessage::PackViewOpen))
+                    .push(
+                        Button::new(Text::new("Browse..."))
+                            .on_press(BEditorMessage::PackViewPickFolder),
+                    ),
+            )
+            .push(self.files2element())
+            .width(Length::Fixed(FILE_LIST_WIDTH));
+
+        let mut col = Column::new();
+
+        if let Some(v) = &self.pending {
+            col = col.push(
+                Row::new()
+                    .push(Text::new(format!(
+                        "The file has unsaved changes, discard them to open {}?",
+                        v.display()
+                    )))
+                    .push(
+                        Button::new(Text::new("Discard")).on_press(BEditorMessage::DiscardChanges),
+                    )
+                    .push(Button::new(Text::new("Cancel")).on_press(BEditorMessage::KeepChanges))
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+            );
+        }
+
+        col = match self.selected {
+            Some(_) => col.push(self.file.view()),
+            None => col.push(Text::new("Select a file to edit it")),
+        };
+
+        Row::new()
+            .push(files)
+            .push(col)
+            .spacing(8)
+            .width(Length::Fill)
+            .into()
+    }
+
+    fn title(&self) -> String {
+        match &self.pack {
+            Ok(v) => v.name.clone(),
+            Err(_) => String::from("Pack"),
+        }
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.file.is_dirty()
+    }
+}
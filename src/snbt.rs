@@ -0,0 +1,556 @@
+use std::collections::HashMap;
+
+use bedrock_rs::nbt::NbtTag;
+
+use crate::nbt_edit;
+
+const INDENT: &str = "    ";
+
+/// A syntax error in SNBT text, lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnbtError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for SnbtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+fn is_bare_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+fn key(s: &str) -> String {
+    match !s.is_empty() && s.chars().all(is_bare_char) {
+        true => s.to_string(),
+        false => quote(s),
+    }
+}
+
+/// Writes `tag` as SNBT. Compound keys are sorted, `pretty` puts every entry
+/// on its own indented line. Bytes are written signed like Mojang does.
+/// Fails on NaN and infinite floats and on `Empty` tags, which have no SNBT form.
+pub fn to_snbt(tag: &NbtTag, pretty: bool) -> Result<String, String> {
+    let mut out = String::new();
+    write_tag(&mut out, tag, pretty, 0)?;
+    Ok(out)
+}
+
+fn check_finite(v: f64, kind: &str) -> Result<(), String> {
+    match v.is_finite() {
+        true => Ok(()),
+        false => Err(format!("{kind} {v} has no SNBT form")),
+    }
+}
+
+fn write_tag(out: &mut String, tag: &NbtTag, pretty: bool, depth: usize) -> Result<(), String> {
+    match tag {
+        NbtTag::Byte(v) => out.push_str(&format!("{}b", *v as i8)),
+        NbtTag::Int16(v) => out.push_str(&format!("{v}s")),
+        NbtTag::Int32(v) => out.push_str(&v.to_string()),
+        NbtTag::Int64(v) => out.push_str(&format!("{v}L")),
+        NbtTag::Float32(v) => {
+            check_finite(*v as f64, "Float32")?;
+            out.push_str(&format!("{v:?}f"));
+        }
+        NbtTag::Float64(v) => {
+            check_finite(*v, "Float64")?;
+            out.push_str(&format!("{v:?}d"));
+        }
+        NbtTag::String(v) => out.push_str(&quote(v)),
+        NbtTag::List(v) => {
+            out.push('[');
+
+            for (i, nbt) in v.iter().enumerate() {
+                if i != 0 {
+                    out.push(',');
+                    if !pretty {
+                        out.push(' ');
+                    }
+                }
+
+                if pretty {
+                    newline(out, depth + 1);
+                }
+
+                write_tag(out, nbt, pretty, depth + 1)?;
+            }
+
+            if pretty && !v.is_empty() {
+                newline(out, depth);
+            }
+
+            out.push(']');
+        }
+        NbtTag::Compound(v) => {
+            out.push('{');
+
+            let mut keys: Vec<&String> = v.keys().collect();
+            keys.sort();
+
+            for (i, k) in keys.iter().enumerate() {
+                if i != 0 {
+                    out.push(',');
+                    if !pretty {
+                        out.push(' ');
+                    }
+                }
+
+                if pretty {
+                    newline(out, depth + 1);
+                }
+
+                out.push_str(&key(k));
+                out.push_str(": ");
+                write_tag(out, &v[*k], pretty, depth + 1)?;
+            }
+
+            if pretty && !v.is_empty() {
+                newline(out, depth);
+            }
+
+            out.push('}');
+        }
+        NbtTag::Empty => return Err(String::from("Empty tags have no SNBT form")),
+    }
+
+    Ok(())
+}
+
+fn newline(out: &mut String, depth: usize) {
+    out.push('\n');
+
+    for _ in 0..depth {
+        out.push_str(INDENT);
+    }
+}
+
+/// Reads a single tag from SNBT text.
+pub fn parse_snbt(text: &str) -> Result<NbtTag, SnbtError> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+    };
+
+    let tag = parser.parse_value(0)?;
+
+    parser.skip_whitespace();
+
+    if parser.peek().is_some() {
+        return Err(parser.error("Unexpected text after the value"));
+    }
+
+    Ok(tag)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, message: impl Into<String>) -> SnbtError {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, pos: usize, message: impl Into<String>) -> SnbtError {
+        let mut line = 1;
+        let mut column = 1;
+
+        for c in self.chars[..pos.min(self.chars.len())].iter() {
+            if *c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+
+        SnbtError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SnbtError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("Expected '{expected}' but found '{c}'"))),
+            None => Err(self.error(format!("Expected '{expected}' but the text ended"))),
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<NbtTag, SnbtError> {
+        if depth > crate::nbt_decode::MAX_DEPTH {
+            return Err(self.error("Nested too deeply"));
+        }
+
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('{') => self.parse_compound(depth),
+            Some('[') => self.parse_list(depth),
+            Some('"') | Some('\'') => Ok(NbtTag::String(self.parse_quoted()?)),
+            Some(c) if is_bare_char(c) => {
+                let start = self.pos;
+                let word = self.parse_bare();
+                parse_bare_value(&word).map_err(|e| self.error_at(start, e))
+            }
+            Some(c) => Err(self.error(format!("Unexpected '{c}'"))),
+            None => Err(self.error("Expected a value but the text ended")),
+        }
+    }
+
+    fn parse_bare(&mut self) -> String {
+        let start = self.pos;
+
+        while self.peek().is_some_and(is_bare_char) {
+            self.pos += 1;
+        }
+
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, SnbtError> {
+        let start = self.pos;
+        let quote = self.chars[self.pos];
+        self.pos += 1;
+
+        let mut out = String::new();
+
+        loop {
+            match self.peek() {
+                None => return Err(self.error_at(start, "Unterminated string")),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+
+                    match self.peek() {
+                        Some('n') => out.push('\n'),
+                        Some('t') => out.push('\t'),
+                        Some('r') => out.push('\r'),
+                        Some(c @ ('\\' | '"' | '\'')) => out.push(c),
+                        Some(c) => return Err(self.error(format!("Unknown escape '\\{c}'"))),
+                        None => return Err(self.error_at(start, "Unterminated string")),
+                    }
+
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    out.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<String, SnbtError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('"') | Some('\'') => self.parse_quoted(),
+            Some(c) if is_bare_char(c) => Ok(self.parse_bare()),
+            Some(c) => Err(self.error(format!("Expected a key but found '{c}'"))),
+            None => Err(self.error("Expected a key but the text ended")),
+        }
+    }
+
+    fn parse_compound(&mut self, depth: usize) -> Result<NbtTag, SnbtError> {
+        self.pos += 1;
+
+        let mut compound = HashMap::new();
+
+        self.skip_whitespace();
+
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(NbtTag::Compound(compound));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            let key = self.parse_key()?;
+
+            self.expect(':')?;
+
+            let tag = self.parse_value(depth + 1)?;
+
+            if compound.insert(key.clone(), tag).is_some() {
+                return Err(self.error_at(start, format!("Duplicate key \"{key}\"")));
+            }
+
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(NbtTag::Compound(compound));
+                }
+                Some(c) => return Err(self.error(format!("Expected ',' or '}}' but found '{c}'"))),
+                None => return Err(self.error("Expected ',' or '}' but the text ended")),
+            }
+        }
+    }
+
+    fn parse_list(&mut self, depth: usize) -> Result<NbtTag, SnbtError> {
+        let open = self.pos;
+        self.pos += 1;
+
+        // Typed arrays like [I; 1, 2] have no NbtTag counterpart
+        if matches!(self.chars.get(self.pos + 1), Some(';'))
+            && matches!(self.peek(), Some('B' | 'I' | 'L'))
+        {
+            return Err(self.error_at(open, "Typed arrays are not supported in Bedrock Nbt"));
+        }
+
+        let mut list = Vec::new();
+
+        self.skip_whitespace();
+
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(NbtTag::List(list));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            let tag = self.parse_value(depth + 1)?;
+
+            if let Err(e) = nbt_edit::check_list_element(&list, &tag) {
+                return Err(self.error_at(start, e));
+            }
+
+            list.push(tag);
+
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(NbtTag::List(list));
+                }
+                Some(c) => return Err(self.error(format!("Expected ',' or ']' but found '{c}'"))),
+                None => return Err(self.error("Expected ',' or ']' but the text ended")),
+            }
+        }
+    }
+}
+
+/// Turns an unquoted word into a number with the type of its suffix, or a string.
+fn parse_bare_value(word: &str) -> Result<NbtTag, String> {
+    match word {
+        "true" => return Ok(NbtTag::Byte(1)),
+        "false" => return Ok(NbtTag::Byte(0)),
+        _ => {}
+    }
+
+    let (number, suffix) = match word.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&word[..i], Some(c.to_ascii_lowercase())),
+        _ => (word, None),
+    };
+
+    let out_of_range = |kind: &str| format!("{word} is out of range for {kind}");
+
+    // Rust also reads words like inf and NaN as floats, SNBT keeps them as strings
+    let is_float = |v: &str| v.chars().all(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+
+    let tag = match suffix {
+        Some('b') => number.parse::<i16>().ok().map(|v| match v {
+            -128..=255 => Ok(NbtTag::Byte(v as u8)),
+            _ => Err(out_of_range("Byte")),
+        }),
+        Some('s') => number.parse::<i64>().ok().map(|v| {
+            i16::try_from(v)
+                .map(NbtTag::Int16)
+                .map_err(|_| out_of_range("Int16"))
+        }),
+        Some('l') => number.parse::<i128>().ok().map(|v| {
+            i64::try_from(v)
+                .map(NbtTag::Int64)
+                .map_err(|_| out_of_range("Int64"))
+        }),
+        Some('f') if is_float(number) => number.parse::<f32>().ok().map(|v| match v.is_finite() {
+            true => Ok(NbtTag::Float32(v)),
+            false => Err(out_of_range("Float32")),
+        }),
+        Some('d') if is_float(number) => number.parse::<f64>().ok().map(|v| match v.is_finite() {
+            true => Ok(NbtTag::Float64(v)),
+            false => Err(out_of_range("Float64")),
+        }),
+        _ => None,
+    };
+
+    if let Some(tag) = tag {
+        return tag;
+    }
+
+    if let Ok(v) = word.parse::<i64>() {
+        return i32::try_from(v)
+            .map(NbtTag::Int32)
+            .map_err(|_| out_of_range("Int32"));
+    }
+
+    if word.contains(['.', 'e', 'E']) && is_float(word) {
+        if let Ok(v) = word.parse::<f64>() {
+            return match v.is_finite() {
+                true => Ok(NbtTag::Float64(v)),
+                false => Err(out_of_range("Float64")),
+            };
+        }
+    }
+
+    Ok(NbtTag::String(word.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compound(entries: &[(&str, NbtTag)]) -> NbtTag {
+        NbtTag::Compound(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    fn sample() -> NbtTag {
+        compound(&[
+            ("byte", NbtTag::Byte(200)),
+            ("short", NbtTag::Int16(-3)),
+            ("int", NbtTag::Int32(i32::MIN)),
+            ("long", NbtTag::Int64(i64::MAX)),
+            ("float", NbtTag::Float32(0.1)),
+            ("double", NbtTag::Float64(-1e300)),
+            ("text", NbtTag::String(String::from("a \"b\"\n\\c"))),
+            ("with space", NbtTag::String(String::from("true"))),
+            (
+                "list",
+                NbtTag::List(vec![NbtTag::Int32(1), NbtTag::Int32(2)]),
+            ),
+            ("empty list", NbtTag::List(Vec::new())),
+            ("nested", compound(&[("x", NbtTag::Byte(1))])),
+        ])
+    }
+
+    #[test]
+    fn round_trip() {
+        for pretty in [false, true] {
+            let text = to_snbt(&sample(), pretty).unwrap();
+            assert_eq!(parse_snbt(&text), Ok(sample()), "{text}");
+        }
+    }
+
+    #[test]
+    fn writes_mojang_suffixes() {
+        let text = to_snbt(
+            &compound(&[("a", NbtTag::Byte(255)), ("b", NbtTag::Int64(5))]),
+            false,
+        );
+
+        assert_eq!(text.as_deref(), Ok("{a: -1b, b: 5L}"));
+    }
+
+    #[test]
+    fn rejects_values_without_snbt_form() {
+        for tag in [
+            NbtTag::Float32(f32::NAN),
+            NbtTag::Float32(f32::INFINITY),
+            NbtTag::Float64(f64::NEG_INFINITY),
+            NbtTag::List(vec![NbtTag::Empty]),
+        ] {
+            assert!(to_snbt(&tag, false).is_err(), "{tag:?}");
+        }
+    }
+
+    #[test]
+    fn parses_bare_values() {
+        assert_eq!(parse_snbt("true"), Ok(NbtTag::Byte(1)));
+        assert_eq!(parse_snbt("-128b"), Ok(NbtTag::Byte(128)));
+        assert_eq!(parse_snbt("1.5"), Ok(NbtTag::Float64(1.5)));
+        assert_eq!(parse_snbt("2147483647"), Ok(NbtTag::Int32(i32::MAX)));
+        assert_eq!(
+            parse_snbt("minecraft:stone"),
+            Err(SnbtError {
+                line: 1,
+                column: 10,
+                message: String::from("Unexpected text after the value")
+            })
+        );
+        assert!(parse_snbt("2147483648").is_err());
+        assert!(parse_snbt("300b").is_err());
+    }
+
+    #[test]
+    fn floats_are_finite() {
+        for word in ["inff", "nanf", "infd", "nand", "NaN", "infinity"] {
+            assert_eq!(parse_snbt(word), Ok(NbtTag::String(word.to_string())));
+        }
+
+        assert_eq!(parse_snbt("1.5f"), Ok(NbtTag::Float32(1.5)));
+        assert_eq!(parse_snbt("-2e3d"), Ok(NbtTag::Float64(-2000.0)));
+        assert!(parse_snbt("1e39f").is_err());
+        assert!(parse_snbt("-1e999").is_err());
+    }
+
+    #[test]
+    fn reports_errors_with_position() {
+        let e = parse_snbt("{\n  a: 1,\n  a: 2\n}").unwrap_err();
+        assert_eq!((e.line, e.column), (3, 3));
+
+        assert!(parse_snbt("[I; 1, 2]").is_err());
+        assert!(parse_snbt("[1, \"a\"]").is_err());
+        assert!(parse_snbt("{a: 1").is_err());
+    }
+}
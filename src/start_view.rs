@@ -0,0 +1,152 @@
+use std::path::PathBuf;
+
+use beditor::backup;
+use beditor::backup::Backup;
+use iced::widget::{Button, Column, Row, Scrollable, Text};
+use iced::{Alignment, Command, Element, Length};
+
+use crate::config::{Config, Recent, RecentKind};
+use crate::messages::BEditorMessage;
+use crate::nbt_view;
+use crate::nbt_view::ERROR_COLOR;
+use crate::view::BEditorView;
+
+/// Start screen, lists the recently opened files, worlds and packs and the
+/// snapshots taken of worlds before they were written.
+pub struct StartView {
+    recent: Vec<Recent>,
+    /// Newest first
+    backups: Vec<Backup>,
+    /// Outcome of the last restore
+    status: Option<Result<String, String>>,
+}
+
+fn load_backups() -> Vec<Backup> {
+    backup::default_root()
+        .map(|v| backup::list(&v))
+        .unwrap_or_default()
+}
+
+fn restore(backup: &Backup) -> Result<String, String> {
+    let current = backup::restore(&backup::default_root()?, backup)?;
+
+    Ok(format!(
+        "Restored {backup}, the state before is kept as {current}"
+    ))
+}
+
+pub async fn pick_folder() -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .pick_folder()
+        .await
+        .map(|v| v.path().to_path_buf())
+}
+
+impl BEditorView for StartView {
+    fn new() -> Self {
+        Self {
+            recent: Config::load().recent,
+            backups: load_backups(),
+            status: None,
+        }
+    }
+
+    fn update(&mut self, message: BEditorMessage) -> Command<BEditorMessage> {
+        match message {
+            BEditorMessage::StartPickNbt => Command::perform(nbt_view::pick_file(), |v| {
+                BEditorMessage::StartPicked(RecentKind::Nbt, v)
+            }),
+            BEditorMessage::StartPickWorld => Command::perform(pick_folder(), |v| {
+                BEditorMessage::StartPicked(RecentKind::World, v)
+            }),
+            BEditorMessage::StartPickPack => Command::perform(pick_folder(), |v| {
+                BEditorMessage::StartPicked(RecentKind::Pack, v)
+            }),
+            BEditorMessage::StartRestore(v) => {
+                self.status = Some(restore(&v));
+                self.backups = load_backups();
+                Command::none()
+            }
+            _ => Command::none(),
+        }
+    }
+
+    fn view(&self) -> Element<'_, BEditorMessage> {
+        let mut recent = Column::new();
+
+        for v in self.recent.iter() {
+            recent = recent.push(
+                Button::new(Text::new(format!("{}: {}", v.kind, v.path.display())))
+                    .on_press(BEditorMessage::Open(v.clone()))
+                    .width(Length::Fill),
+            );
+        }
+
+        if self.recent.is_empty() {
+            recent = recent.push(Text::new("Nothing opened yet"));
+        }
+
+        let mut backups = Column::new();
+
+        for v in self.backups.iter() {
+            backups = backups.push(
+                Row::new()
+                    .push(Text::new(v.to_string()).width(Length::Fill))
+                    .push(
+                        Button::new(Text::new("Restore"))
+                            .on_press(BEditorMessage::StartRestore(v.clone())),
+                    )
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+            );
+        }
+
+        if self.backups.is_empty() {
+            backups = backups.push(Text::new("No world was written yet"));
+        }
+
+        Column::new()
+            .push(Text::new("BEditor").size(32))
+            .push(
+                Row::new()
+                    .push(
+                        Button::new(Text::new("Open Nbt File"))
+                            .on_press(BEditorMessage::StartPickNbt),
+                    )
+                    .push(
+                        Button::new(Text::new("Open World"))
+                            .on_press(BEditorMessage::StartPickWorld),
+                    )
+                    .push(
+                        Button::new(Text::new("Open Pack")).on_press(BEditorMessage::StartPickPack),
+                    )
+                    .push(
+                        Button::new(Text::new("Compare Nbt Files"))
+                            .on_press(BEditorMessage::StartCompare),
+                    )
+                    .push(
+                        Button::new(Text::new("Merge Nbt Files"))
+                            .on_press(BEditorMessage::StartMerge),
+                    ),
+            )
+            .push(Text::new("Recent"))
+            .push(Scrollable::new(recent).width(Length::Fill))
+            .push(Text::new("Backups"))
+            .push(match &self.status {
+                None => Text::new(""),
+                Some(Ok(v)) => Text::new(v.clone()),
+                Some(Err(e)) => Text::new(e.clone()).style(ERROR_COLOR),
+            })
+            .push(Scrollable::new(backups).width(Length::Fill))
+            .width(Length::Fill)
+            .into()
+    }
+
+    fn title(&self) -> String {
+        String::from("Start")
+    }
+
+    fn is_dirty(&self) -> bool {
+        false
+    }
+}
@@ -1,7 +1,64 @@
+use iced::{Command, Element};
+
+use crate::diff_view::DiffView;
+use crate::merge_view::MergeView;
+use crate::messages::BEditorMessage;
 use crate::nbt_view::NbtView;
+use crate::pack_view::PackView;
+use crate::start_view::StartView;
+use crate::view::BEditorView;
+use crate::world_view::WorldView;
 
 pub enum BEditorState {
     /// Start Screen
-    Idle,
+    Idle(StartView),
     NbtView(NbtView),
+    /// Two Nbt files compared side by side
+    DiffView(DiffView),
+    /// Three-way merge of two versions of an Nbt file
+    MergeView(MergeView),
+    /// The level.dat and database of a world folder
+    WorldView(Box<WorldView>),
+    /// The Nbt files of a resource or behavior pack
+    PackView(PackView),
+}
+
+impl BEditorState {
+    fn view_mut(&mut self) -> &mut dyn BEditorView {
+        match self {
+            BEditorState::Idle(v) => v,
+            BEditorState::NbtView(v) => v,
+            BEditorState::DiffView(v) => v,
+            BEditorState::MergeView(v) => v,
+            BEditorState::WorldView(v) => v.as_mut(),
+            BEditorState::PackView(v) => v,
+        }
+    }
+
+    fn view_ref(&self) -> &dyn BEditorView {
+        match self {
+            BEditorState::Idle(v) => v,
+            BEditorState::NbtView(v) => v,
+            BEditorState::DiffView(v) => v,
+            BEditorState::MergeView(v) => v,
+            BEditorState::WorldView(v) => v.as_ref(),
+            BEditorState::PackView(v) => v,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        self.view_ref().title()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.view_ref().is_dirty()
+    }
+
+    pub fn update(&mut self, message: BEditorMessage) -> Command<BEditorMessage> {
+        self.view_mut().update(message)
+    }
+
+    pub fn view(&self) -> Element<'_, BEditorMessage> {
+        self.view_ref().view()
+    }
 }
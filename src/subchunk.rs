@@ -0,0 +1,334 @@
+//! Blocks of a SubChunkPrefix record, one 16x16x16 part of a chunk. Every
+//! layer stores a palette index per block packed into 32 bit words, followed
+//! by the palette as Little Endian Nbt compounds.
+
+use std::ops::Range;
+
+use bedrock_rs::nbt::NbtTag;
+
+use crate::document::NbtEndian;
+use crate::nbt_decode::NbtDecoder;
+
+/// Blocks along each side of a subchunk.
+pub const SIZE: usize = 16;
+const BLOCK_COUNT: usize = SIZE * SIZE * SIZE;
+const TAG_COMPOUND: u8 = 10;
+
+/// One block state of a palette.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteEntry {
+    pub tag: NbtTag,
+    /// Bytes of the compound in the record
+    pub range: Range<usize>,
+}
+
+impl PaletteEntry {
+    /// The block id, like `minecraft:stone`.
+    pub fn name(&self) -> Option<&str> {
+        match &self.tag {
+            NbtTag::Compound(v) => match v.get("name") {
+                Some(NbtTag::String(v)) => Some(v),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// One layer of blocks, the second one usually holds water in waterlogged blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockStorage {
+    /// Bits of each palette index, 0 if the whole layer is one block
+    pub bits: u8,
+    /// Palette index of every block in x, z, y order
+    indices: Vec<u16>,
+    pub palette: Vec<PaletteEntry>,
+}
+
+impl BlockStorage {
+    /// Palette index of the block, `None` if a coordinate isn't below [`SIZE`].
+    pub fn index_at(&self, x: usize, y: usize, z: usize) -> Option<u16> {
+        if x >= SIZE || y >= SIZE || z >= SIZE {
+            return None;
+        }
+
+        self.indices.get((x * SIZE + z) * SIZE + y).copied()
+    }
+
+    pub fn block_at(&self, x: usize, y: usize, z: usize) -> Option<&PaletteEntry> {
+        self.palette.get(self.index_at(x, y, z)? as usize)
+    }
+
+    /// Number of blocks using each palette entry.
+    pub fn counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.palette.len()];
+
+        for index in self.indices.iter() {
+            if let Some(v) = counts.get_mut(*index as usize) {
+                *v += 1;
+            }
+        }
+
+        counts
+    }
+}
+
+/// A decoded SubChunkPrefix record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubChunk {
+    pub version: u8,
+    /// Vertical index of the subchunk, stored since version 9
+    pub y: Option<i8>,
+    pub layers: Vec<BlockStorage>,
+}
+
+/// Reads the record front to back.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], String> {
+        match self.data.get(self.pos..self.pos + n) {
+            Some(v) => {
+                self.pos += n;
+                Ok(v)
+            }
+            None => Err(format!("SubChunk ended at offset {}", self.data.len())),
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn palette_entry(&mut self) -> Result<PaletteEntry, String> {
+        let mut decoder = NbtDecoder::new(&self.data[self.pos..], NbtEndian::Little);
+
+        let tag = match decoder.read_root() {
+            Ok((_, v)) => v,
+            Err(mut e) => {
+                e.shift(self.pos);
+                return Err(format!("Error reading palette: {e}"));
+            }
+        };
+
+        let start = self.pos;
+        self.pos += decoder.position();
+
+        Ok(PaletteEntry {
+            tag,
+            range: start..self.pos,
+        })
+    }
+
+    fn storage(&mut self) -> Result<BlockStorage, String> {
+        let flags = self.u8()?;
+
+        if flags & 1 != 0 {
+            return Err(String::from(
+                "Block storage with runtime ids is only sent over the network",
+            ));
+        }
+
+        let bits = flags >> 1;
+
+        if !matches!(bits, 0..=6 | 8 | 16) {
+            return Err(format!("Invalid block storage with {bits} bits per block"));
+        }
+
+        let mut indices = Vec::with_capacity(BLOCK_COUNT);
+
+        match bits {
+            // The whole layer is the first palette entry
+            0 => indices.resize(BLOCK_COUNT, 0),
+            _ => {
+                // Indices don't span words, the bits left over in each are padding
+                let per_word = 32 / bits as usize;
+                let mask = (1u32 << bits) - 1;
+
+                for _ in 0..BLOCK_COUNT.div_ceil(per_word) {
+                    let word = self.u32()?;
+
+                    for i in 0..per_word {
+                        indices.push((word >> (i * bits as usize) & mask) as u16);
+                    }
+                }
+
+                indices.truncate(BLOCK_COUNT);
+            }
+        }
+
+        // Single block layers may leave out the palette size
+        let size = match (bits, self.data.get(self.pos)) {
+            (0, Some(&TAG_COMPOUND)) => 1,
+            _ => self.u32()?,
+        };
+
+        if size as usize > BLOCK_COUNT {
+            return Err(format!(
+                "Palette of {size} entries is larger than the subchunk"
+            ));
+        }
+
+        let palette = (0..size)
+            .map(|_| self.palette_entry())
+            .collect::<Result<_, _>>()?;
+
+        Ok(BlockStorage {
+            bits,
+            indices,
+            palette,
+        })
+    }
+}
+
+impl SubChunk {
+    /// Decodes the value of a SubChunkPrefix record of version 1, 8 or 9.
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { data, pos: 0 };
+
+        let version = reader.u8()?;
+
+        let (count, y) = match version {
+            1 => (1, None),
+            8 => (reader.u8()?, None),
+            9 => (reader.u8()?, Some(reader.u8()? as i8)),
+            v => return Err(format!("SubChunk version {v} isn't supported")),
+        };
+
+        let layers = (0..count)
+            .map(|_| reader.storage())
+            .collect::<Result<_, _>>()?;
+
+        if reader.pos != data.len() {
+            return Err(format!(
+                "SubChunk has {} bytes left after the last layer",
+                data.len() - reader.pos
+            ));
+        }
+
+        Ok(Self { version, y, layers })
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn palette(&self, layer: usize) -> &[PaletteEntry] {
+        self.layers.get(layer).map_or(&[], |v| &v.palette)
+    }
+
+    /// The block in `layer`, `None` if a coordinate isn't below [`SIZE`].
+    pub fn block_at(&self, layer: usize, x: usize, y: usize, z: usize) -> Option<&PaletteEntry> {
+        self.layers.get(layer)?.block_at(x, y, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::nbt_decode::NbtLayout;
+    use crate::nbt_encode;
+
+    fn block(name: &str) -> Vec<u8> {
+        let tag = NbtTag::Compound(HashMap::from([(
+            String::from("name"),
+            NbtTag::String(name.to_string()),
+        )]));
+
+        nbt_encode::encode_root("", &tag, NbtEndian::Little, &NbtLayout::default()).unwrap()
+    }
+
+    /// A version 9 subchunk with one layer of 3 bit indices, 10 to a word
+    /// with 2 bits of padding, where only the block at `x`, `y`, `z` is stone.
+    fn stone_at(x: usize, y: usize, z: usize) -> Vec<u8> {
+        let mut indices = vec![0u32; BLOCK_COUNT];
+        indices[(x * SIZE + z) * SIZE + y] = 1;
+
+        let mut data = vec![9, 1, 0xfc, 3 << 1];
+
+        for word in indices.chunks(10) {
+            let word = word
+                .iter()
+                .enumerate()
+                .fold(0xc000_0000_u32, |word, (i, v)| word | v << (i * 3));
+
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend(block("minecraft:air"));
+        data.extend(block("minecraft:stone"));
+
+        data
+    }
+
+    #[test]
+    fn indices_skip_the_padding_of_each_word() {
+        for (x, y, z) in [(0, 0, 0), (0, 9, 0), (0, 10, 0), (3, 15, 7), (15, 15, 15)] {
+            let subchunk = SubChunk::parse(&stone_at(x, y, z)).unwrap();
+            let layer = &subchunk.layers[0];
+
+            assert_eq!(subchunk.y, Some(-4));
+            assert_eq!(layer.counts(), [BLOCK_COUNT - 1, 1]);
+            assert_eq!(
+                subchunk.block_at(0, x, y, z).and_then(|v| v.name()),
+                Some("minecraft:stone")
+            );
+        }
+    }
+
+    #[test]
+    fn palette_ranges_point_into_the_record() {
+        let data = stone_at(0, 0, 0);
+        let subchunk = SubChunk::parse(&data).unwrap();
+        let stone = &subchunk.palette(0)[1];
+
+        assert_eq!(data[stone.range.clone()], block("minecraft:stone"));
+        assert_eq!(stone.range.end, data.len());
+    }
+
+    #[test]
+    fn single_block_layer_without_palette_size() {
+        let mut data = vec![8, 1, 0];
+        data.extend(block("minecraft:water"));
+
+        let subchunk = SubChunk::parse(&data).unwrap();
+
+        assert_eq!(subchunk.layers[0].bits, 0);
+        assert_eq!(
+            subchunk.block_at(0, 15, 15, 15).and_then(|v| v.name()),
+            Some("minecraft:water")
+        );
+    }
+
+    #[test]
+    fn coordinates_outside_are_none() {
+        let subchunk = SubChunk::parse(&stone_at(0, 0, 1)).unwrap();
+        let layer = &subchunk.layers[0];
+
+        // y of 16 would otherwise read the next column
+        assert_eq!(layer.index_at(0, 16, 0), None);
+        assert_eq!(layer.index_at(16, 0, 0), None);
+        assert_eq!(layer.block_at(0, 0, SIZE), None);
+        assert_eq!(subchunk.block_at(1, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut data = stone_at(0, 0, 0);
+        data.push(0);
+
+        assert!(SubChunk::parse(&data).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+//! Folders for tests that touch the file system.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// A new folder in the temp directory, removed when dropped.
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("beditor-{name}-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
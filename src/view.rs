@@ -1,11 +1,19 @@
-use iced::Element;
+use iced::{Command, Element};
 
 use crate::messages::BEditorMessage;
 
 pub trait BEditorView {
-    fn new() -> Self;
+    fn new() -> Self
+    where
+        Self: Sized;
 
-    fn update(&mut self, message: BEditorMessage);
+    fn update(&mut self, message: BEditorMessage) -> Command<BEditorMessage>;
 
-    fn view(&self) -> Element<BEditorMessage>;
+    fn view(&self) -> Element<'_, BEditorMessage>;
+
+    /// Short name of what is shown, used for the tab and window title
+    fn title(&self) -> String;
+
+    /// Whether there are changes that weren't saved yet
+    fn is_dirty(&self) -> bool;
 }
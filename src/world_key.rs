@@ -0,0 +1,431 @@
+//! What the keys in the LevelDB of a Bedrock world stand for.
+
+/// Dimension a chunk is in, stored as a little endian id after the chunk
+/// coordinates. The Overworld leaves the id out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Dimension {
+    Overworld,
+    Nether,
+    End,
+    Other(i32),
+}
+
+impl Dimension {
+    pub fn from_id(id: i32) -> Self {
+        match id {
+            0 => Dimension::Overworld,
+            1 => Dimension::Nether,
+            2 => Dimension::End,
+            v => Dimension::Other(v),
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        match self {
+            Dimension::Overworld => 0,
+            Dimension::Nether => 1,
+            Dimension::End => 2,
+            Dimension::Other(v) => *v,
+        }
+    }
+}
+
+impl std::fmt::Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dimension::Overworld => write!(f, "Overworld"),
+            Dimension::Nether => write!(f, "Nether"),
+            Dimension::End => write!(f, "The End"),
+            Dimension::Other(v) => write!(f, "Dimension {v}"),
+        }
+    }
+}
+
+/// What a chunk record holds, the byte after the chunk coordinates and dimension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ChunkTag {
+    Data3D,
+    Version,
+    Data2D,
+    Data2DLegacy,
+    /// Blocks of a 16x16x16 part of the chunk, followed by its vertical index
+    SubChunkPrefix,
+    LegacyTerrain,
+    BlockEntity,
+    Entity,
+    PendingTicks,
+    LegacyBlockExtraData,
+    BiomeState,
+    FinalizedState,
+    ConversionData,
+    BorderBlocks,
+    HardcodedSpawners,
+    RandomTicks,
+    Checksums,
+    GenerationSeed,
+    GeneratedPreCavesAndCliffsBlending,
+    BlendingBiomeHeight,
+    MetaDataHash,
+    BlendingData,
+    ActorDigestVersion,
+    LegacyVersion,
+}
+
+impl ChunkTag {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Some(match byte {
+            43 => ChunkTag::Data3D,
+            44 => ChunkTag::Version,
+            45 => ChunkTag::Data2D,
+            46 => ChunkTag::Data2DLegacy,
+            47 => ChunkTag::SubChunkPrefix,
+            48 => ChunkTag::LegacyTerrain,
+            49 => ChunkTag::BlockEntity,
+            50 => ChunkTag::Entity,
+            51 => ChunkTag::PendingTicks,
+            52 => ChunkTag::LegacyBlockExtraData,
+            53 => ChunkTag::BiomeState,
+            54 => ChunkTag::FinalizedState,
+            55 => ChunkTag::ConversionData,
+            56 => ChunkTag::BorderBlocks,
+            57 => ChunkTag::HardcodedSpawners,
+            58 => ChunkTag::RandomTicks,
+            59 => ChunkTag::Checksums,
+            60 => ChunkTag::GenerationSeed,
+            61 => ChunkTag::GeneratedPreCavesAndCliffsBlending,
+            62 => ChunkTag::BlendingBiomeHeight,
+            63 => ChunkTag::MetaDataHash,
+            64 => ChunkTag::BlendingData,
+            65 => ChunkTag::ActorDigestVersion,
+            118 => ChunkTag::LegacyVersion,
+            _ => return None,
+        })
+    }
+}
+
+impl std::fmt::Display for ChunkTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Keys of records about the whole world.
+const GLOBAL_KEYS: [&str; 14] = [
+    "portals",
+    "scoreboard",
+    "AutonomousEntities",
+    "BiomeData",
+    "mobevents",
+    "schedulerWT",
+    "LevelChunkMetaDataDictionary",
+    "Overworld",
+    "Nether",
+    "TheEnd",
+    "dimension0",
+    "dimension1",
+    "dimension2",
+    "game_flatworldlayers",
+];
+
+/// Prefixes of the keys of named records about the whole world.
+const GLOBAL_PREFIXES: [&str; 3] = ["structuretemplate_", "tickingarea_", "PosTrackDB-"];
+
+/// What a LevelDB key of a world stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorldKey {
+    /// A record of the chunk at the chunk coordinates, `subchunk` is the
+    /// vertical index of [`ChunkTag::SubChunkPrefix`] records
+    Chunk {
+        x: i32,
+        z: i32,
+        dimension: Dimension,
+        tag: ChunkTag,
+        subchunk: Option<i8>,
+    },
+    /// Ids of the actors in a chunk, `digp` followed by the chunk coordinates
+    ActorDigest {
+        x: i32,
+        z: i32,
+        dimension: Dimension,
+    },
+    /// An actor, `actorprefix` followed by the 8 bytes of its unique id
+    Actor([u8; 8]),
+    /// `~local_player`, the player of a single player world
+    LocalPlayer,
+    /// `player_<uuid>`
+    Player(String),
+    /// `player_server_<uuid>`
+    ServerPlayer(String),
+    /// `map_<id>`
+    Map(i64),
+    /// `VILLAGE_<id>_<part>`, newer versions put the dimension before the id
+    Village {
+        id: String,
+        part: String,
+    },
+    /// A record about the whole world like `portals` or `scoreboard`
+    Global(String),
+    Unknown,
+}
+
+/// Printable ASCII of a key as is, other bytes as `\xNN`.
+pub fn escape_key(key: &[u8]) -> String {
+    let mut text = String::new();
+
+    for byte in key {
+        match byte {
+            b' '..=b'~' if *byte != b'\\' => text.push(*byte as char),
+            _ => text.push_str(&format!("\\x{byte:02x}")),
+        }
+    }
+
+    text
+}
+
+fn read_i32(bytes: &[u8]) -> i32 {
+    i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Reads the chunk coordinates and dimension at the start of `key`, returning
+/// the bytes after them.
+fn chunk_position(key: &[u8], tail: usize) -> Option<(i32, i32, Dimension, &[u8])> {
+    let (dimension, rest) = match key.len().checked_sub(tail)? {
+        8 => (Dimension::Overworld, &key[8..]),
+        // Keeps text keys of the same length from being taken for chunks
+        12 if (1..=255).contains(&read_i32(&key[8..12])) => {
+            (Dimension::from_id(read_i32(&key[8..12])), &key[12..])
+        }
+        _ => return None,
+    };
+
+    Some((read_i32(&key[0..4]), read_i32(&key[4..8]), dimension, rest))
+}
+
+fn chunk_key(key: &[u8]) -> Option<WorldKey> {
+    let (x, z, dimension, rest, subchunk) = match chunk_position(key, 1) {
+        Some((x, z, dimension, rest)) => (x, z, dimension, rest, None),
+        None => {
+            let (x, z, dimension, rest) = chunk_position(key, 2)?;
+            (x, z, dimension, &rest[..1], Some(rest[1] as i8))
+        }
+    };
+
+    let tag = ChunkTag::from_byte(rest[0])?;
+
+    if (tag == ChunkTag::SubChunkPrefix) != subchunk.is_some() {
+        return None;
+    }
+
+    Some(WorldKey::Chunk {
+        x,
+        z,
+        dimension,
+        tag,
+        subchunk,
+    })
+}
+
+impl WorldKey {
+    pub fn parse(key: &[u8]) -> Self {
+        if let Some(v) = key.strip_prefix(b"actorprefix") {
+            if let Ok(id) = v.try_into() {
+                return WorldKey::Actor(id);
+            }
+        }
+
+        if let Some(v) = key.strip_prefix(b"digp") {
+            if let Some((x, z, dimension, [])) = chunk_position(v, 0) {
+                return WorldKey::ActorDigest { x, z, dimension };
+            }
+        }
+
+        if let Ok(text) = std::str::from_utf8(key) {
+            if let Some(v) = Self::parse_text(text) {
+                return v;
+            }
+        }
+
+        chunk_key(key).unwrap_or(WorldKey::Unknown)
+    }
+
+    fn parse_text(text: &str) -> Option<Self> {
+        if text == "~local_player" {
+            return Some(WorldKey::LocalPlayer);
+        }
+
+        if let Some(v) = text.strip_prefix("player_server_") {
+            return Some(WorldKey::ServerPlayer(v.to_string()));
+        }
+
+        if let Some(v) = text.strip_prefix("player_") {
+            return Some(WorldKey::Player(v.to_string()));
+        }
+
+        if let Some(Ok(v)) = text.strip_prefix("map_").map(|v| v.parse()) {
+            return Some(WorldKey::Map(v));
+        }
+
+        if let Some((id, part)) = text
+            .strip_prefix("VILLAGE_")
+            .and_then(|v| v.rsplit_once('_'))
+        {
+            return Some(WorldKey::Village {
+                id: id.to_string(),
+                part: part.to_string(),
+            });
+        }
+
+        if GLOBAL_KEYS.contains(&text) || GLOBAL_PREFIXES.iter().any(|v| text.starts_with(v)) {
+            return Some(WorldKey::Global(text.to_string()));
+        }
+
+        None
+    }
+
+    /// Name of the record without the chunk or dimension it belongs to.
+    pub fn short_name(&self) -> String {
+        match self {
+            WorldKey::Chunk {
+                tag,
+                subchunk: Some(y),
+                ..
+            } => format!("{tag} {y}"),
+            WorldKey::Chunk { tag, .. } => tag.to_string(),
+            WorldKey::ActorDigest { .. } => String::from("Actor Digest"),
+            WorldKey::Actor(id) => {
+                let hex: String = id.iter().map(|v| format!("{v:02x}")).collect();
+                format!("Actor {hex}")
+            }
+            WorldKey::LocalPlayer => String::from("Local Player"),
+            WorldKey::Player(v) => format!("Player {v}"),
+            WorldKey::ServerPlayer(v) => format!("Server Player {v}"),
+            WorldKey::Map(v) => format!("Map {v}"),
+            WorldKey::Village { id, part } => format!("Village {id} {part}"),
+            WorldKey::Global(v) => v.clone(),
+            WorldKey::Unknown => String::from("Unknown"),
+        }
+    }
+
+    /// Chunk the record belongs to.
+    pub fn chunk(&self) -> Option<(Dimension, i32, i32)> {
+        match self {
+            WorldKey::Chunk {
+                x, z, dimension, ..
+            }
+            | WorldKey::ActorDigest { x, z, dimension } => Some((*dimension, *x, *z)),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for WorldKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.chunk() {
+            Some((dimension, x, z)) => {
+                write!(f, "{dimension} Chunk {x}, {z} {}", self.short_name())
+            }
+            None => write!(f, "{}", self.short_name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(x: i32, z: i32, dimension: Option<i32>, tail: &[u8]) -> Vec<u8> {
+        let mut key = [x.to_le_bytes(), z.to_le_bytes()].concat();
+        if let Some(v) = dimension {
+            key.extend_from_slice(&v.to_le_bytes());
+        }
+        key.extend_from_slice(tail);
+        key
+    }
+
+    #[test]
+    fn chunk_records() {
+        assert_eq!(
+            WorldKey::parse(&chunk(-1, 2, None, &[44])),
+            WorldKey::Chunk {
+                x: -1,
+                z: 2,
+                dimension: Dimension::Overworld,
+                tag: ChunkTag::Version,
+                subchunk: None,
+            }
+        );
+
+        let subchunk = WorldKey::parse(&chunk(3, -4, Some(1), &[47, 0xfc]));
+
+        assert_eq!(subchunk.chunk(), Some((Dimension::Nether, 3, -4)));
+        assert_eq!(subchunk.to_string(), "Nether Chunk 3, -4 SubChunkPrefix -4");
+    }
+
+    #[test]
+    fn only_subchunks_have_a_vertical_index() {
+        assert_eq!(
+            WorldKey::parse(&chunk(0, 0, None, &[44, 0])),
+            WorldKey::Unknown
+        );
+        assert_eq!(
+            WorldKey::parse(&chunk(0, 0, None, &[47])),
+            WorldKey::Unknown
+        );
+        assert_eq!(WorldKey::parse(&chunk(0, 0, None, &[0])), WorldKey::Unknown);
+    }
+
+    #[test]
+    fn actor_keys() {
+        let digest = [b"digp".as_slice(), &chunk(5, 6, Some(2), &[])].concat();
+
+        assert_eq!(
+            WorldKey::parse(&digest),
+            WorldKey::ActorDigest {
+                x: 5,
+                z: 6,
+                dimension: Dimension::End,
+            }
+        );
+
+        let actor = WorldKey::parse(b"actorprefix\x00\x00\x00\x01\x00\x00\x00\xff");
+
+        assert_eq!(actor, WorldKey::Actor([0, 0, 0, 1, 0, 0, 0, 0xff]));
+        assert_eq!(actor.to_string(), "Actor 00000001000000ff");
+        assert_eq!(WorldKey::parse(b"actorprefix\x00"), WorldKey::Unknown);
+    }
+
+    #[test]
+    fn text_keys() {
+        let cases = [
+            (b"~local_player".as_slice(), WorldKey::LocalPlayer),
+            (
+                b"player_server_ab-12",
+                WorldKey::ServerPlayer(String::from("ab-12")),
+            ),
+            (b"player_ab-12", WorldKey::Player(String::from("ab-12"))),
+            (b"map_-42", WorldKey::Map(-42)),
+            (
+                b"VILLAGE_Overworld_1a-2b_POI",
+                WorldKey::Village {
+                    id: String::from("Overworld_1a-2b"),
+                    part: String::from("POI"),
+                },
+            ),
+            (b"scoreboard", WorldKey::Global(String::from("scoreboard"))),
+            (
+                b"structuretemplate_mystructure:house",
+                WorldKey::Global(String::from("structuretemplate_mystructure:house")),
+            ),
+            (b"map_x", WorldKey::Unknown),
+        ];
+
+        for (key, expected) in cases {
+            assert_eq!(WorldKey::parse(key), expected, "{}", escape_key(key));
+        }
+    }
+
+    #[test]
+    fn escapes_unprintable_bytes() {
+        assert_eq!(escape_key(b"a b\\\x00\xff~"), "a b\\x5c\\x00\\xff~");
+    }
+}
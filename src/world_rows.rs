@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use beditor::world_key;
+use beditor::world_key::{ChunkTag, Dimension, WorldKey};
+
+/// A branch of the tree the keys of a world are shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WorldGroup {
+    Dimension(Dimension),
+    /// The records of the chunk at the chunk coordinates
+    Chunk(Dimension, i32, i32),
+    Players,
+    Maps,
+    Villages,
+    Actors,
+    /// Records about the whole world
+    Global,
+    Unknown,
+}
+
+impl std::fmt::Display for WorldGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorldGroup::Dimension(v) => write!(f, "{v}"),
+            WorldGroup::Chunk(_, x, z) => write!(f, "Chunk {x}, {z}"),
+            WorldGroup::Players => write!(f, "Players"),
+            WorldGroup::Maps => write!(f, "Maps"),
+            WorldGroup::Villages => write!(f, "Villages"),
+            WorldGroup::Actors => write!(f, "Actors"),
+            WorldGroup::Global => write!(f, "World"),
+            WorldGroup::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// What a line of the key tree shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorldRowKind {
+    /// A branch with the number of keys below it
+    Group(WorldGroup, usize),
+    Key(Vec<u8>),
+}
+
+/// One line of the key tree as it is shown in the WorldView.
+#[derive(Debug, Clone)]
+pub struct WorldRow {
+    pub depth: usize,
+    pub label: String,
+    pub kind: WorldRowKind,
+}
+
+/// Branch a key is listed in, and the chunk below it for chunk records.
+fn groups(key: &WorldKey) -> (WorldGroup, Option<WorldGroup>) {
+    match key {
+        WorldKey::Chunk { .. } | WorldKey::ActorDigest { .. } => match key.chunk() {
+            Some((dimension, x, z)) => (
+                WorldGroup::Dimension(dimension),
+                Some(WorldGroup::Chunk(dimension, x, z)),
+            ),
+            None => (WorldGroup::Unknown, None),
+        },
+        WorldKey::LocalPlayer | WorldKey::Player(_) | WorldKey::ServerPlayer(_) => {
+            (WorldGroup::Players, None)
+        }
+        WorldKey::Map(_) => (WorldGroup::Maps, None),
+        WorldKey::Village { .. } => (WorldGroup::Villages, None),
+        WorldKey::Actor(_) => (WorldGroup::Actors, None),
+        WorldKey::Global(_) => (WorldGroup::Global, None),
+        WorldKey::Unknown => (WorldGroup::Unknown, None),
+    }
+}
+
+/// Order of the records of a chunk, subchunks from the bottom up.
+fn chunk_order(key: &WorldKey) -> (Option<ChunkTag>, Option<i8>) {
+    match key {
+        WorldKey::Chunk { tag, subchunk, .. } => (Some(*tag), *subchunk),
+        _ => (None, None),
+    }
+}
+
+/// Sorts the keys into the tree and flattens the branches `expanded` returns
+/// true for into the lines shown for them.
+pub fn flatten<'a>(
+    keys: impl Iterator<Item = &'a (Vec<u8>, WorldKey)>,
+    expanded: &dyn Fn(&WorldGroup) -> bool,
+) -> Vec<WorldRow> {
+    type Branch<'a> = BTreeMap<Option<WorldGroup>, Vec<&'a (Vec<u8>, WorldKey)>>;
+
+    let mut tree: BTreeMap<WorldGroup, Branch> = BTreeMap::new();
+
+    for entry in keys {
+        let (group, chunk) = groups(&entry.1);
+        tree.entry(group)
+            .or_default()
+            .entry(chunk)
+            .or_default()
+            .push(entry);
+    }
+
+    let mut rows = Vec::new();
+
+    let push_key = |rows: &mut Vec<WorldRow>, depth, (raw, key): &(Vec<u8>, WorldKey)| {
+        rows.push(WorldRow {
+            depth,
+            label: match key {
+                WorldKey::Unknown => world_key::escape_key(raw),
+                _ => key.short_name(),
+            },
+            kind: WorldRowKind::Key(raw.clone()),
+        })
+    };
+
+    for (group, branch) in tree.iter_mut() {
+        rows.push(WorldRow {
+            depth: 0,
+            label: group.to_string(),
+            kind: WorldRowKind::Group(*group, branch.values().map(|v| v.len()).sum()),
+        });
+
+        if !expanded(group) {
+            continue;
+        }
+
+        for (chunk, entries) in branch.iter_mut() {
+            let Some(chunk) = chunk else {
+                for entry in entries.iter() {
+                    push_key(&mut rows, 1, entry);
+                }
+
+                continue;
+            };
+
+            rows.push(WorldRow {
+                depth: 1,
+                label: chunk.to_string(),
+                kind: WorldRowKind::Group(*chunk, entries.len()),
+            });
+
+            if !expanded(chunk) {
+                continue;
+            }
+
+            entries.sort_by_key(|(_, v)| chunk_order(v));
+
+            for entry in entries.iter() {
+                push_key(&mut rows, 2, entry);
+            }
+        }
+    }
+
+    rows
+}
@@ -0,0 +1,611 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use beditor::backup;
+use beditor::document::NbtEndian;
+use beditor::leveldb::{LevelDb, WriteBatch};
+use beditor::nbt_decode;
+use beditor::subchunk::SubChunk;
+use beditor::world_key;
+use beditor::world_key::{ChunkTag, WorldKey};
+use iced::widget::scrollable::AbsoluteOffset;
+use iced::widget::{scrollable, Button, Column, Row, Scrollable, Space, Text, TextInput};
+use iced::{theme, Alignment, Command, Element, Font, Length, Padding};
+
+use crate::messages::BEditorMessage;
+use crate::nbt_view::{NbtView, ERROR_COLOR, INDENTATION, ROW_HEIGHT};
+use crate::start_view;
+use crate::view::BEditorView;
+use crate::world_rows;
+use crate::world_rows::{WorldGroup, WorldRow, WorldRowKind};
+
+const KEY_LIST_WIDTH: f32 = 360.0;
+const DEFAULT_VIEWPORT_HEIGHT: f32 = 1080.0;
+/// Bytes of a record that isn't Nbt shown as hex
+const PREVIEW_LENGTH: usize = 512;
+const HEX_LINE: usize = 16;
+
+/// What is shown next to the list of keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum WorldEntry {
+    LevelDat,
+    Record(Vec<u8>),
+}
+
+/// Change of what is shown that waits for the unsaved changes of the record
+/// to be discarded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PendingSwitch {
+    Open,
+    Entry(WorldEntry),
+    Root(usize),
+}
+
+/// Browses the level.dat and the records in the LevelDB of a world folder.
+pub struct WorldView {
+    path: String,
+    db: Result<LevelDb, String>,
+    /// Every key of the database with what it stands for
+    keys: Vec<(Vec<u8>, WorldKey)>,
+    filter: String,
+    /// Number of keys matching the filter
+    matches: usize,
+    /// Branches of the key tree whose children are shown
+    expanded: HashSet<WorldGroup>,
+    /// The key tree flattened into the lines that are shown
+    rows: Vec<WorldRow>,
+    scroll_offset: f32,
+    viewport_height: f32,
+    selected: Option<WorldEntry>,
+    /// Value of the selected record
+    data: Vec<u8>,
+    /// Where the root tags of the selected record are, or why it isn't Nbt.
+    /// For subchunks these are the palette entries of all layers.
+    roots: Result<Vec<Range<usize>>, String>,
+    /// The selected record decoded as block storage, if it is a subchunk
+    subchunk: Option<SubChunk>,
+    /// Root tag of the selected record that is shown
+    root: usize,
+    record: NbtView,
+    pending: Option<PendingSwitch>,
+}
+
+fn keys_id() -> scrollable::Id {
+    scrollable::Id::new("world-keys")
+}
+
+/// Renders the start of `data` as lines of hex and ASCII.
+fn hex_dump<'a>(data: &[u8]) -> Element<'a, BEditorMessage> {
+    let mut col = Column::new();
+
+    for (i, line) in data.chunks(HEX_LINE).enumerate() {
+        let hex: String = line.iter().map(|v| format!("{v:02x} ")).collect();
+
+        let ascii: String = line
+            .iter()
+            .map(|v| match v.is_ascii_graphic() || *v == b' ' {
+                true => *v as char,
+                false => '.',
+            })
+            .collect();
+
+        col = col.push(
+            Text::new(format!(
+                "{:08x}  {hex:<width$} |{ascii}|",
+                i * HEX_LINE,
+                width = HEX_LINE * 3
+            ))
+            .font(Font::MONOSPACE),
+        );
+    }
+
+    col.into()
+}
+
+impl WorldView {
+    /// Reads the world folder at the current path and shows its level.dat.
+    fn open(&mut self) {
+        self.db = LevelDb::open(&Path::new(&self.path).join("db"));
+
+        self.keys = match &self.db {
+            Ok(db) => db
+                .keys()
+                .map(|v| (v.to_vec(), WorldKey::parse(v)))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        self.filter.clear();
+        self.expanded.clear();
+        self.rebuild_rows();
+        self.select(WorldEntry::LevelDat);
+    }
+
+    /// Opens the world folder at `path`.
+    pub fn open_path(&mut self, path: &Path) {
+        self.path = path.to_string_lossy().to_string();
+        self.open();
+    }
+
+    /// Path of the world folder that is shown, if its database could be read.
+    pub fn loaded_path(&self) -> Option<PathBuf> {
+        self.db.as_ref().ok().map(|_| PathBuf::from(&self.path))
+    }
+
+    /// Sorts the keys matching the filter into the tree, every branch is
+    /// expanded while filtering.
+    fn rebuild_rows(&mut self) {
+        let filter = self.filter.to_lowercase();
+
+        let matches = |(raw, key): &&(Vec<u8>, WorldKey)| {
+            filter.is_empty()
+                || key.to_string().to_lowercase().contains(&filter)
+                || world_key::escape_key(raw).to_lowercase().contains(&filter)
+        };
+
+        self.matches = self.keys.iter().filter(matches).count();
+        self.rows = world_rows::flatten(self.keys.iter().filter(matches), &|v| {
+            !filter.is_empty() || self.expanded.contains(v)
+        });
+    }
+
+    fn select(&mut self, entry: WorldEntry) {
+        self.data.clear();
+        self.roots = Ok(Vec::new());
+        self.subchunk = None;
+        self.root = 0;
+        self.record = NbtView::new();
+        self.selected = Some(entry.clone());
+
+        match &entry {
+            WorldEntry::LevelDat => self
+                .record
+                .open_path(&Path::new(&self.path).join("level.dat")),
+            WorldEntry::Record(key) => {
+                let value = match &self.db {
+                    Ok(db) => db.get(key),
+                    Err(e) => Err(e.clone()),
+                };
+
+                match value {
+                    Ok(Some(v)) => {
+                        self.data = v;
+                        self.find_roots(key);
+                        self.show_root(0);
+                    }
+                    Ok(None) => self.roots = Err(String::from("The key doesn't exist")),
+                    Err(e) => self.roots = Err(e),
+                }
+            }
+        }
+    }
+
+    /// Finds the root tags in the value of the record `key`.
+    fn find_roots(&mut self, key: &[u8]) {
+        self.subchunk = None;
+
+        let WorldKey::Chunk {
+            tag: ChunkTag::SubChunkPrefix,
+            ..
+        } = WorldKey::parse(key)
+        else {
+            self.roots = nbt_decode::split_roots(&self.data, NbtEndian::Little)
+                .map_err(|e| format!("Not Nbt: {e}"));
+            return;
+        };
+
+        match SubChunk::parse(&self.data) {
+            Ok(v) => {
+                self.roots = Ok(v
+                    .layers
+                    .iter()
+                    .flat_map(|v| v.palette.iter().map(|v| v.range.clone()))
+                    .collect());
+                self.subchunk = Some(v);
+            }
+            Err(e) => self.roots = Err(e),
+        }
+    }
+
+    /// Makes `switch`, asking first if the shown record has unsaved changes.
+    fn switch(&mut self, switch: PendingSwitch) {
+        if self.record.is_dirty() {
+            self.pending = Some(switch);
+            return;
+        }
+
+        self.pending = None;
+
+        match switch {
+            PendingSwitch::Open => self.open(),
+            PendingSwitch::Entry(v) => self.select(v),
+            PendingSwitch::Root(v) => self.show_root(v),
+        }
+    }
+
+    /// Shows the root tag at `index` of the selected record.
+    fn show_root(&mut self, index: usize) {
+        let Some(WorldEntry::Record(key)) = &self.selected else {
+            return;
+        };
+
+        let Some(range) = self.roots.as_ref().ok().and_then(|v| v.get(index)) else {
+            return;
+        };
+
+        self.root = index;
+        self.record.open_record(
+            WorldKey::parse(key).to_string(),
+            self.data[range.clone()].to_vec(),
+        );
+    }
+
+    /// Writes the edited root tag back into the selected record.
+    fn save_record(&mut self) -> Result<String, String> {
+        let Some(WorldEntry::Record(key)) = self.selected.clone() else {
+            return Err(String::from("No record selected"));
+        };
+
+        let Some(range) = self.roots.as_ref().ok().and_then(|v| v.get(self.root)) else {
+            return Err(String::from("The record isn't Nbt"));
+        };
+
+        let tag = self.record.to_bytes()?;
+
+        // The other root tags of the record are kept as they are
+        let mut data = self.data[..range.start].to_vec();
+        data.extend_from_slice(&tag);
+        data.extend_from_slice(&self.data[range.end..]);
+
+        let db = self.db.as_mut().map_err(|e| e.clone())?;
+
+        // Locked before the backup so the game can't change the database in between
+        let lock = db.lock()?;
+
+        backup::snapshot_db(db.dir())?;
+
+        let mut batch = WriteBatch::new();
+        batch.put(key.clone(), data.clone());
+        db.write_locked(&batch, &lock)?;
+
+        let name = WorldKey::parse(&key);
+
+        self.data = data;
+        self.find_roots(&key);
+        self.record.saved_record(tag);
+
+        Ok(format!("Saved {name} to the world"))
+    }
+
+    /// Renders one line of the key tree.
+    fn row2element<'a>(&self, row: &'a WorldRow) -> Element<'a, BEditorMessage> {
+        let padding = Padding {
+            top: 0.0,
+            right: 0.0,
+            bottom: 0.0,
+            left: row.depth as f32 * INDENTATION,
+        };
+
+        let line = match &row.kind {
+            WorldRowKind::Group(group, count) => Row::new()
+                .push(
+                    Button::new(Text::new(match self.expanded.contains(group) {
+                        true => "v",
+                        false => ">",
+                    }))
+                    .on_press(BEditorMessage::WorldViewToggleGroup(*group)),
+                )
+                .push(Text::new(format!("{} ({count})", row.label))),
+            WorldRowKind::Key(key) => {
+                let style = match &self.selected {
+                    Some(WorldEntry::Record(v)) if v == key => theme::Button::Primary,
+                    _ => theme::Button::Text,
+                };
+
+                Row::new().push(
+                    Button::new(Text::new(row.label.clone()))
+                        .style(style)
+                        .on_press(BEditorMessage::WorldViewSelect(key.clone()))
+                        .width(Length::Fill),
+                )
+            }
+        };
+
+        line.padding(padding)
+            .spacing(4)
+            .height(Length::Fixed(ROW_HEIGHT))
+            .align_items(Alignment::Center)
+            .into()
+    }
+
+    /// Renders the keys inside the scrolled viewport, like the rows of [`NbtView`].
+    fn keys2element(&self) -> Element<'_, BEditorMessage> {
+        let first = ((self.scroll_offset / ROW_HEIGHT).floor() as usize).min(self.rows.len());
+        let count = (self.viewport_height / ROW_HEIGHT).ceil() as usize + 1;
+        let last = (first + count).min(self.rows.len());
+
+        let mut col =
+            Column::new().push(Space::with_height(Length::Fixed(first as f32 * ROW_HEIGHT)));
+
+        for row in self.rows[first..last].iter() {
+            col = col.push(self.row2element(row));
+        }
+
+        col.push(Space::with_height(Length::Fixed(
+            (self.rows.len() - last) as f32 * ROW_HEIGHT,
+        )))
+        .into()
+    }
+
+    /// Lists the layers of a subchunk with the palette entries to pick from.
+    fn subchunk2element(&self, subchunk: &SubChunk) -> Element<'_, BEditorMessage> {
+        let mut col = Column::new().push(Text::new(format!(
+            "SubChunk version {}{}, {} layers",
+            subchunk.version,
+            subchunk.y.map_or(String::new(), |v| format!(" at y {v}")),
+            subchunk.layer_count()
+        )));
+
+        // Palette entries are the roots of the record, counted across layers
+        let mut index = 0;
+
+        for (i, layer) in subchunk.layers.iter().enumerate() {
+            col = col.push(Text::new(format!(
+                "Layer {i}: {} bits per block, {} palette entries",
+                layer.bits,
+                layer.palette.len()
+            )));
+
+            for (entry, count) in layer.palette.iter().zip(layer.counts()) {
+                let style = match index == self.root {
+                    true => theme::Button::Primary,
+                    false => theme::Button::Text,
+                };
+
+                col = col.push(
+                    Button::new(Text::new(format!(
+                        "{} ({count} blocks)",
+                        entry.name().unwrap_or("Unnamed")
+                    )))
+                    .style(style)
+                    .on_press(BEditorMessage::WorldViewSelectRoot(index))
+                    .width(Length::Fill),
+                );
+
+                index += 1;
+            }
+        }
+
+        Scrollable::new(col.spacing(4))
+            .width(Length::Fixed(KEY_LIST_WIDTH))
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn record2element(&self) -> Element<'_, BEditorMessage> {
+        let key = match &self.selected {
+            None => return Text::new("Select a key to show its record").into(),
+            Some(WorldEntry::LevelDat) => return self.record.view(),
+            Some(WorldEntry::Record(v)) => v,
+        };
+
+        let mut col = Column::new().push(Text::new(format!(
+            "{} ({} bytes), key {}",
+            WorldKey::parse(key),
+            self.data.len(),
+            world_key::escape_key(key)
+        )));
+
+        match &self.roots {
+            Err(e) => col
+                .push(Text::new(e.clone()).style(ERROR_COLOR))
+                .push(Scrollable::new(hex_dump(
+                    &self.data[..self.data.len().min(PREVIEW_LENGTH)],
+                )))
+                .into(),
+            Ok(roots) if roots.is_empty() => col.push(Text::new("The record is empty")).into(),
+            Ok(roots) => {
+                if let Some(subchunk) = &self.subchunk {
+                    return col
+                        .push(
+                            Row::new()
+                                .push(self.subchunk2element(subchunk))
+                                .push(self.record.view())
+                                .spacing(16),
+                        )
+                        .into();
+                }
+
+                if roots.len() > 1 {
+                    let mut row = Row::new().push(Text::new(format!("{} tags:", roots.len())));
+
+                    for i in 0..roots.len() {
+                        let style = match i == self.root {
+                            true => theme::Button::Primary,
+                            false => theme::Button::Secondary,
+                        };
+
+                        row = row.push(
+                            Button::new(Text::new(format!("{}", i + 1)))
+                                .style(style)
+                                .on_press(BEditorMessage::WorldViewSelectRoot(i)),
+                        );
+                    }
+
+                    col = col.push(row.spacing(4).align_items(Alignment::Center));
+                }
+
+                col.push(self.record.view()).into()
+            }
+        }
+    }
+}
+
+impl BEditorView for WorldView {
+    fn new() -> Self {
+        Self {
+            path: String::new(),
+            db: Err(String::new()),
+            keys: Vec::new(),
+            filter: String::new(),
+            matches: 0,
+            expanded: HashSet::new(),
+            rows: Vec::new(),
+            scroll_offset: 0.0,
+            viewport_height: DEFAULT_VIEWPORT_HEIGHT,
+            selected: None,
+            data: Vec::new(),
+            roots: Ok(Vec::new()),
+            subchunk: None,
+            root: 0,
+            record: NbtView::new(),
+            pending: None,
+        }
+    }
+
+    fn update(&mut self, message: BEditorMessage) -> Command<BEditorMessage> {
+        match message {
+            BEditorMessage::WorldViewSetPath(v) => self.path = v,
+            BEditorMessage::WorldViewOpen => self.switch(PendingSwitch::Open),
+            BEditorMessage::WorldViewPickFolder => {
+                return Command::perform(
+                    start_view::pick_folder(),
+                    BEditorMessage::WorldViewFolderPicked,
+                );
+            }
+            BEditorMessage::WorldViewFolderPicked(Some(v)) => {
+                self.path = v.to_string_lossy().to_string();
+                self.switch(PendingSwitch::Open);
+            }
+            BEditorMessage::WorldViewFolderPicked(None) => {}
+            BEditorMessage::WorldViewFilter(v) => {
+                self.filter = v;
+                self.rebuild_rows();
+                self.scroll_offset = 0.0;
+
+                return scrollable::scroll_to(keys_id(), AbsoluteOffset { x: 0.0, y: 0.0 });
+            }
+            BEditorMessage::WorldViewToggleGroup(v) => {
+                if !self.expanded.remove(&v) {
+                    self.expanded.insert(v);
+                }
+                self.rebuild_rows();
+            }
+            BEditorMessage::WorldViewScrolled(offset, height) => {
+                self.scroll_offset = offset;
+                self.viewport_height = height;
+            }
+            BEditorMessage::WorldViewSelect(v) => {
+                self.switch(PendingSwitch::Entry(WorldEntry::Record(v)))
+            }
+            BEditorMessage::WorldViewSelectLevelDat => {
+                self.switch(PendingSwitch::Entry(WorldEntry::LevelDat))
+            }
+            BEditorMessage::WorldViewSelectRoot(v) => self.switch(PendingSwitch::Root(v)),
+            BEditorMessage::DiscardChanges => {
+                if let Some(v) = self.pending.take() {
+                    self.record = NbtView::new();
+                    self.switch(v);
+                }
+            }
+            BEditorMessage::KeepChanges => self.pending = None,
+            // level.dat is saved like any other file
+            BEditorMessage::NbtViewSave if self.selected != Some(WorldEntry::LevelDat) => {
+                let status = self.save_record();
+                self.record.set_status(status);
+            }
+            // Everything else is for the record that is shown
+            message => return self.record.update(message),
+        }
+
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, BEditorMessage> {
+        let keys = Column::new()
+            .push(
+                Row::new()
+                    .push(
+                        TextInput::new("World Folder", &self.path)
+                            .on_input(BEditorMessage::WorldViewSetPath)
+                            .on_submit(BEditorMessage::WorldViewOpen),
+                    )
+                    .push(Button::new(Text::new("Open")).on_press(BEditorMessage::WorldViewOpen))
+                    .push(
+                        Button::new(Text::new("Browse..."))
+                            .on_press(BEditorMessage::WorldViewPickFolder),
+                    ),
+            )
+            .push(
+                Button::new(Text::new("level.dat"))
+                    .style(match self.selected {
+                        Some(WorldEntry::LevelDat) => theme::Button::Primary,
+                        _ => theme::Button::Text,
+                    })
+                    .on_press(BEditorMessage::WorldViewSelectLevelDat)
+                    .width(Length::Fill),
+            )
+            .push(
+                TextInput::new("Filter keys", &self.filter)
+                    .on_input(BEditorMessage::WorldViewFilter),
+            )
+            .push(match &self.db {
+                Ok(db) => Text::new(format!("{} of {} keys", self.matches, db.len())),
+                Err(e) => Text::new(e.clone()).style(ERROR_COLOR),
+            })
+            .push(
+                Scrollable::new(self.keys2element())
+                    .id(keys_id())
+                    .on_scroll(|v| {
+                        BEditorMessage::WorldViewScrolled(v.absolute_offset().y, v.bounds().height)
+                    })
+                    .height(Length::Fill),
+            )
+            .width(Length::Fixed(KEY_LIST_WIDTH));
+
+        let mut col = Column::new();
+
+        if let Some(v) = &self.pending {
+            let target = match v {
+                PendingSwitch::Open => String::from("open the world"),
+                PendingSwitch::Entry(WorldEntry::LevelDat) => String::from("show level.dat"),
+                PendingSwitch::Entry(WorldEntry::Record(v)) => {
+                    format!("show {}", WorldKey::parse(v))
+                }
+                PendingSwitch::Root(v) => format!("show tag {}", v + 1),
+            };
+
+            col = col.push(
+                Row::new()
+                    .push(Text::new(format!(
+                        "The record has unsaved changes, discard them to {target}?"
+                    )))
+                    .push(
+                        Button::new(Text::new("Discard")).on_press(BEditorMessage::DiscardChanges),
+                    )
+                    .push(Button::new(Text::new("Cancel")).on_press(BEditorMessage::KeepChanges))
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+            );
+        }
+
+        Row::new()
+            .push(keys)
+            .push(col.push(self.record2element()))
+            .spacing(8)
+            .width(Length::Fill)
+            .into()
+    }
+
+    fn title(&self) -> String {
+        match Path::new(&self.path).file_name() {
+            Some(v) => v.to_string_lossy().to_string(),
+            None => String::from("World"),
+        }
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.record.is_dirty()
+    }
+}